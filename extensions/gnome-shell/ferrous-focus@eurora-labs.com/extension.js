@@ -0,0 +1,103 @@
+// Publishes the focused window on the session bus for the ferrous-focus GNOME backend.
+//
+// Bus name:  com.eurora_labs.FerrousFocus
+// Object:    /com/eurora_labs/FerrousFocus
+// Interface: com.eurora_labs.FerrousFocus1
+
+import Gio from 'gi://Gio';
+import GLib from 'gi://GLib';
+import Meta from 'gi://Meta';
+
+import {Extension} from 'resource:///org/gnome/shell/extensions/extension.js';
+
+const BUS_NAME = 'com.eurora_labs.FerrousFocus';
+const OBJECT_PATH = '/com/eurora_labs/FerrousFocus';
+const INTERFACE_XML = `
+<node>
+    <interface name="com.eurora_labs.FerrousFocus1">
+        <method name="GetFocusedWindow">
+            <arg type="a{sv}" direction="out" name="window"/>
+        </method>
+        <signal name="FocusChanged">
+            <arg type="a{sv}" name="window"/>
+        </signal>
+    </interface>
+</node>`;
+
+// Describe a window as an a{sv} dictionary; an empty dictionary means nothing is focused.
+function describeWindow(window) {
+    if (!window)
+        return {};
+
+    const properties = {
+        id: GLib.Variant.new_uint64(window.get_id()),
+        title: GLib.Variant.new_string(window.get_title() ?? ''),
+        wm_class: GLib.Variant.new_string(window.get_wm_class() ?? ''),
+        fullscreen: GLib.Variant.new_boolean(window.is_fullscreen()),
+        xwayland: GLib.Variant.new_boolean(
+            window.get_client_type() === Meta.WindowClientType.X11),
+    };
+
+    const pid = window.get_pid();
+    if (pid > 0)
+        properties.pid = GLib.Variant.new_uint32(pid);
+
+    const workspace = window.get_workspace();
+    if (workspace) {
+        properties.workspace = GLib.Variant.new_string(
+            Meta.prefs_get_workspace_name(workspace.index()));
+    }
+
+    return properties;
+}
+
+export default class FerrousFocusExtension extends Extension {
+    enable() {
+        this._window = null;
+        this._windowSignals = [];
+
+        this._dbus = Gio.DBusExportedObject.wrapJSObject(INTERFACE_XML, this);
+        this._dbus.export(Gio.DBus.session, OBJECT_PATH);
+        this._nameId = Gio.bus_own_name_on_connection(
+            Gio.DBus.session, BUS_NAME, Gio.BusNameOwnerFlags.NONE, null, null);
+
+        this._focusSignal = global.display.connect('notify::focus-window',
+            () => this._onFocusChanged());
+        this._onFocusChanged();
+    }
+
+    disable() {
+        global.display.disconnect(this._focusSignal);
+        this._watchWindow(null);
+
+        Gio.bus_unown_name(this._nameId);
+        this._dbus.unexport();
+        this._dbus = null;
+    }
+
+    GetFocusedWindow() {
+        return describeWindow(global.display.focus_window);
+    }
+
+    _onFocusChanged() {
+        this._watchWindow(global.display.focus_window);
+        this._emitFocusChanged();
+    }
+
+    // Follow title and fullscreen changes of the focused window only.
+    _watchWindow(window) {
+        for (const id of this._windowSignals)
+            this._window.disconnect(id);
+
+        this._window = window;
+        this._windowSignals = window ? [
+            window.connect('notify::title', () => this._emitFocusChanged()),
+            window.connect('notify::fullscreen', () => this._emitFocusChanged()),
+        ] : [];
+    }
+
+    _emitFocusChanged() {
+        this._dbus.emit_signal('FocusChanged',
+            new GLib.Variant('(a{sv})', [describeWindow(this._window)]));
+    }
+}
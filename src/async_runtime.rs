@@ -0,0 +1,95 @@
+use crate::{FerrousFocusError, FerrousFocusResult};
+use futures::future::BoxFuture;
+use std::{sync::Arc, thread, time::Duration};
+
+/// What async tracking needs from an executor: somewhere to run backends, which block on their
+/// display connections, and a timer to check stop signals.
+///
+/// [`ThreadRuntime`] works under any executor. The `tokio` and `smol` features add
+/// [`TokioRuntime`] and [`SmolRuntime`]; implement this trait to integrate with another one,
+/// e.g. a glib main loop, and pass it to
+/// [`FocusTracker::with_async_runtime`](crate::FocusTracker::with_async_runtime).
+pub trait AsyncRuntime: Send + Sync {
+    /// Run `task` where it may block for as long as tracking lasts.
+    ///
+    /// # Errors
+    /// Returns an error if the task cannot be started, e.g. because no thread can be spawned;
+    /// async tracking then fails with it.
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send + 'static>) -> FerrousFocusResult<()>;
+
+    /// A future that completes after `duration`.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Runs backends on plain threads and times with a shared timer thread; needs no executor.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ThreadRuntime;
+
+impl AsyncRuntime for ThreadRuntime {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send + 'static>) -> FerrousFocusResult<()> {
+        thread::Builder::new()
+            .name("ferrous-focus".to_string())
+            .spawn(task)
+            .map(drop)
+            .map_err(|e| FerrousFocusError::new(format!("Failed to spawn tracker thread: {e}")))
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(futures_timer::Delay::new(duration))
+    }
+}
+
+/// Runs backends on tokio's blocking pool and times with tokio's timer - requires the "tokio"
+/// feature
+///
+/// Must be used from within a tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokioRuntime;
+
+#[cfg(feature = "tokio")]
+impl AsyncRuntime for TokioRuntime {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send + 'static>) -> FerrousFocusResult<()> {
+        tokio::task::spawn_blocking(task);
+        Ok(())
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Runs backends on smol's blocking pool and times with smol's timer - requires the "smol"
+/// feature
+#[cfg(feature = "smol")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SmolRuntime;
+
+#[cfg(feature = "smol")]
+impl AsyncRuntime for SmolRuntime {
+    fn spawn_blocking(&self, task: Box<dyn FnOnce() + Send + 'static>) -> FerrousFocusResult<()> {
+        smol::unblock(task).detach();
+        Ok(())
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async move {
+            smol::Timer::after(duration).await;
+        })
+    }
+}
+
+/// The runtime to use when none is configured: tokio when called from within a tokio runtime
+/// and the feature is enabled, else smol when enabled, else plain threads.
+pub(crate) fn default_runtime() -> Arc<dyn AsyncRuntime> {
+    #[cfg(feature = "tokio")]
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Arc::new(TokioRuntime);
+    }
+
+    #[cfg(feature = "smol")]
+    return Arc::new(SmolRuntime);
+
+    #[allow(unreachable_code)]
+    Arc::new(ThreadRuntime)
+}
@@ -0,0 +1,219 @@
+use crate::{FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, platform};
+use std::{env::var_os, fmt, str::FromStr};
+
+/// Environment variable with a comma separated backend priority list (e.g. `kde,xwayland`),
+/// used when the configuration does not name any backend.
+pub const BACKEND_ENV_VAR: &str = "FERROUS_FOCUS_BACKEND";
+
+/// A source of focus events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Backend {
+    /// Any EWMH compliant X11 window manager, through `_NET_ACTIVE_WINDOW`.
+    X11,
+    /// X11 clients of XWayland inside a Wayland session.
+    XWayland,
+    /// sway or i3, through the i3 IPC socket.
+    I3Ipc,
+    /// Hyprland, through its IPC sockets.
+    Hyprland,
+    /// GNOME on Wayland, through the companion GNOME Shell extension.
+    Gnome,
+    /// KDE Plasma on Wayland, through a KWin script.
+    Kde,
+    /// Windows, through the Win32 API.
+    Windows,
+    /// macOS, through Cocoa and the accessibility API.
+    MacOs,
+}
+
+impl Backend {
+    /// Every backend, on any platform.
+    pub const ALL: [Backend; 8] = [
+        Backend::X11,
+        Backend::XWayland,
+        Backend::I3Ipc,
+        Backend::Hyprland,
+        Backend::Gnome,
+        Backend::Kde,
+        Backend::Windows,
+        Backend::MacOs,
+    ];
+
+    /// Short name, as accepted by [`Backend::from_str`] and `FERROUS_FOCUS_BACKEND`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::X11 => "x11",
+            Backend::XWayland => "xwayland",
+            Backend::I3Ipc => "i3ipc",
+            Backend::Hyprland => "hyprland",
+            Backend::Gnome => "gnome",
+            Backend::Kde => "kde",
+            Backend::Windows => "windows",
+            Backend::MacOs => "macos",
+        }
+    }
+
+    /// What the backend can report when it runs.
+    pub fn capabilities(self) -> Capabilities {
+        let basic = Capabilities {
+            titles: true,
+            pids: true,
+            ..Capabilities::default()
+        };
+
+        match self {
+            Backend::X11 | Backend::XWayland | Backend::Windows | Backend::MacOs => Capabilities {
+                icons: true,
+                ..basic
+            },
+            // Icons only for X11 windows, which is all of them on i3
+            Backend::I3Ipc => Capabilities {
+                icons: true,
+                workspaces: true,
+                ..basic
+            },
+            Backend::Hyprland | Backend::Gnome | Backend::Kde => Capabilities {
+                workspaces: true,
+                ..basic
+            },
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = FerrousFocusError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "x11" | "xorg" => Ok(Backend::X11),
+            "xwayland" => Ok(Backend::XWayland),
+            "i3ipc" | "sway" | "i3" => Ok(Backend::I3Ipc),
+            "hyprland" => Ok(Backend::Hyprland),
+            "gnome" => Ok(Backend::Gnome),
+            "kde" | "kwin" => Ok(Backend::Kde),
+            "windows" => Ok(Backend::Windows),
+            "macos" => Ok(Backend::MacOs),
+            _ => Err(FerrousFocusError::new(format!("Unknown backend `{s}`"))),
+        }
+    }
+}
+
+/// Information a backend can report about the focused window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Window titles.
+    pub titles: bool,
+    /// Process IDs.
+    pub pids: bool,
+    /// Window icons.
+    pub icons: bool,
+    /// Window position and size.
+    pub geometry: bool,
+    /// Workspace of the window.
+    pub workspaces: bool,
+    /// User idle state.
+    pub idle: bool,
+}
+
+/// Result of probing the environment for one backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackendProbe {
+    /// The probed backend.
+    pub backend: Backend,
+    /// What the backend can report.
+    pub capabilities: Capabilities,
+    /// Why the backend cannot run here, or `None` if it can.
+    pub unavailable_reason: Option<String>,
+}
+
+impl BackendProbe {
+    /// Probe a single backend.
+    pub fn probe(backend: Backend) -> Self {
+        Self {
+            backend,
+            capabilities: backend.capabilities(),
+            unavailable_reason: platform::impl_focus_tracker::probe(backend).err(),
+        }
+    }
+
+    /// Check if the backend can run here.
+    pub fn is_available(&self) -> bool {
+        self.unavailable_reason.is_none()
+    }
+}
+
+/// Probe every backend of this platform, in the order they are preferred.
+pub fn available_backends() -> Vec<BackendProbe> {
+    platform::impl_focus_tracker::BACKENDS
+        .iter()
+        .map(|&backend| BackendProbe::probe(backend))
+        .collect()
+}
+
+/// Pick the backend to start: the first available one of the configured priority list, or of
+/// `FERROUS_FOCUS_BACKEND`, or else the one matching the session.
+pub(crate) fn select_backend(config: &FocusTrackerConfig) -> FerrousFocusResult<Backend> {
+    let requested = if !config.backends.is_empty() {
+        config.backends.clone()
+    } else if let Some(list) = var_os(BACKEND_ENV_VAR) {
+        list.to_string_lossy()
+            .split(',')
+            .map(str::parse)
+            .collect::<FerrousFocusResult<Vec<_>>>()?
+    } else {
+        return platform::impl_focus_tracker::detect();
+    };
+
+    let mut reasons = Vec::new();
+    for backend in requested {
+        match BackendProbe::probe(backend).unavailable_reason {
+            None => return Ok(backend),
+            Some(reason) => reasons.push(format!("{backend}: {reason}")),
+        }
+    }
+    Err(FerrousFocusError::BackendUnavailable(reasons.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backend_names_round_trip() {
+        for backend in Backend::ALL {
+            assert_eq!(backend.name().parse::<Backend>().unwrap(), backend);
+        }
+        assert_eq!("Sway".parse::<Backend>().unwrap(), Backend::I3Ipc);
+        assert!("weston".parse::<Backend>().is_err());
+    }
+
+    #[test]
+    fn test_available_backends_cover_platform() {
+        let probes = available_backends();
+        assert!(!probes.is_empty());
+        for probe in &probes {
+            assert_eq!(probe.capabilities, probe.backend.capabilities());
+        }
+    }
+
+    #[test]
+    fn test_foreign_backend_unavailable() {
+        let foreign = if cfg!(target_os = "windows") {
+            Backend::MacOs
+        } else {
+            Backend::Windows
+        };
+        let config = FocusTrackerConfig::new().with_backend(foreign);
+        assert!(matches!(
+            select_backend(&config),
+            Err(FerrousFocusError::BackendUnavailable(_))
+        ));
+    }
+}
@@ -1,14 +1,46 @@
-use std::time::Duration;
+use crate::{
+    Backend, ConfigError, FerrousFocusError, FerrousFocusResult, FocusFilter, FocusedWindow,
+};
+use std::{fmt, sync::Arc, time::Duration};
+use tracing::info;
+
+/// Default byte budget of the icon cache, enough for about 128 icons of 128x128 pixels
+const DEFAULT_ICON_CACHE_BYTES: usize = 8 * 1024 * 1024;
 
 /// Configuration for icon processing behavior
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct IconConfig {
     /// Target size for icons (width and height will be equal)
     /// Default: None (use platform default size)
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "crate::config_file::icon_size")
+    )]
     pub size: Option<u32>,
 
     /// The algorithm to use for icon scaling, supports Windows and Linux x11
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "crate::config_file::filter_type")
+    )]
     pub filter_type: image::imageops::FilterType,
+
+    /// When icons are fetched
+    /// Default: IconMode::Eager (with every focus change)
+    pub mode: IconMode,
+
+    /// How many bytes of icons are kept in the process-wide cache shared by all trackers, so
+    /// switching back to an app reuses its icon; the least recently used icons are dropped first.
+    /// The budget is not per tracker: a tracker trims the whole cache to its own budget whenever
+    /// it adds an icon, so with differing budgets the cache shrinks to that of the tracker that
+    /// added an icon last
+    /// Default: 8 MiB (zero disables the cache for this tracker)
+    pub cache_bytes: usize,
 }
 
 impl Default for IconConfig {
@@ -16,16 +48,67 @@ impl Default for IconConfig {
         Self {
             size: None,
             filter_type: image::imageops::FilterType::Lanczos3,
+            mode: IconMode::default(),
+            cache_bytes: DEFAULT_ICON_CACHE_BYTES,
         }
     }
 }
 
+/// When the icon of a focused window is fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum IconMode {
+    /// Fetch the icon when focus changes, and report it in
+    /// [`FocusedWindow::icon`](crate::FocusedWindow::icon)
+    #[default]
+    Eager,
+    /// Report an [`IconHandle`](crate::IconHandle) in
+    /// [`FocusedWindow::icon_handle`](crate::FocusedWindow::icon_handle) instead, which fetches
+    /// the icon only when asked to
+    Lazy,
+    /// Do no icon work at all
+    Disabled,
+}
+
 impl IconConfig {
     /// Create a new icon configuration with default settings
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Create an icon configuration that fetches icons only when asked to, through
+    /// [`FocusedWindow::icon_handle`](crate::FocusedWindow::icon_handle)
+    pub fn lazy() -> Self {
+        Self::default().with_mode(IconMode::Lazy)
+    }
+
+    /// Create an icon configuration that skips all icon work
+    pub fn disabled() -> Self {
+        Self::default().with_mode(IconMode::Disabled)
+    }
+
+    /// Set when icons are fetched
+    ///
+    /// # Arguments
+    /// * `mode` - Fetch icons with every focus change, only when asked to, or never
+    pub fn with_mode(mut self, mode: IconMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Set how many bytes of icons are kept in the process-wide icon cache
+    ///
+    /// # Arguments
+    /// * `bytes` - The cache budget, zero to fetch every icon anew
+    pub fn with_cache_bytes(mut self, bytes: usize) -> Self {
+        self.cache_bytes = bytes;
+        self
+    }
+
     /// Set the icon size (width and height will be equal)
     ///
     /// # Arguments
@@ -33,10 +116,21 @@ impl IconConfig {
     ///
     /// # Panics
     /// Panics if the size is zero or too large (> 512)
-    pub fn with_size(mut self, size: u32) -> Self {
-        self.validate_size(size);
+    pub fn with_size(self, size: u32) -> Self {
+        self.try_with_size(size).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Set the icon size (width and height will be equal), failing on invalid input
+    ///
+    /// # Arguments
+    /// * `size` - The icon size in pixels
+    ///
+    /// # Errors
+    /// Returns an error if the size is zero or too large (> 512)
+    pub fn try_with_size(mut self, size: u32) -> Result<Self, ConfigError> {
+        validate_size(size)?;
         self.size = Some(size);
-        self
+        Ok(self)
     }
 
     /// Get the icon size, using a default if none is configured
@@ -44,26 +138,125 @@ impl IconConfig {
         self.size.unwrap_or(128) // Default to 128x128
     }
 
-    /// Validate the icon size
-    fn validate_size(&self, size: u32) {
-        if size == 0 {
-            panic!("Icon size cannot be zero");
+    /// Check every field, e.g. of a configuration built by field assignment
+    ///
+    /// # Errors
+    /// Returns the first invalid value
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.size.map_or(Ok(()), validate_size)
+    }
+}
+
+/// What the tracker does when a handler returns an error
+#[derive(Clone, Default)]
+pub enum ErrorPolicy {
+    /// Log the error and keep tracking
+    #[default]
+    Continue,
+    /// Stop tracking and return the error from the tracking call, e.g.
+    /// [`FocusTracker::track_focus`](crate::FocusTracker::track_focus) or
+    /// [`TrackerHandle::join`](crate::TrackerHandle::join)
+    Stop,
+    /// Pass the error to the sink and keep tracking. Trackers running in the background, e.g. for
+    /// a [`Subscription`](crate::Subscription), also pass the error they end with.
+    Sink(Arc<dyn Fn(&FerrousFocusError) + Send + Sync>),
+}
+
+impl ErrorPolicy {
+    /// Pass handler errors to `sink` and keep tracking
+    pub fn sink<F>(sink: F) -> Self
+    where
+        F: Fn(&FerrousFocusError) + Send + Sync + 'static,
+    {
+        Self::Sink(Arc::new(sink))
+    }
+
+    /// Apply the policy to the result of a handler, returning an error only if tracking should
+    /// stop.
+    pub(crate) fn handle(&self, result: FerrousFocusResult<()>) -> FerrousFocusResult<()> {
+        let Err(e) = result else {
+            return Ok(());
+        };
+        match self {
+            ErrorPolicy::Continue => {
+                info!("Focus event handler failed: {}", e);
+                Ok(())
+            }
+            ErrorPolicy::Stop => Err(e),
+            ErrorPolicy::Sink(sink) => {
+                sink(&e);
+                Ok(())
+            }
+        }
+    }
+
+    /// Pass the error a background tracker ended with to the sink, if there is one.
+    pub(crate) fn report_exit(&self, result: &FerrousFocusResult<()>) {
+        if let (ErrorPolicy::Sink(sink), Err(e)) = (self, result) {
+            sink(e);
         }
-        if size > 512 {
-            panic!("Icon size cannot be greater than 512 pixels");
+    }
+}
+
+impl fmt::Debug for ErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorPolicy::Continue => f.write_str("Continue"),
+            ErrorPolicy::Stop => f.write_str("Stop"),
+            ErrorPolicy::Sink(_) => f.debug_tuple("Sink").finish_non_exhaustive(),
         }
     }
 }
 
 /// Configuration for focus tracking behavior
+///
+/// With the `serde` feature, it can be loaded from a file with [`FocusTrackerConfig::load`].
 #[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct FocusTrackerConfig {
     /// Polling interval for focus change detection
     /// Default: 100ms
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "poll_interval_ms",
+            deserialize_with = "crate::config_file::poll_interval_ms"
+        )
+    )]
     pub poll_interval: Duration,
     /// Icon processing configuration
     /// Default: IconConfig::default()
     pub icon: IconConfig,
+    /// Backends to try, in order of preference; the first available one is started
+    /// Default: empty (use `FERROUS_FOCUS_BACKEND`, or else the backend matching the session)
+    #[cfg_attr(
+        feature = "serde",
+        serde(deserialize_with = "crate::config_file::backends")
+    )]
+    pub backends: Vec<Backend>,
+    /// How long focus must rest on a window before the change is reported; focus changes
+    /// replaced within this time, e.g. while alt-tabbing, are suppressed
+    /// Default: zero (report every change right away)
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "debounce_ms",
+            deserialize_with = "crate::config_file::debounce_ms"
+        )
+    )]
+    pub debounce: Duration,
+    /// Which windows are reported; filtered windows get no icon
+    /// Default: FocusFilter::default() (every window)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub filter: FocusFilter,
+    /// What happens when a handler returns an error
+    /// Default: ErrorPolicy::Continue (log it and keep tracking)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub error_policy: ErrorPolicy,
 }
 
 impl Default for FocusTrackerConfig {
@@ -71,6 +264,10 @@ impl Default for FocusTrackerConfig {
         Self {
             poll_interval: Duration::from_millis(100),
             icon: IconConfig::default(),
+            backends: Vec::new(),
+            debounce: Duration::ZERO,
+            filter: FocusFilter::default(),
+            error_policy: ErrorPolicy::default(),
         }
     }
 }
@@ -97,9 +294,21 @@ impl FocusTrackerConfig {
     ///
     /// # Panics
     /// Panics if the size is zero or too large (> 512)
-    pub fn with_icon_size(mut self, size: u32) -> Self {
-        self.icon = self.icon.with_size(size);
-        self
+    pub fn with_icon_size(self, size: u32) -> Self {
+        self.try_with_icon_size(size)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Set the icon size (convenience method), failing on invalid input
+    ///
+    /// # Arguments
+    /// * `size` - The icon size in pixels
+    ///
+    /// # Errors
+    /// Returns an error if the size is zero or too large (> 512)
+    pub fn try_with_icon_size(mut self, size: u32) -> Result<Self, ConfigError> {
+        self.icon = self.icon.try_with_size(size)?;
+        Ok(self)
     }
 
     /// Set the polling interval for focus change detection
@@ -109,10 +318,22 @@ impl FocusTrackerConfig {
     ///
     /// # Panics
     /// Panics if the interval is zero or too large (> 10 seconds)
-    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
-        self.validate_poll_interval(interval);
+    pub fn with_poll_interval(self, interval: Duration) -> Self {
+        self.try_with_poll_interval(interval)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Set the polling interval for focus change detection, failing on invalid input
+    ///
+    /// # Arguments
+    /// * `interval` - The polling interval duration
+    ///
+    /// # Errors
+    /// Returns an error if the interval is zero or too large (> 10 seconds)
+    pub fn try_with_poll_interval(mut self, interval: Duration) -> Result<Self, ConfigError> {
+        validate_poll_interval(interval)?;
         self.poll_interval = interval;
-        self
+        Ok(self)
     }
 
     /// Set the polling interval in milliseconds
@@ -126,15 +347,177 @@ impl FocusTrackerConfig {
         self.with_poll_interval(Duration::from_millis(ms))
     }
 
-    /// Validate the polling interval
-    fn validate_poll_interval(&self, interval: Duration) {
-        if interval.is_zero() {
-            panic!("Poll interval cannot be zero");
-        }
-        if interval > Duration::from_secs(10) {
-            panic!("Poll interval cannot be greater than 10 seconds");
+    /// Set the polling interval in milliseconds, failing on invalid input
+    ///
+    /// # Arguments
+    /// * `ms` - The polling interval in milliseconds
+    ///
+    /// # Errors
+    /// Returns an error if the interval is zero or too large (> 10000ms)
+    pub fn try_with_poll_interval_ms(self, ms: u64) -> Result<Self, ConfigError> {
+        self.try_with_poll_interval(Duration::from_millis(ms))
+    }
+
+    /// Use only the given backend
+    ///
+    /// # Arguments
+    /// * `backend` - The backend to start
+    pub fn with_backend(self, backend: Backend) -> Self {
+        self.with_backend_priority([backend])
+    }
+
+    /// Try the given backends in order and start the first available one
+    ///
+    /// # Arguments
+    /// * `backends` - The backends, most preferred first
+    ///
+    /// # Panics
+    /// Panics if a backend is listed more than once
+    pub fn with_backend_priority(self, backends: impl IntoIterator<Item = Backend>) -> Self {
+        self.try_with_backend_priority(backends)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Try the given backends in order and start the first available one, failing on invalid
+    /// input
+    ///
+    /// # Arguments
+    /// * `backends` - The backends, most preferred first
+    ///
+    /// # Errors
+    /// Returns an error if a backend is listed more than once
+    pub fn try_with_backend_priority(
+        mut self,
+        backends: impl IntoIterator<Item = Backend>,
+    ) -> Result<Self, ConfigError> {
+        let backends = backends.into_iter().collect::<Vec<_>>();
+        validate_backends(&backends)?;
+        self.backends = backends;
+        Ok(self)
+    }
+
+    /// Report focus changes only once focus has rested for `debounce`
+    ///
+    /// # Arguments
+    /// * `debounce` - How long focus must rest on a window, zero to report every change
+    ///
+    /// # Panics
+    /// Panics if the duration is too large (> 10 seconds)
+    pub fn with_debounce(self, debounce: Duration) -> Self {
+        self.try_with_debounce(debounce)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Report focus changes only once focus has rested for `debounce`, failing on invalid input
+    ///
+    /// # Arguments
+    /// * `debounce` - How long focus must rest on a window, zero to report every change
+    ///
+    /// # Errors
+    /// Returns an error if the duration is too large (> 10 seconds)
+    pub fn try_with_debounce(mut self, debounce: Duration) -> Result<Self, ConfigError> {
+        validate_debounce(debounce)?;
+        self.debounce = debounce;
+        Ok(self)
+    }
+
+    /// Report focus changes only once focus has rested for `ms` milliseconds
+    ///
+    /// # Arguments
+    /// * `ms` - How long focus must rest on a window, in milliseconds
+    ///
+    /// # Panics
+    /// Panics if the duration is too large (> 10000ms)
+    pub fn with_debounce_ms(self, ms: u64) -> Self {
+        self.with_debounce(Duration::from_millis(ms))
+    }
+
+    /// Report focus changes only once focus has rested for `ms` milliseconds, failing on invalid
+    /// input
+    ///
+    /// # Arguments
+    /// * `ms` - How long focus must rest on a window, in milliseconds
+    ///
+    /// # Errors
+    /// Returns an error if the duration is too large (> 10000ms)
+    pub fn try_with_debounce_ms(self, ms: u64) -> Result<Self, ConfigError> {
+        self.try_with_debounce(Duration::from_millis(ms))
+    }
+
+    /// Report only the windows `filter` allows
+    ///
+    /// # Arguments
+    /// * `filter` - The rules deciding which windows are reported
+    pub fn with_filter(mut self, filter: FocusFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Decide what happens when a handler returns an error
+    ///
+    /// # Arguments
+    /// * `error_policy` - Whether to keep tracking, stop, or pass errors to a sink
+    pub fn with_error_policy(mut self, error_policy: ErrorPolicy) -> Self {
+        self.error_policy = error_policy;
+        self
+    }
+
+    /// Check every field, e.g. of a configuration built by field assignment. Trackers validate
+    /// their configuration before they start.
+    ///
+    /// # Errors
+    /// Returns the first invalid value
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        validate_poll_interval(self.poll_interval)?;
+        self.icon.validate()?;
+        validate_backends(&self.backends)?;
+        validate_debounce(self.debounce)
+    }
+
+    /// Check if the icon of `window` should be fetched, now or on demand.
+    pub(crate) fn wants_icon(&self, window: &FocusedWindow) -> bool {
+        self.icon.mode != IconMode::Disabled && self.filter.allows(window)
+    }
+}
+
+/// Validate the icon size
+pub(crate) fn validate_size(size: u32) -> Result<(), ConfigError> {
+    if size == 0 {
+        return Err(ConfigError::ZeroIconSize);
+    }
+    if size > 512 {
+        return Err(ConfigError::IconSizeTooLarge(size));
+    }
+    Ok(())
+}
+
+/// Validate the polling interval
+pub(crate) fn validate_poll_interval(interval: Duration) -> Result<(), ConfigError> {
+    if interval.is_zero() {
+        return Err(ConfigError::ZeroPollInterval);
+    }
+    if interval > Duration::from_secs(10) {
+        return Err(ConfigError::PollIntervalTooLong(interval));
+    }
+    Ok(())
+}
+
+/// Validate the backend priority list
+pub(crate) fn validate_backends(backends: &[Backend]) -> Result<(), ConfigError> {
+    for (i, backend) in backends.iter().enumerate() {
+        if backends[..i].contains(backend) {
+            return Err(ConfigError::DuplicateBackend(*backend));
         }
     }
+    Ok(())
+}
+
+/// Validate the debounce duration
+pub(crate) fn validate_debounce(debounce: Duration) -> Result<(), ConfigError> {
+    if debounce > Duration::from_secs(10) {
+        return Err(ConfigError::DebounceTooLong(debounce));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -165,6 +548,134 @@ mod tests {
         assert_eq!(config.icon.size, Some(64));
     }
 
+    #[test]
+    fn test_backend_priority_builder() {
+        let config = FocusTrackerConfig::new();
+        assert!(config.backends.is_empty());
+
+        let config = config.with_backend_priority([Backend::Kde, Backend::XWayland]);
+        assert_eq!(config.backends, [Backend::Kde, Backend::XWayland]);
+
+        let config = config.with_backend(Backend::X11);
+        assert_eq!(config.backends, [Backend::X11]);
+    }
+
+    #[test]
+    fn test_debounce_builder() {
+        assert_eq!(FocusTrackerConfig::new().debounce, Duration::ZERO);
+        let config = FocusTrackerConfig::new().with_debounce_ms(150);
+        assert_eq!(config.debounce, Duration::from_millis(150));
+    }
+
+    #[test]
+    #[should_panic(expected = "Debounce cannot be greater than 10 seconds")]
+    fn test_large_debounce_panics() {
+        FocusTrackerConfig::new().with_debounce(Duration::from_secs(11));
+    }
+
+    #[test]
+    fn test_error_policy() {
+        let failed = || Err(FerrousFocusError::new("Handler failed"));
+        assert!(ErrorPolicy::Continue.handle(failed()).is_ok());
+        assert!(ErrorPolicy::Stop.handle(failed()).is_err());
+        assert!(ErrorPolicy::Stop.handle(Ok(())).is_ok());
+
+        let errors = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = {
+            let errors = Arc::clone(&errors);
+            ErrorPolicy::sink(move |e| errors.lock().unwrap().push(e.to_string()))
+        };
+        assert!(sink.handle(failed()).is_ok());
+        sink.report_exit(&Ok(()));
+        sink.report_exit(&failed());
+        assert_eq!(
+            *errors.lock().unwrap(),
+            ["Handler failed", "Handler failed"]
+        );
+    }
+
+    #[test]
+    fn test_try_builders() {
+        let config = FocusTrackerConfig::new()
+            .try_with_poll_interval_ms(250)
+            .and_then(|c| c.try_with_icon_size(64))
+            .and_then(|c| c.try_with_debounce_ms(150))
+            .and_then(|c| c.try_with_backend_priority([Backend::Kde, Backend::X11]))
+            .unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(250));
+        assert_eq!(config.icon.size, Some(64));
+        assert_eq!(config.debounce, Duration::from_millis(150));
+
+        let config = FocusTrackerConfig::new();
+        assert_eq!(
+            config.clone().try_with_poll_interval_ms(0).unwrap_err(),
+            ConfigError::ZeroPollInterval
+        );
+        assert_eq!(
+            config.clone().try_with_icon_size(1024).unwrap_err(),
+            ConfigError::IconSizeTooLarge(1024)
+        );
+        assert_eq!(
+            config.clone().try_with_debounce_ms(11_000).unwrap_err(),
+            ConfigError::DebounceTooLong(Duration::from_secs(11))
+        );
+        assert_eq!(
+            config
+                .try_with_backend_priority([Backend::Kde, Backend::X11, Backend::Kde])
+                .unwrap_err(),
+            ConfigError::DuplicateBackend(Backend::Kde)
+        );
+        assert_eq!(
+            IconConfig::new().try_with_size(0).unwrap_err(),
+            ConfigError::ZeroIconSize
+        );
+    }
+
+    #[test]
+    fn test_validate_assigned_fields() {
+        assert_eq!(FocusTrackerConfig::default().validate(), Ok(()));
+
+        let config = FocusTrackerConfig {
+            poll_interval: Duration::ZERO,
+            ..Default::default()
+        };
+        assert_eq!(config.validate(), Err(ConfigError::ZeroPollInterval));
+
+        let mut config = FocusTrackerConfig::default();
+        config.icon.size = Some(0);
+        assert_eq!(config.validate(), Err(ConfigError::ZeroIconSize));
+
+        let config = FocusTrackerConfig {
+            debounce: Duration::from_secs(60),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::DebounceTooLong(Duration::from_secs(60)))
+        );
+
+        let config = FocusTrackerConfig {
+            backends: vec![Backend::X11, Backend::X11],
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ConfigError::DuplicateBackend(Backend::X11))
+        );
+    }
+
+    #[test]
+    fn test_icon_modes() {
+        assert_eq!(IconConfig::new().mode, IconMode::Eager);
+        assert_eq!(IconConfig::lazy().mode, IconMode::Lazy);
+        assert_eq!(IconConfig::disabled().mode, IconMode::Disabled);
+
+        let window = FocusedWindow::default();
+        assert!(FocusTrackerConfig::new().wants_icon(&window));
+        let config = FocusTrackerConfig::new().with_icon_config(IconConfig::disabled());
+        assert!(!config.wants_icon(&window));
+    }
+
     #[test]
     fn test_icon_config_default_size() {
         let icon_config = IconConfig::new();
@@ -0,0 +1,348 @@
+//! Loading [`FocusTrackerConfig`] from TOML or JSON files and `FERROUS_FOCUS_*` environment
+//! variables.
+
+use crate::{
+    Backend, ConfigError, FocusTrackerConfig,
+    backend::BACKEND_ENV_VAR,
+    config::{validate_backends, validate_debounce, validate_poll_interval, validate_size},
+};
+use image::imageops::FilterType;
+use serde::{Deserialize, Deserializer, de::Error as _};
+use serde_path_to_error::{Path as KeyPath, Segment};
+use std::{env, fs, path::Path, time::Duration};
+
+/// Environment variable overriding `poll_interval_ms`.
+const POLL_INTERVAL_ENV_VAR: &str = "FERROUS_FOCUS_POLL_INTERVAL_MS";
+/// Environment variable overriding `debounce_ms`.
+const DEBOUNCE_ENV_VAR: &str = "FERROUS_FOCUS_DEBOUNCE_MS";
+/// Environment variable overriding `icon.size`.
+const ICON_SIZE_ENV_VAR: &str = "FERROUS_FOCUS_ICON_SIZE";
+/// Environment variable overriding `icon.filter_type`.
+const ICON_FILTER_TYPE_ENV_VAR: &str = "FERROUS_FOCUS_ICON_FILTER_TYPE";
+
+impl FocusTrackerConfig {
+    /// Load the configuration from a `.toml` or `.json` file, then apply the `FERROUS_FOCUS_*`
+    /// environment variables on top.
+    ///
+    /// Keys missing from the file keep their default. `filter` and `error_policy` cannot be set
+    /// from a file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read, is neither TOML nor JSON, or has an invalid
+    /// value, or if an environment variable has an invalid value. The error names the offending
+    /// key or variable.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|e| ConfigError::Read {
+            path: path.to_path_buf(),
+            message: e.to_string(),
+        })?;
+        let config = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&contents)?,
+            Some("json") => Self::from_json(&contents)?,
+            _ => return Err(ConfigError::UnsupportedFormat(path.to_path_buf())),
+        };
+        config.try_with_env_overrides()
+    }
+
+    /// Parse the configuration from TOML, e.g.
+    ///
+    /// ```toml
+    /// poll_interval_ms = 50
+    /// debounce_ms = 150
+    /// backends = ["kde", "xwayland"]
+    ///
+    /// [icon]
+    /// size = 64
+    /// filter_type = "nearest"
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error naming the offending key if the TOML is malformed or has an invalid value
+    pub fn from_toml(toml: &str) -> Result<Self, ConfigError> {
+        let deserializer =
+            toml::Deserializer::parse(toml).map_err(|e| ConfigError::Parse(e.message().into()))?;
+        serde_path_to_error::deserialize(deserializer)
+            .map_err(|e| key_error(e.path(), e.inner().message()))
+    }
+
+    /// Parse the configuration from JSON, with the same keys as [`FocusTrackerConfig::from_toml`].
+    ///
+    /// # Errors
+    /// Returns an error naming the offending key if the JSON is malformed or has an invalid value
+    pub fn from_json(json: &str) -> Result<Self, ConfigError> {
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        serde_path_to_error::deserialize(&mut deserializer)
+            .map_err(|e| key_error(e.path(), &e.inner().to_string()))
+    }
+
+    /// Apply the `FERROUS_FOCUS_*` environment variables that are set, replacing the configured
+    /// values: `FERROUS_FOCUS_POLL_INTERVAL_MS`, `FERROUS_FOCUS_DEBOUNCE_MS`,
+    /// `FERROUS_FOCUS_ICON_SIZE`, `FERROUS_FOCUS_ICON_FILTER_TYPE` and `FERROUS_FOCUS_BACKEND`.
+    ///
+    /// # Errors
+    /// Returns an error naming the variable if one has an invalid value
+    pub fn try_with_env_overrides(self) -> Result<Self, ConfigError> {
+        self.try_with_overrides(|name| env::var(name).ok())
+    }
+
+    /// Apply the overrides `var` returns a value for.
+    fn try_with_overrides(
+        mut self,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ConfigError> {
+        let invalid = |name: &str, error: &dyn ToString| ConfigError::Invalid {
+            key: name.to_string(),
+            message: error.to_string(),
+        };
+        let millis = |name: &str, value: &str| {
+            value
+                .trim()
+                .parse()
+                .map(Duration::from_millis)
+                .map_err(|e| invalid(name, &e))
+        };
+
+        if let Some(value) = var(POLL_INTERVAL_ENV_VAR) {
+            let interval = millis(POLL_INTERVAL_ENV_VAR, &value)?;
+            self = self
+                .try_with_poll_interval(interval)
+                .map_err(|e| invalid(POLL_INTERVAL_ENV_VAR, &e))?;
+        }
+        if let Some(value) = var(DEBOUNCE_ENV_VAR) {
+            let debounce = millis(DEBOUNCE_ENV_VAR, &value)?;
+            self = self
+                .try_with_debounce(debounce)
+                .map_err(|e| invalid(DEBOUNCE_ENV_VAR, &e))?;
+        }
+        if let Some(value) = var(ICON_SIZE_ENV_VAR) {
+            let size = value
+                .trim()
+                .parse()
+                .map_err(|e| invalid(ICON_SIZE_ENV_VAR, &e))?;
+            self = self
+                .try_with_icon_size(size)
+                .map_err(|e| invalid(ICON_SIZE_ENV_VAR, &e))?;
+        }
+        if let Some(value) = var(ICON_FILTER_TYPE_ENV_VAR) {
+            self.icon.filter_type =
+                parse_filter_type(&value).map_err(|e| invalid(ICON_FILTER_TYPE_ENV_VAR, &e))?;
+        }
+        if let Some(value) = var(BACKEND_ENV_VAR) {
+            let backends = value
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<Backend>, _>>()
+                .map_err(|e| invalid(BACKEND_ENV_VAR, &e))?;
+            self = self
+                .try_with_backend_priority(backends)
+                .map_err(|e| invalid(BACKEND_ENV_VAR, &e))?;
+        }
+        Ok(self)
+    }
+}
+
+/// Turn a deserialization error into one naming the key it happened at, if known.
+fn key_error(path: &KeyPath, message: &str) -> ConfigError {
+    if path
+        .iter()
+        .all(|segment| matches!(segment, Segment::Unknown))
+    {
+        ConfigError::Parse(message.to_string())
+    } else {
+        ConfigError::Invalid {
+            key: path.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Parse the name of an icon scaling algorithm.
+fn parse_filter_type(name: &str) -> Result<FilterType, String> {
+    match name.trim().to_lowercase().as_str() {
+        "nearest" => Ok(FilterType::Nearest),
+        "triangle" => Ok(FilterType::Triangle),
+        "catmull_rom" | "catmullrom" => Ok(FilterType::CatmullRom),
+        "gaussian" => Ok(FilterType::Gaussian),
+        "lanczos3" => Ok(FilterType::Lanczos3),
+        _ => Err(format!(
+            "Unknown filter type `{name}`, expected nearest, triangle, catmull_rom, gaussian or \
+             lanczos3"
+        )),
+    }
+}
+
+/// Deserialize a value, then check it with `validate`.
+fn validated<'de, D, T>(
+    deserializer: D,
+    validate: impl FnOnce(&T) -> Result<(), ConfigError>,
+) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let value = T::deserialize(deserializer)?;
+    validate(&value).map_err(D::Error::custom)?;
+    Ok(value)
+}
+
+pub(crate) fn poll_interval_ms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    let interval = validated(deserializer, |&ms| {
+        validate_poll_interval(Duration::from_millis(ms))
+    })?;
+    Ok(Duration::from_millis(interval))
+}
+
+pub(crate) fn debounce_ms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    let debounce = validated(deserializer, |&ms| {
+        validate_debounce(Duration::from_millis(ms))
+    })?;
+    Ok(Duration::from_millis(debounce))
+}
+
+pub(crate) fn backends<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<Backend>, D::Error> {
+    validated(deserializer, |backends: &Vec<Backend>| {
+        validate_backends(backends)
+    })
+}
+
+pub(crate) fn icon_size<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    validated(deserializer, |size: &Option<u32>| {
+        size.map_or(Ok(()), validate_size)
+    })
+}
+
+pub(crate) fn filter_type<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<FilterType, D::Error> {
+    parse_filter_type(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+}
+
+impl<'de> Deserialize<'de> for Backend {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_from_toml() {
+        let config = FocusTrackerConfig::from_toml(
+            r#"
+            poll_interval_ms = 50
+            debounce_ms = 150
+            backends = ["kde", "xwayland"]
+
+            [icon]
+            size = 64
+            filter_type = "nearest"
+            mode = "lazy"
+            cache_bytes = 0
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(50));
+        assert_eq!(config.debounce, Duration::from_millis(150));
+        assert_eq!(config.backends, [Backend::Kde, Backend::XWayland]);
+        assert_eq!(config.icon.size, Some(64));
+        assert_eq!(config.icon.filter_type, FilterType::Nearest);
+        assert_eq!(config.icon.mode, crate::IconMode::Lazy);
+        assert_eq!(config.icon.cache_bytes, 0);
+
+        let config = FocusTrackerConfig::from_toml("").unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(100));
+        assert_eq!(config.icon.filter_type, FilterType::Lanczos3);
+    }
+
+    #[test]
+    fn test_from_json() {
+        let config = FocusTrackerConfig::from_json(
+            r#"{"poll_interval_ms": 250, "icon": {"filter_type": "catmull_rom"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(250));
+        assert_eq!(config.icon.size, None);
+        assert_eq!(config.icon.filter_type, FilterType::CatmullRom);
+    }
+
+    #[test]
+    fn test_errors_name_offending_key() {
+        let key = |result: Result<FocusTrackerConfig, ConfigError>| {
+            result.unwrap_err().key().map(str::to_string)
+        };
+        assert_eq!(
+            key(FocusTrackerConfig::from_toml("poll_interval_ms = 0")),
+            Some("poll_interval_ms".into())
+        );
+        assert_eq!(
+            key(FocusTrackerConfig::from_toml("[icon]\nsize = 1024")),
+            Some("icon.size".into())
+        );
+        assert_eq!(
+            key(FocusTrackerConfig::from_json(
+                r#"{"icon": {"filter_type": "bicubic"}}"#
+            )),
+            Some("icon.filter_type".into())
+        );
+        assert_eq!(
+            key(FocusTrackerConfig::from_json(
+                r#"{"backends": ["kde", "kde"]}"#
+            )),
+            Some("backends".into())
+        );
+        assert_eq!(
+            key(FocusTrackerConfig::from_toml("debounce_ms = \"soon\"")),
+            Some("debounce_ms".into())
+        );
+        assert_eq!(
+            key(FocusTrackerConfig::from_toml("poll_interval = 50")),
+            Some("poll_interval".into())
+        );
+        assert!(matches!(
+            FocusTrackerConfig::from_json("{"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_overrides_replace_file_values() {
+        let vars = HashMap::from([
+            (POLL_INTERVAL_ENV_VAR, "20"),
+            (ICON_FILTER_TYPE_ENV_VAR, "Gaussian"),
+            (BACKEND_ENV_VAR, "x11,xwayland"),
+        ]);
+        let config = FocusTrackerConfig::from_toml("poll_interval_ms = 50\nbackends = [\"kde\"]")
+            .unwrap()
+            .try_with_overrides(|name| vars.get(name).map(|value| value.to_string()))
+            .unwrap();
+        assert_eq!(config.poll_interval, Duration::from_millis(20));
+        assert_eq!(config.icon.filter_type, FilterType::Gaussian);
+        assert_eq!(config.backends, [Backend::X11, Backend::XWayland]);
+        assert_eq!(config.debounce, Duration::ZERO);
+
+        for (name, value) in [
+            (ICON_SIZE_ENV_VAR, "0"),
+            (DEBOUNCE_ENV_VAR, "later"),
+            (BACKEND_ENV_VAR, "weston"),
+        ] {
+            let error = FocusTrackerConfig::new()
+                .try_with_overrides(|var| (var == name).then(|| value.to_string()))
+                .unwrap_err();
+            assert_eq!(error.key(), Some(name));
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::{
+    FerrousFocusResult, FocusedWindow,
+    focus_event::{Change, Detection},
+    stop_signal::StopSignal,
+};
+use std::{
+    panic,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Run a backend, passing its changes on to `on_change` once focus has rested for `debounce`,
+/// and dropping changes that repeat the window passed on last. Every change that is not passed
+/// on is counted in `suppressed`.
+///
+/// `run` receives the handler to report changes to; with a debounce it runs on a separate
+/// thread, so the handler can keep waiting for focus to rest while the backend blocks. When
+/// `on_change` fails, the backend is stopped through `stop_signal` and the error returned.
+pub(crate) fn run<R>(
+    debounce: Duration,
+    suppressed: &AtomicU64,
+    stop_signal: &StopSignal,
+    on_change: &mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+    run: R,
+) -> FerrousFocusResult<()>
+where
+    R: FnOnce(
+            &mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+        ) -> FerrousFocusResult<()>
+        + Send,
+{
+    let mut coalescer = Coalescer::new(suppressed);
+    if debounce.is_zero() {
+        return run(&mut |change, detection| coalescer.pass(change, detection, on_change));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        let backend = scope.spawn(move || {
+            run(&mut |change, detection| {
+                // The receiver is only gone once the backend was stopped, which then ends by
+                // itself
+                sender.send((change, detection)).or(Ok(()))
+            })
+        });
+
+        let mut failure = None;
+        let mut debouncer = Debouncer::new(debounce, suppressed);
+        loop {
+            let received = match debouncer.deadline() {
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let settled = match received {
+                Ok((change, detection)) => debouncer.push(change, detection),
+                Err(RecvTimeoutError::Timeout) => debouncer.settle(),
+                // The backend stopped; a change that did not rest is not reported
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+            if let Some((change, detection)) = settled
+                && let Err(e) = coalescer.pass(change, detection, on_change)
+            {
+                failure = Some(e);
+                stop_signal.stop();
+                break;
+            }
+        }
+        debouncer.discard();
+        drop(receiver);
+
+        let result = backend
+            .join()
+            .unwrap_or_else(|payload| panic::resume_unwind(payload));
+        match failure {
+            Some(e) => Err(e),
+            None => result,
+        }
+    })
+}
+
+/// Drops changes that report the same window as the change passed on before, e.g. title
+/// updates for a title that did not change.
+struct Coalescer<'a> {
+    last: Option<FocusedWindow>,
+    suppressed: &'a AtomicU64,
+}
+
+impl<'a> Coalescer<'a> {
+    fn new(suppressed: &'a AtomicU64) -> Self {
+        Self {
+            last: None,
+            suppressed,
+        }
+    }
+
+    fn pass(
+        &mut self,
+        change: Change,
+        detection: Detection,
+        on_change: &mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+    ) -> FerrousFocusResult<()> {
+        match &change {
+            Change::Focus(window) | Change::Update(window) | Change::Icon(window) => {
+                if self.last.as_ref() == Some(window) {
+                    self.suppressed.fetch_add(1, Ordering::Relaxed);
+                    return Ok(());
+                }
+                self.last = Some(window.clone());
+            }
+            Change::Cleared => self.last = None,
+            Change::Started(_) | Change::Stopped => {}
+        }
+        on_change(change, detection)
+    }
+}
+
+/// Holds back focus changes until no other focus change followed them for a while.
+struct Debouncer<'a> {
+    debounce: Duration,
+    /// The latest focus change, not yet rested.
+    pending: Option<(Change, Detection)>,
+    /// The window focused as far as reported changes go.
+    focused: Option<FocusedWindow>,
+    suppressed: &'a AtomicU64,
+}
+
+impl<'a> Debouncer<'a> {
+    fn new(debounce: Duration, suppressed: &'a AtomicU64) -> Self {
+        Self {
+            debounce,
+            pending: None,
+            focused: None,
+            suppressed,
+        }
+    }
+
+    /// When the pending change will have rested long enough.
+    fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .as_ref()
+            .map(|(_, detection)| detection.instant() + self.debounce)
+    }
+
+    /// Take a change from the backend, returning it if it can be reported right away.
+    fn push(&mut self, change: Change, detection: Detection) -> Option<(Change, Detection)> {
+        match change {
+            Change::Focus(_) | Change::Cleared => {
+                if self.pending.replace((change, detection)).is_some() {
+                    self.suppressed.fetch_add(1, Ordering::Relaxed);
+                }
+                None
+            }
+            Change::Update(ref window) | Change::Icon(ref window) => {
+                // Changes of a window that has not rested yet become part of its focus change;
+                // a window changing while focus is about to be cleared has focus after all.
+                // Passing them on would overtake the pending change.
+                if let Some((pending, _)) = &mut self.pending {
+                    *pending = Change::Focus(window.clone());
+                    self.suppressed.fetch_add(1, Ordering::Relaxed);
+                    return None;
+                }
+                self.focused = Some(window.clone());
+                Some((change, detection))
+            }
+            Change::Started(_) | Change::Stopped => Some((change, detection)),
+        }
+    }
+
+    /// Take the pending change once it has rested.
+    fn settle(&mut self) -> Option<(Change, Detection)> {
+        let (change, detection) = self.pending.take()?;
+        let change = match change {
+            // Focus that came back to the window reported last is no focus change
+            Change::Focus(window) => {
+                let change = Change::between(self.focused.as_ref(), window.clone());
+                self.focused = Some(window);
+                change
+            }
+            change => {
+                self.focused = None;
+                change
+            }
+        };
+        Some((change, detection))
+    }
+
+    /// Drop the pending change when tracking ends before it rested.
+    fn discard(&mut self) {
+        if self.pending.take().is_some() {
+            self.suppressed.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(title: &str) -> FocusedWindow {
+        FocusedWindow {
+            window_title: Some(title.to_string()),
+            window_class: Some(title.to_lowercase()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_debouncer_keeps_latest_focus() {
+        let suppressed = AtomicU64::new(0);
+        let mut debouncer = Debouncer::new(Duration::from_millis(50), &suppressed);
+        let detection = Detection::now();
+
+        assert!(debouncer.deadline().is_none());
+        assert!(
+            debouncer
+                .push(Change::Focus(window("Terminal")), detection)
+                .is_none()
+        );
+        assert!(
+            debouncer
+                .push(Change::Focus(window("Editor")), detection)
+                .is_none()
+        );
+        // A title change before focus rested is folded into the pending focus change
+        assert!(
+            debouncer
+                .push(Change::Update(window("Editor*")), detection)
+                .is_none()
+        );
+        assert_eq!(
+            debouncer.deadline(),
+            Some(detection.instant() + Duration::from_millis(50))
+        );
+
+        let Some((Change::Focus(focused), _)) = debouncer.settle() else {
+            panic!("focus change did not settle");
+        };
+        assert_eq!(focused, window("Editor*"));
+        assert_eq!(suppressed.load(Ordering::Relaxed), 2);
+
+        // Once focus rested, title changes pass straight through
+        assert!(matches!(
+            debouncer.push(Change::Update(window("Editor")), detection),
+            Some((Change::Update(_), _))
+        ));
+    }
+
+    #[test]
+    fn test_debouncer_keeps_updates_behind_pending_clear() {
+        let suppressed = AtomicU64::new(0);
+        let mut debouncer = Debouncer::new(Duration::from_millis(50), &suppressed);
+        let detection = Detection::now();
+
+        debouncer.push(Change::Focus(window("Editor")), detection);
+        assert!(matches!(debouncer.settle(), Some((Change::Focus(_), _))));
+
+        // The update is not reported before the pending clear, and ends up focused
+        let renamed = FocusedWindow {
+            window_title: Some("Editor*".to_string()),
+            ..window("Editor")
+        };
+        assert!(debouncer.push(Change::Cleared, detection).is_none());
+        assert!(
+            debouncer
+                .push(Change::Update(renamed.clone()), detection)
+                .is_none()
+        );
+        let Some((Change::Update(focused), _)) = debouncer.settle() else {
+            panic!("update did not settle as a change of the focused window");
+        };
+        assert_eq!(focused, renamed);
+        assert_eq!(suppressed.load(Ordering::Relaxed), 1);
+        assert!(debouncer.settle().is_none());
+    }
+
+    #[test]
+    fn test_coalescer_drops_repeated_windows() {
+        let suppressed = AtomicU64::new(0);
+        let mut coalescer = Coalescer::new(&suppressed);
+        let mut passed = Vec::new();
+        let mut on_change = |change: Change, _| {
+            passed.push(change);
+            Ok(())
+        };
+
+        for change in [
+            Change::Focus(window("Editor")),
+            Change::Update(window("Editor")),
+            Change::Cleared,
+            Change::Focus(window("Editor")),
+        ] {
+            coalescer
+                .pass(change, Detection::now(), &mut on_change)
+                .unwrap();
+        }
+        assert!(matches!(
+            passed[..],
+            [Change::Focus(_), Change::Cleared, Change::Focus(_)]
+        ));
+        assert_eq!(suppressed.load(Ordering::Relaxed), 1);
+    }
+}
@@ -1,7 +1,9 @@
-use std::sync::PoisonError;
+use std::{path::PathBuf, sync::PoisonError, time::Duration};
 
 use thiserror::Error;
 
+use crate::Backend;
+
 #[derive(Debug, Error)]
 pub enum FerrousFocusError {
     #[error("{0}")]
@@ -24,6 +26,20 @@ pub enum FerrousFocusError {
 
     #[error("Platform error: {0}")]
     Platform(String),
+
+    #[error(
+        "GNOME Shell extension `ferrous-focus@eurora-labs.com` is not installed or not enabled"
+    )]
+    GnomeExtensionUnavailable,
+
+    #[error("No requested focus backend is available ({0})")]
+    BackendUnavailable(String),
+
+    #[error("Invalid pattern {0}")]
+    InvalidPattern(String),
+
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ConfigError),
 }
 
 impl FerrousFocusError {
@@ -32,6 +48,52 @@ impl FerrousFocusError {
     }
 }
 
+/// An invalid [`FocusTrackerConfig`](crate::FocusTrackerConfig) or
+/// [`IconConfig`](crate::IconConfig) value
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    #[error("Icon size cannot be zero")]
+    ZeroIconSize,
+
+    #[error("Icon size cannot be greater than 512 pixels (got {0})")]
+    IconSizeTooLarge(u32),
+
+    #[error("Poll interval cannot be zero")]
+    ZeroPollInterval,
+
+    #[error("Poll interval cannot be greater than 10 seconds (got {0:?})")]
+    PollIntervalTooLong(Duration),
+
+    #[error("Debounce cannot be greater than 10 seconds (got {0:?})")]
+    DebounceTooLong(Duration),
+
+    #[error("Backend {0} is listed more than once")]
+    DuplicateBackend(Backend),
+
+    #[error("Cannot read {}: {message}", path.display())]
+    Read { path: PathBuf, message: String },
+
+    #[error("Unsupported configuration file {}, expected .toml or .json", .0.display())]
+    UnsupportedFormat(PathBuf),
+
+    #[error("Malformed configuration: {0}")]
+    Parse(String),
+
+    #[error("Invalid `{key}`: {message}")]
+    Invalid { key: String, message: String },
+}
+
+impl ConfigError {
+    /// The configuration key or environment variable with the invalid value, if known
+    pub fn key(&self) -> Option<&str> {
+        match self {
+            ConfigError::Invalid { key, .. } => Some(key),
+            _ => None,
+        }
+    }
+}
+
 pub type FerrousFocusResult<T> = Result<T, FerrousFocusError>;
 
 impl<T> From<PoisonError<T>> for FerrousFocusError {
@@ -0,0 +1,302 @@
+use crate::{
+    Capabilities, FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow,
+    focus_event::{Change, Detection},
+    stop_signal::{self, StopSignal, WakerGuard},
+};
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::{AsyncRuntime, ErrorPolicy};
+#[cfg(feature = "async")]
+use std::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "async")]
+use tracing::info;
+
+/// Focus changes the blocking backend may queue up for the async handler.
+#[cfg(feature = "async")]
+const ASYNC_CHANNEL_CAPACITY: usize = 16;
+
+/// How often async tracking checks the caller's stop signal.
+#[cfg(feature = "async")]
+const ASYNC_STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+/// A source of focus events that a [`FocusTracker`](crate::FocusTracker) can run.
+///
+/// The built-in platform backends are used by default; implement this trait to track focus
+/// somewhere else (a remote desktop, a VNC session, the windows of your own application) and
+/// pass it to [`FocusTracker::from_backend`](crate::FocusTracker::from_backend). Channels, async
+/// tracking and the configuration keep working on top of it.
+pub trait FocusBackend: Send + Sync {
+    /// Short name of the backend, used in logs.
+    fn name(&self) -> &str;
+
+    /// What the backend can report. Nothing by default.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Track focus until [`FocusEmitter::should_stop`] returns `true`, passing every change to
+    /// [`FocusEmitter::emit`].
+    ///
+    /// The backend should report the window focused at start right away, like the built-in
+    /// backends do. To stop promptly it should wait with [`FocusEmitter::sleep`], or unblock its
+    /// reads from [`FocusEmitter::on_stop`], and otherwise check the stop signal at least every
+    /// `config.poll_interval`.
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()>;
+
+    /// Get the focused window, or `None` if nothing has focus.
+    ///
+    /// By default the backend is run until it reports its first window, which relies on it
+    /// reporting the window focused at start right away; errors after that are ignored.
+    /// Backends that can look up the focused window directly, or find nothing focused, should
+    /// override this.
+    fn current(&self, config: &FocusTrackerConfig) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let stop_signal = StopSignal::new();
+        let mut first = None;
+        let mut on_change = |change: Change, _: Detection| -> FerrousFocusResult<()> {
+            first.get_or_insert(change);
+            stop_signal.stop();
+            Ok(())
+        };
+        let result = self.run(
+            &mut FocusEmitter::new(&mut on_change, Some(&stop_signal)),
+            config,
+        );
+        match first {
+            Some(Change::Focus(window) | Change::Update(window) | Change::Icon(window)) => {
+                Ok(Some(window))
+            }
+            Some(_) => Ok(None),
+            None => result.map(|()| None),
+        }
+    }
+}
+
+/// Hands the focus changes of a running [`FocusBackend`] to the tracker's handler.
+pub struct FocusEmitter<'a> {
+    on_change: &'a mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+    stop_signal: Option<&'a StopSignal<'a>>,
+    wakers: Vec<WakerGuard<'a>>,
+    /// The error the handler stopped tracking with.
+    failure: Option<FerrousFocusError>,
+}
+
+impl<'a> FocusEmitter<'a> {
+    pub(crate) fn new(
+        on_change: &'a mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+        stop_signal: Option<&'a StopSignal<'a>>,
+    ) -> Self {
+        Self {
+            on_change,
+            stop_signal,
+            wakers: Vec::new(),
+            failure: None,
+        }
+    }
+
+    /// The result of tracking once the backend returned `result`: the handler's error if it
+    /// stopped tracking.
+    pub(crate) fn finish(self, result: FerrousFocusResult<()>) -> FerrousFocusResult<()> {
+        match self.failure {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+
+    /// Report a newly focused window.
+    ///
+    /// When the handler fails, the tracker is stopped and its call returns the handler's error,
+    /// whatever the backend returns.
+    pub fn emit(&mut self, window: FocusedWindow) {
+        self.report(Change::Focus(window));
+    }
+
+    /// Report a change of the focused window, e.g. of its title.
+    pub fn emit_update(&mut self, window: FocusedWindow) {
+        self.report(Change::Update(window));
+    }
+
+    /// Report that the focused window changed its icon; `window` carries the new icon.
+    pub fn emit_icon(&mut self, window: FocusedWindow) {
+        self.report(Change::Icon(window));
+    }
+
+    /// Report that no window has focus anymore.
+    pub fn emit_cleared(&mut self) {
+        self.report(Change::Cleared);
+    }
+
+    fn report(&mut self, change: Change) {
+        if self.failure.is_some() {
+            return;
+        }
+        if let Err(e) = (self.on_change)(change, Detection::now()) {
+            self.failure = Some(e);
+            if let Some(stop_signal) = self.stop_signal {
+                stop_signal.stop();
+            }
+        }
+    }
+
+    /// Check if the tracker asked the backend to stop.
+    pub fn should_stop(&self) -> bool {
+        self.failure.is_some() || self.stop_signal.is_some_and(StopSignal::is_stopped)
+    }
+
+    /// Sleep for `duration`, waking up early when the tracker is stopped.
+    ///
+    /// Returns `true` if the backend should stop.
+    pub fn sleep(&self, duration: Duration) -> bool {
+        stop_signal::sleep(self.stop_signal, duration)
+    }
+
+    /// Run `waker` as soon as the tracker is stopped, e.g. to shut down a socket the backend is
+    /// blocked on. Runs it right away if the tracker is already stopped.
+    ///
+    /// The waker is dropped without running once [`FocusBackend::run`] returns.
+    pub fn on_stop(&mut self, waker: impl FnOnce() + Send + 'static) {
+        if let Some(stop_signal) = self.stop_signal {
+            self.wakers.push(stop_signal.on_stop(waker));
+        }
+    }
+}
+
+/// Stops the blocking backend once async tracking ends, also when its future is dropped.
+#[cfg(feature = "async")]
+struct StopOnDrop(std::sync::Arc<StopSignal<'static>>);
+
+#[cfg(feature = "async")]
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+/// Run a blocking backend on the runtime's blocking pool and feed its events to an async handler.
+///
+/// `run` receives the handler to call for every focus change and the stop signal it must obey.
+#[cfg(feature = "async")]
+pub(crate) async fn run_blocking_async<R, F, Fut>(
+    runtime: &dyn AsyncRuntime,
+    run: R,
+    mut on_focus: F,
+    stop_signal: Option<&AtomicBool>,
+    error_policy: &ErrorPolicy,
+) -> FerrousFocusResult<()>
+where
+    R: FnOnce(
+            &mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+            &StopSignal<'_>,
+        ) -> FerrousFocusResult<()>
+        + Send
+        + 'static,
+    F: FnMut(FocusedWindow) -> Fut,
+    Fut: std::future::Future<Output = FerrousFocusResult<()>>,
+{
+    use futures::{
+        SinkExt, StreamExt,
+        channel::{mpsc, oneshot},
+        executor::block_on,
+        future::{Either, select},
+    };
+    use std::sync::Arc;
+
+    // Create a channel for communicating focus events from the blocking thread to the async
+    // context; the backend waits while the handler is busy with earlier events
+    let (tx, mut rx) = mpsc::channel::<FocusedWindow>(ASYNC_CHANNEL_CAPACITY);
+    let (done_tx, done_rx) = oneshot::channel();
+
+    // Create an internal stop signal for the blocking task; it is raised when this future ends
+    // or is dropped, so the backend never outlives it
+    let internal_stop = StopOnDrop(Arc::new(StopSignal::new()));
+    let thread_stop = Arc::clone(&internal_stop.0);
+    let send_stop = Arc::clone(&internal_stop.0);
+
+    // Spawn a blocking task for the backend (X11 and the IPC sockets are inherently blocking)
+    runtime.spawn_blocking(Box::new(move || {
+        let mut tx = tx;
+        let result = run(
+            &mut move |change: Change, _: Detection| -> FerrousFocusResult<()> {
+                // Async handlers are told about focus and window changes only
+                let Some(window) = change.into_window() else {
+                    return Ok(());
+                };
+                // Send to async context via channel. It closes when the async side ends or its
+                // future is dropped; both stop the backend first, so only an unexpected close is
+                // an error
+                block_on(tx.send(window)).or_else(|_| {
+                    if send_stop.is_stopped() {
+                        Ok(())
+                    } else {
+                        Err(FerrousFocusError::new("Async task dropped"))
+                    }
+                })
+            },
+            &thread_stop,
+        );
+        let _ = done_tx.send(result);
+    }))?;
+
+    // Process focus events in async context
+    let mut failure = None;
+    loop {
+        let next = match stop_signal {
+            None => rx.next().await,
+            // Wake up periodically to check the external stop signal
+            Some(external_stop) => {
+                if external_stop.load(Ordering::Acquire) {
+                    info!("External stop signal detected");
+                    break;
+                }
+                let tick = runtime.sleep(ASYNC_STOP_CHECK_INTERVAL);
+                match select(rx.next(), tick).await {
+                    Either::Left((next, _)) => next,
+                    Either::Right(_) => continue,
+                }
+            }
+        };
+
+        match next {
+            Some(focused_window) => {
+                if let Err(e) = error_policy.handle(on_focus(focused_window).await) {
+                    failure = Some(e);
+                    break;
+                }
+            }
+            // Channel closed
+            None => break,
+        }
+    }
+
+    // Signal the blocking thread to stop
+    info!("Async task ending, signaling backend thread to stop");
+    drop(internal_stop);
+
+    // Drop the receiver to close the channel, which also releases a backend waiting to send
+    drop(rx);
+
+    // Wait for the blocking task to finish and get its result
+    let result = done_rx.await;
+    if let Some(e) = failure {
+        return Err(e);
+    }
+    match result {
+        Ok(Ok(())) => {
+            info!("Backend event loop completed successfully");
+            Ok(())
+        }
+        Ok(Err(e)) => {
+            info!("Backend event loop error: {}", e);
+            Err(e)
+        }
+        Err(_) => {
+            let err_msg = "Backend blocking task failed".to_string();
+            info!("{}", err_msg);
+            Err(FerrousFocusError::Platform(err_msg))
+        }
+    }
+}
@@ -0,0 +1,574 @@
+use crate::{FerrousFocusResult, FocusedWindow};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+/// A change of focus reported by a tracker.
+///
+/// With the `serde` feature, events can be serialized, tagged with their `type`, e.g.
+/// `"focus_changed"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "snake_case")
+)]
+#[non_exhaustive]
+pub enum FocusEvent {
+    /// A window gained focus.
+    FocusChanged {
+        /// The window focused before, `None` if nothing was.
+        previous: Option<FocusedWindow>,
+        /// The window focused now.
+        current: FocusedWindow,
+        /// When and in which order the change was seen.
+        meta: EventMeta,
+    },
+    /// The title of the focused window changed.
+    TitleChanged {
+        /// The window as reported before.
+        previous: FocusedWindow,
+        /// The window with its new title.
+        current: FocusedWindow,
+        /// When and in which order the change was seen.
+        meta: EventMeta,
+    },
+    /// The icon of the focused window changed.
+    IconChanged {
+        /// The window as reported before.
+        previous: FocusedWindow,
+        /// The window with its new icon.
+        current: FocusedWindow,
+        /// When and in which order the change was seen.
+        meta: EventMeta,
+    },
+    /// Something else reported about the focused window changed, e.g. its fullscreen state or
+    /// workspace.
+    WindowChanged {
+        /// The window as reported before.
+        previous: FocusedWindow,
+        /// The window as reported now.
+        current: FocusedWindow,
+        /// When and in which order the change was seen.
+        meta: EventMeta,
+    },
+    /// No window has focus anymore.
+    FocusCleared {
+        /// The window that lost focus.
+        previous: FocusedWindow,
+        /// When and in which order the change was seen.
+        meta: EventMeta,
+    },
+    /// The tracker started its backend; comes before any other event.
+    BackendStarted {
+        /// Name of the backend, e.g. `X11`.
+        backend: String,
+        /// When the backend started.
+        meta: EventMeta,
+    },
+    /// The backend stopped because tracking was stopped; comes last.
+    ///
+    /// A backend that fails ends tracking with its error instead.
+    BackendStopped {
+        /// When the backend stopped.
+        meta: EventMeta,
+    },
+}
+
+impl FocusEvent {
+    /// The window focused after this event, if the event is about one.
+    pub fn window(&self) -> Option<&FocusedWindow> {
+        match self {
+            Self::FocusChanged { current, .. }
+            | Self::TitleChanged { current, .. }
+            | Self::IconChanged { current, .. }
+            | Self::WindowChanged { current, .. } => Some(current),
+            Self::FocusCleared { .. }
+            | Self::BackendStarted { .. }
+            | Self::BackendStopped { .. } => None,
+        }
+    }
+
+    /// When and in which order the event was seen.
+    pub fn meta(&self) -> &EventMeta {
+        match self {
+            Self::FocusChanged { meta, .. }
+            | Self::TitleChanged { meta, .. }
+            | Self::IconChanged { meta, .. }
+            | Self::WindowChanged { meta, .. }
+            | Self::FocusCleared { meta, .. }
+            | Self::BackendStarted { meta, .. }
+            | Self::BackendStopped { meta } => meta,
+        }
+    }
+}
+
+/// Timing of a [`FocusEvent`], taken when the backend saw the change rather than when the
+/// event reached its handler.
+///
+/// `instant` is not serialized; a deserialized one is derived from `system_time`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedMeta", from = "SerializedMeta")
+)]
+#[non_exhaustive]
+pub struct EventMeta {
+    /// Monotonic time the change was seen, for measuring durations.
+    pub instant: Instant,
+    /// Wall-clock time the change was seen, for logging and storage.
+    pub system_time: SystemTime,
+    /// Timestamp of the X11 event that reported the change, in milliseconds of server time.
+    ///
+    /// Only set by the X11 backends, for changes reported by an X event.
+    pub server_time: Option<u32>,
+    /// Position of the event among all events of the tracker and its clones, starting at 0.
+    pub sequence: u64,
+    /// How long the window that lost focus had it, for [`FocusEvent::FocusChanged`] and
+    /// [`FocusEvent::FocusCleared`] events that end a focus seen by this tracker.
+    pub previous_focus_duration: Option<Duration>,
+}
+
+/// Serialized form of [`EventMeta`], which has no `instant`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedMeta {
+    system_time: SystemTime,
+    #[serde(default)]
+    server_time: Option<u32>,
+    sequence: u64,
+    #[serde(default)]
+    previous_focus_duration: Option<Duration>,
+}
+
+#[cfg(feature = "serde")]
+impl From<EventMeta> for SerializedMeta {
+    fn from(meta: EventMeta) -> Self {
+        Self {
+            system_time: meta.system_time,
+            server_time: meta.server_time,
+            sequence: meta.sequence,
+            previous_focus_duration: meta.previous_focus_duration,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<SerializedMeta> for EventMeta {
+    fn from(meta: SerializedMeta) -> Self {
+        // As long ago on the monotonic clock as the wall-clock time is
+        let age = SystemTime::now()
+            .duration_since(meta.system_time)
+            .unwrap_or_default();
+        let now = Instant::now();
+        Self {
+            instant: now.checked_sub(age).unwrap_or(now),
+            system_time: meta.system_time,
+            server_time: meta.server_time,
+            sequence: meta.sequence,
+            previous_focus_duration: meta.previous_focus_duration,
+        }
+    }
+}
+
+/// When a backend saw a change.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Detection {
+    instant: Instant,
+    system_time: SystemTime,
+    server_time: Option<u32>,
+}
+
+impl Detection {
+    pub(crate) fn now() -> Self {
+        Self {
+            instant: Instant::now(),
+            system_time: SystemTime::now(),
+            server_time: None,
+        }
+    }
+
+    /// Monotonic time the change was seen.
+    pub(crate) fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Record the server timestamp of the X event that reported the change.
+    pub(crate) fn with_server_time(mut self, server_time: u32) -> Self {
+        self.server_time = Some(server_time);
+        self
+    }
+}
+
+/// What a backend observed, before a tracker turns it into a [`FocusEvent`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Change {
+    /// The backend started.
+    Started(String),
+    /// A window gained focus.
+    Focus(FocusedWindow),
+    /// Something about the focused window changed, e.g. its title.
+    Update(FocusedWindow),
+    /// The icon of the focused window changed.
+    Icon(FocusedWindow),
+    /// No window has focus anymore.
+    Cleared,
+    /// The backend stopped because tracking was stopped.
+    Stopped,
+}
+
+impl Change {
+    /// The change from `previous` to `current` for backends that report the focused window
+    /// without saying what changed; without window IDs, the same process and class are taken to
+    /// be the same window.
+    pub(crate) fn between(previous: Option<&FocusedWindow>, current: FocusedWindow) -> Self {
+        let same_window =
+            previous.is_some_and(|previous| match (&previous.window_id, &current.window_id) {
+                (Some(previous), Some(current)) => previous == current,
+                _ => {
+                    previous.process_id == current.process_id
+                        && previous.window_class == current.window_class
+                }
+            });
+        if same_window {
+            Self::Update(current)
+        } else {
+            Self::Focus(current)
+        }
+    }
+
+    /// The window to pass to handlers of the [`FocusedWindow`] API, which are told about focus
+    /// and window changes only.
+    pub(crate) fn into_window(self) -> Option<FocusedWindow> {
+        match self {
+            Self::Focus(window) | Self::Update(window) => Some(window),
+            Self::Started(_) | Self::Icon(_) | Self::Cleared | Self::Stopped => None,
+        }
+    }
+}
+
+/// Adapt a handler of the [`FocusedWindow`] API to the changes reported by backends.
+pub(crate) fn window_handler<F>(
+    mut on_focus: F,
+) -> impl FnMut(Change, Detection) -> FerrousFocusResult<()>
+where
+    F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
+{
+    move |change, _| match change.into_window() {
+        Some(window) => on_focus(window),
+        None => Ok(()),
+    }
+}
+
+/// Adapt a handler of [`FocusEvent`]s to the changes reported by backends, numbering the events
+/// with `sequence`.
+pub(crate) fn event_handler<F>(
+    mut on_event: F,
+    sequence: Arc<AtomicU64>,
+) -> impl FnMut(Change, Detection) -> FerrousFocusResult<()>
+where
+    F: FnMut(FocusEvent) -> FerrousFocusResult<()>,
+{
+    let mut builder = EventBuilder::new(sequence);
+    move |change, detection| match builder.build(change, detection) {
+        Some(event) => on_event(event),
+        None => Ok(()),
+    }
+}
+
+/// Turns the changes observed by a backend into [`FocusEvent`]s, remembering the focused window.
+#[derive(Debug)]
+pub(crate) struct EventBuilder {
+    focused: Option<FocusedWindow>,
+    /// When the focused window gained focus.
+    focused_since: Option<Instant>,
+    sequence: Arc<AtomicU64>,
+}
+
+impl EventBuilder {
+    pub(crate) fn new(sequence: Arc<AtomicU64>) -> Self {
+        Self {
+            focused: None,
+            focused_since: None,
+            sequence,
+        }
+    }
+
+    /// The event for `change`, or `None` if nothing changed.
+    pub(crate) fn build(&mut self, change: Change, detection: Detection) -> Option<FocusEvent> {
+        match change {
+            Change::Started(backend) => Some(FocusEvent::BackendStarted {
+                backend,
+                meta: self.meta(detection, None),
+            }),
+            Change::Stopped => Some(FocusEvent::BackendStopped {
+                meta: self.meta(detection, None),
+            }),
+            Change::Focus(current) => {
+                let duration = self.end_focus(detection);
+                self.focused_since = Some(detection.instant);
+                Some(FocusEvent::FocusChanged {
+                    previous: self.focused.replace(current.clone()),
+                    current,
+                    meta: self.meta(detection, duration),
+                })
+            }
+            Change::Update(current) | Change::Icon(current) => {
+                let Some(previous) = self.focused.replace(current.clone()) else {
+                    self.focused_since = Some(detection.instant);
+                    return Some(FocusEvent::FocusChanged {
+                        previous: None,
+                        current,
+                        meta: self.meta(detection, None),
+                    });
+                };
+                if previous == current {
+                    return None;
+                }
+                let meta = self.meta(detection, None);
+                Some(if previous.window_title != current.window_title {
+                    FocusEvent::TitleChanged {
+                        previous,
+                        current,
+                        meta,
+                    }
+                } else if previous.icon != current.icon
+                    || previous.icon_handle != current.icon_handle
+                {
+                    FocusEvent::IconChanged {
+                        previous,
+                        current,
+                        meta,
+                    }
+                } else {
+                    FocusEvent::WindowChanged {
+                        previous,
+                        current,
+                        meta,
+                    }
+                })
+            }
+            Change::Cleared => {
+                let previous = self.focused.take()?;
+                let duration = self.end_focus(detection);
+                Some(FocusEvent::FocusCleared {
+                    previous,
+                    meta: self.meta(detection, duration),
+                })
+            }
+        }
+    }
+
+    /// How long the focused window had focus when it loses it at `detection`.
+    fn end_focus(&mut self, detection: Detection) -> Option<Duration> {
+        self.focused_since
+            .take()
+            .map(|since| detection.instant.saturating_duration_since(since))
+    }
+
+    fn meta(&self, detection: Detection, previous_focus_duration: Option<Duration>) -> EventMeta {
+        EventMeta {
+            instant: detection.instant,
+            system_time: detection.system_time,
+            server_time: detection.server_time,
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed),
+            previous_focus_duration,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::WindowId;
+
+    fn window(title: &str) -> FocusedWindow {
+        FocusedWindow {
+            process_id: Some(42),
+            window_title: Some(title.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// A detection `millis` after `start`.
+    fn at(start: Detection, millis: u64) -> Detection {
+        Detection {
+            instant: start.instant + Duration::from_millis(millis),
+            ..start
+        }
+    }
+
+    #[test]
+    fn test_builds_events_from_changes() {
+        let mut builder = EventBuilder::new(Arc::default());
+        let now = Detection::now();
+        let editor = window("Editor");
+        let renamed = window("Editor*");
+        let fullscreen = FocusedWindow {
+            fullscreen: Some(true),
+            ..renamed.clone()
+        };
+
+        assert_eq!(builder.build(Change::Cleared, now), None);
+        assert!(matches!(
+            builder.build(Change::Update(editor.clone()), now),
+            Some(FocusEvent::FocusChanged { previous: None, current, .. }) if current == editor
+        ));
+        assert_eq!(builder.build(Change::Update(editor.clone()), now), None);
+        assert!(matches!(
+            builder.build(Change::Update(renamed.clone()), now),
+            Some(FocusEvent::TitleChanged { previous, current, .. })
+                if previous == editor && current == renamed
+        ));
+        assert!(matches!(
+            builder.build(Change::Update(fullscreen.clone()), now),
+            Some(FocusEvent::WindowChanged { previous, current, .. })
+                if previous == renamed && current == fullscreen
+        ));
+        assert!(matches!(
+            builder.build(Change::Focus(editor.clone()), now),
+            Some(FocusEvent::FocusChanged { previous: Some(previous), current, .. })
+                if previous == fullscreen && current == editor
+        ));
+        assert!(matches!(
+            builder.build(Change::Cleared, now),
+            Some(FocusEvent::FocusCleared { previous, .. }) if previous == editor
+        ));
+        assert_eq!(builder.build(Change::Cleared, now), None);
+    }
+
+    #[test]
+    fn test_stamps_events() {
+        let sequence = Arc::new(AtomicU64::new(0));
+        let mut builder = EventBuilder::new(Arc::clone(&sequence));
+        let start = Detection::now().with_server_time(1000);
+
+        let started = builder.build(Change::Started("test".to_string()), start);
+        let meta = *started.unwrap().meta();
+        assert_eq!(meta.sequence, 0);
+        assert_eq!(meta.instant, start.instant);
+        assert_eq!(meta.system_time, start.system_time);
+        assert_eq!(meta.server_time, Some(1000));
+        assert_eq!(meta.previous_focus_duration, None);
+
+        let focused = builder.build(Change::Focus(window("Editor")), at(start, 10));
+        assert_eq!(focused.unwrap().meta().previous_focus_duration, None);
+        // Unchanged windows take no sequence number
+        assert_eq!(builder.build(Change::Update(window("Editor")), start), None);
+
+        let renamed = builder.build(Change::Update(window("Editor*")), at(start, 20));
+        let meta = *renamed.unwrap().meta();
+        assert_eq!(meta.sequence, 2);
+        assert_eq!(meta.previous_focus_duration, None);
+
+        let switched = builder.build(Change::Focus(window("Browser")), at(start, 250));
+        let meta = *switched.unwrap().meta();
+        assert_eq!(
+            meta.previous_focus_duration,
+            Some(Duration::from_millis(240))
+        );
+
+        let cleared = builder.build(Change::Cleared, at(start, 300));
+        let meta = *cleared.unwrap().meta();
+        assert_eq!(meta.sequence, 4);
+        assert_eq!(
+            meta.previous_focus_duration,
+            Some(Duration::from_millis(50))
+        );
+
+        // The sequence is shared with other builders of the tracker
+        let mut other = EventBuilder::new(Arc::clone(&sequence));
+        let stopped = other.build(Change::Stopped, at(start, 400));
+        assert_eq!(stopped.unwrap().meta().sequence, 5);
+    }
+
+    #[test]
+    fn test_window_ids_tell_windows_of_one_app_apart() {
+        let first = FocusedWindow {
+            window_id: Some(WindowId::from_number(1)),
+            ..window("Editor")
+        };
+        let second = FocusedWindow {
+            window_id: Some(WindowId::from_number(2)),
+            ..first.clone()
+        };
+        assert!(matches!(
+            Change::between(Some(&first), second),
+            Change::Focus(_)
+        ));
+        assert!(matches!(
+            Change::between(Some(&first), first.clone()),
+            Change::Update(_)
+        ));
+        assert!(matches!(
+            Change::between(Some(&window("Editor")), window("Editor*")),
+            Change::Update(_)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_events_round_trip() {
+        let mut builder = EventBuilder::new(Arc::default());
+        let start = Detection::now();
+        let editor = FocusedWindow {
+            window_id: Some(WindowId::from_number(0x3a00007)),
+            ..window("Editor")
+        };
+        let browser = FocusedWindow {
+            window_id: Some(WindowId::from_text("{4a1f}")),
+            icon: Some(Arc::new(image::RgbaImage::new(2, 2))),
+            ..window("Browser")
+        };
+        let events = [
+            builder.build(Change::Started("test".to_string()), start),
+            builder.build(Change::Focus(editor), at(start, 10)),
+            builder.build(Change::Focus(browser), at(start, 30)),
+            builder.build(Change::Cleared, at(start, 50)),
+            builder.build(Change::Stopped, at(start, 60)),
+        ]
+        .map(Option::unwrap);
+
+        let json = serde_json::to_string(&events).unwrap();
+        assert!(json.contains(r#""type":"focus_changed""#));
+        assert!(json.contains(r#""window_id":60817415"#));
+        let decoded: Vec<FocusEvent> = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.len(), events.len());
+        for (decoded, event) in decoded.iter().zip(&events) {
+            let (meta, decoded_meta) = (event.meta(), decoded.meta());
+            assert_eq!(decoded_meta.system_time, meta.system_time);
+            assert_eq!(decoded_meta.sequence, meta.sequence);
+            assert_eq!(
+                decoded_meta.previous_focus_duration,
+                meta.previous_focus_duration
+            );
+            assert!(decoded_meta.instant <= Instant::now());
+            assert_eq!(decoded.window(), event.window());
+        }
+        assert!(matches!(
+            &decoded[2],
+            FocusEvent::FocusChanged { previous: Some(previous), .. }
+                if previous.window_title.as_deref() == Some("Editor")
+        ));
+    }
+
+    #[test]
+    fn test_only_windows_reach_window_handlers() {
+        let editor = window("Editor");
+        assert_eq!(
+            Change::Focus(editor.clone()).into_window(),
+            Some(editor.clone())
+        );
+        assert_eq!(
+            Change::Update(editor.clone()).into_window(),
+            Some(editor.clone())
+        );
+        assert_eq!(Change::Icon(editor).into_window(), None);
+        assert_eq!(Change::Cleared.into_window(), None);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{FerrousFocusError, FerrousFocusResult, FocusedWindow, focus_event::Change};
+use regex::Regex;
+use std::{cell::OnceCell, ops::Not, path::PathBuf};
+
+/// Decides which windows the tracker reports, before their icons are fetched.
+///
+/// A window is reported when it matches the `include` rule (if any) and does not match the
+/// `exclude` rule (if any). Other windows are dropped, or reported as
+/// [`FocusedWindow::filtered`] when `filtered` is [`FilteredWindows::Report`].
+///
+/// ```
+/// use ferrous_focus::{FocusFilter, FocusTrackerConfig, Pattern, WindowRule};
+///
+/// let filter = FocusFilter::new()
+///     .exclude(WindowRule::any([
+///         WindowRule::process_name(Pattern::exact("keepassxc")),
+///         WindowRule::title(Pattern::glob("*Private Browsing*")),
+///     ]))
+///     .report_filtered();
+/// let config = FocusTrackerConfig::new().with_filter(filter);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct FocusFilter {
+    /// Only windows matching this rule are reported
+    /// Default: None (every window)
+    pub include: Option<WindowRule>,
+    /// Windows matching this rule are not reported
+    /// Default: None (no window)
+    pub exclude: Option<WindowRule>,
+    /// What happens to focus on a window that is not reported
+    /// Default: FilteredWindows::Drop
+    pub filtered: FilteredWindows,
+}
+
+/// What the tracker does when focus moves to a window its [`FocusFilter`] does not report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FilteredWindows {
+    /// Report nothing, as if focus had not moved.
+    #[default]
+    Drop,
+    /// Report [`FocusedWindow::filtered`], a window without any details.
+    Report,
+}
+
+impl FocusFilter {
+    /// Create a filter that reports every window
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Report only windows matching `rule`, replacing any previous include rule
+    pub fn include(mut self, rule: WindowRule) -> Self {
+        self.include = Some(rule);
+        self
+    }
+
+    /// Do not report windows matching `rule`, replacing any previous exclude rule
+    pub fn exclude(mut self, rule: WindowRule) -> Self {
+        self.exclude = Some(rule);
+        self
+    }
+
+    /// Report focus on filtered windows as [`FocusedWindow::filtered`] instead of dropping it
+    pub fn report_filtered(mut self) -> Self {
+        self.filtered = FilteredWindows::Report;
+        self
+    }
+
+    /// Check if the filter reports `window`.
+    ///
+    /// Custom backends can call this before doing expensive work, like fetching an icon, for
+    /// a window the tracker will not report anyway.
+    pub fn allows(&self, window: &FocusedWindow) -> bool {
+        if self.include.is_none() && self.exclude.is_none() {
+            return true;
+        }
+
+        let subject = Subject::new(window);
+        self.include
+            .as_ref()
+            .is_none_or(|rule| rule.matches(&subject))
+            && !self
+                .exclude
+                .as_ref()
+                .is_some_and(|rule| rule.matches(&subject))
+    }
+
+    /// Apply the filter to a window about to be reported.
+    pub(crate) fn apply(&self, window: FocusedWindow) -> Option<FocusedWindow> {
+        if window.filtered || self.allows(&window) {
+            return Some(window);
+        }
+        match self.filtered {
+            FilteredWindows::Drop => None,
+            FilteredWindows::Report => Some(FocusedWindow::filtered()),
+        }
+    }
+
+    /// Apply the filter to the window of a change about to be reported.
+    pub(crate) fn apply_change(&self, change: Change) -> Option<Change> {
+        match change {
+            Change::Focus(window) => self.apply(window).map(Change::Focus),
+            Change::Update(window) => self.apply(window).map(Change::Update),
+            Change::Icon(window) => self.apply(window).map(Change::Icon),
+            change => Some(change),
+        }
+    }
+}
+
+/// A condition on a window: a pattern matched against one of its details, or a combination
+/// of other rules.
+///
+/// Rules on a detail the backend did not report never match. Negate a rule with `!`.
+#[derive(Debug, Clone)]
+pub enum WindowRule {
+    /// The detail of the window matches the pattern.
+    Match(WindowField, Pattern),
+    /// Every rule matches; true when there are none.
+    All(Vec<WindowRule>),
+    /// At least one rule matches; false when there are none.
+    Any(Vec<WindowRule>),
+    /// The rule does not match.
+    Not(Box<WindowRule>),
+}
+
+/// A detail of a window that a [`WindowRule`] matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowField {
+    /// [`FocusedWindow::process_name`].
+    ProcessName,
+    /// Full path of the executable the window's process runs, looked up from its process ID.
+    ExecutablePath,
+    /// [`FocusedWindow::window_class`].
+    WindowClass,
+    /// [`FocusedWindow::app_id`].
+    AppId,
+    /// [`FocusedWindow::window_title`].
+    Title,
+}
+
+impl WindowRule {
+    /// Match the process name
+    pub fn process_name(pattern: Pattern) -> Self {
+        Self::Match(WindowField::ProcessName, pattern)
+    }
+
+    /// Match the full path of the executable
+    pub fn executable_path(pattern: Pattern) -> Self {
+        Self::Match(WindowField::ExecutablePath, pattern)
+    }
+
+    /// Match the window class
+    pub fn window_class(pattern: Pattern) -> Self {
+        Self::Match(WindowField::WindowClass, pattern)
+    }
+
+    /// Match the application ID
+    pub fn app_id(pattern: Pattern) -> Self {
+        Self::Match(WindowField::AppId, pattern)
+    }
+
+    /// Match the window title
+    pub fn title(pattern: Pattern) -> Self {
+        Self::Match(WindowField::Title, pattern)
+    }
+
+    /// Match when every one of `rules` matches
+    pub fn all(rules: impl IntoIterator<Item = WindowRule>) -> Self {
+        Self::All(rules.into_iter().collect())
+    }
+
+    /// Match when any of `rules` matches
+    pub fn any(rules: impl IntoIterator<Item = WindowRule>) -> Self {
+        Self::Any(rules.into_iter().collect())
+    }
+
+    fn matches(&self, subject: &Subject<'_>) -> bool {
+        match self {
+            WindowRule::Match(field, pattern) => subject
+                .field(*field)
+                .is_some_and(|value| pattern.matches(&value)),
+            WindowRule::All(rules) => rules.iter().all(|rule| rule.matches(subject)),
+            WindowRule::Any(rules) => rules.iter().any(|rule| rule.matches(subject)),
+            WindowRule::Not(rule) => !rule.matches(subject),
+        }
+    }
+}
+
+impl Not for WindowRule {
+    type Output = WindowRule;
+
+    fn not(self) -> Self::Output {
+        WindowRule::Not(Box::new(self))
+    }
+}
+
+/// How a [`WindowRule`] compares a detail of a window. Matching is case-sensitive and covers
+/// the whole detail.
+#[derive(Debug, Clone)]
+pub struct Pattern(Matcher);
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Match the detail exactly
+    pub fn exact(value: impl Into<String>) -> Self {
+        Self(Matcher::Exact(value.into()))
+    }
+
+    /// Match a glob: `*` matches any run of characters (including `/`), `?` any one character,
+    /// and everything else itself
+    pub fn glob(glob: &str) -> Self {
+        let mut pattern = String::from("(?s)^");
+        for c in glob.chars() {
+            match c {
+                '*' => pattern.push_str(".*"),
+                '?' => pattern.push('.'),
+                c => pattern.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+            }
+        }
+        pattern.push('$');
+        let regex = Regex::new(&pattern).expect("an escaped glob is a valid regex");
+        Self(Matcher::Regex(regex))
+    }
+
+    /// Match a regular expression against the whole detail; use `(?i)` to ignore case
+    ///
+    /// # Errors
+    /// Returns [`FerrousFocusError::InvalidPattern`] if `regex` is not a valid regular
+    /// expression.
+    pub fn regex(regex: &str) -> FerrousFocusResult<Self> {
+        Regex::new(&format!("^(?:{regex})$"))
+            .map(|regex| Self(Matcher::Regex(regex)))
+            .map_err(|e| FerrousFocusError::InvalidPattern(format!("`{regex}`: {e}")))
+    }
+
+    fn matches(&self, value: &str) -> bool {
+        match &self.0 {
+            Matcher::Exact(exact) => exact == value,
+            Matcher::Regex(regex) => regex.is_match(value),
+        }
+    }
+}
+
+/// A window being filtered, looking up details that are not part of it only when a rule
+/// needs them.
+struct Subject<'a> {
+    window: &'a FocusedWindow,
+    executable_path: OnceCell<Option<PathBuf>>,
+}
+
+impl<'a> Subject<'a> {
+    fn new(window: &'a FocusedWindow) -> Self {
+        Self {
+            window,
+            executable_path: OnceCell::new(),
+        }
+    }
+
+    fn field(&self, field: WindowField) -> Option<String> {
+        match field {
+            WindowField::ProcessName => self.window.process_name.clone(),
+            WindowField::ExecutablePath => self
+                .executable_path
+                .get_or_init(|| {
+                    self.window
+                        .process_id
+                        .and_then(crate::platform::utils::executable_path)
+                })
+                .as_ref()
+                .map(|path| path.to_string_lossy().into_owned()),
+            WindowField::WindowClass => self.window.window_class.clone(),
+            WindowField::AppId => self.window.app_id().map(|app_id| app_id.to_string()),
+            WindowField::Title => self.window.window_title.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(process_name: &str, title: &str) -> FocusedWindow {
+        FocusedWindow {
+            process_id: Some(std::process::id()),
+            process_name: Some(process_name.to_string()),
+            window_title: Some(title.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert!(Pattern::exact("firefox").matches("firefox"));
+        assert!(!Pattern::exact("firefox").matches("firefox-bin"));
+
+        let glob = Pattern::glob("*Private Browsing*");
+        assert!(glob.matches("Bank - Mozilla Firefox Private Browsing"));
+        assert!(!glob.matches("Bank - Mozilla Firefox"));
+        assert!(Pattern::glob("fire?ox.*").matches("firefox.exe"));
+        assert!(!Pattern::glob("fire?ox.*").matches("firefoxexe"));
+
+        let regex = Pattern::regex("(?i)code|codium").unwrap();
+        assert!(regex.matches("Code"));
+        assert!(!regex.matches("vscode"));
+        assert!(matches!(
+            Pattern::regex("(unclosed"),
+            Err(FerrousFocusError::InvalidPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_rules_combine() {
+        let rule = WindowRule::all([
+            WindowRule::process_name(Pattern::exact("firefox")),
+            !WindowRule::title(Pattern::glob("*Private*")),
+        ]);
+        let subject = window("firefox", "News");
+        assert!(rule.matches(&Subject::new(&subject)));
+        let subject = window("firefox", "Private Browsing");
+        assert!(!rule.matches(&Subject::new(&subject)));
+
+        assert!(WindowRule::all([]).matches(&Subject::new(&subject)));
+        assert!(!WindowRule::any([]).matches(&Subject::new(&subject)));
+        // Details the backend did not report never match
+        assert!(!WindowRule::window_class(Pattern::glob("*")).matches(&Subject::new(&subject)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_matches_executable_path() {
+        let rule = WindowRule::executable_path(Pattern::glob("*/ferrous_focus-*"));
+        let subject = window("ferrous_focus", "Tests");
+        assert!(rule.matches(&Subject::new(&subject)));
+    }
+
+    #[test]
+    fn test_filter_drops_or_anonymizes() {
+        let filter = FocusFilter::new()
+            .include(WindowRule::process_name(Pattern::glob("*")))
+            .exclude(WindowRule::process_name(Pattern::exact("keepassxc")));
+        assert!(filter.allows(&window("firefox", "News")));
+        assert!(!filter.allows(&window("keepassxc", "Passwords")));
+        assert!(!filter.allows(&FocusedWindow::default()));
+        assert_eq!(filter.apply(window("keepassxc", "Passwords")), None);
+
+        let filter = filter.report_filtered();
+        assert_eq!(
+            filter.apply(window("keepassxc", "Passwords")),
+            Some(FocusedWindow::filtered())
+        );
+        assert!(matches!(
+            filter.apply_change(Change::Update(window("keepassxc", "Vault"))),
+            Some(Change::Update(window)) if window.filtered
+        ));
+        assert!(matches!(
+            filter.apply_change(Change::Cleared),
+            Some(Change::Cleared)
+        ));
+    }
+}
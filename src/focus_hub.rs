@@ -0,0 +1,443 @@
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTracker, FocusTrackerConfig, FocusedWindow,
+    Subscription, TrackerHandle, focus_event::window_handler,
+};
+use std::{
+    fmt,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak, mpsc},
+};
+use tracing::info;
+
+#[cfg(feature = "async")]
+use crate::AsyncSubscription;
+
+/// Decides which focus changes a hub subscriber receives.
+type Filter = Box<dyn Fn(&FocusedWindow) -> bool + Send>;
+
+/// Runs one focus tracker and broadcasts its focus changes to any number of subscribers.
+///
+/// Tracking starts with the first subscriber and stops once the last one is dropped, so the
+/// components of an application can share one display connection and one set of fetched icons.
+/// Subscribers that join while tracking runs receive the currently focused window first.
+///
+/// ```no_run
+/// use ferrous_focus::FocusHub;
+///
+/// let hub = FocusHub::new();
+/// let all = hub.subscribe()?;
+/// let browsers = hub.subscribe_filtered(|window| window.window_class.as_deref() == Some("firefox"))?;
+///
+/// for window in &all {
+///     println!("Focused: {:?}", window.window_title);
+/// }
+/// # Ok::<(), ferrous_focus::FerrousFocusError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FocusHub {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    tracker: FocusTracker,
+    state: Mutex<HubState>,
+}
+
+#[derive(Default)]
+struct HubState {
+    subscribers: Vec<Subscriber>,
+    next_id: u64,
+    /// The running tracker, `None` while nobody is subscribed.
+    handle: Option<TrackerHandle>,
+    /// Counts the trackers started and stopped, so that one stopped or replaced, which may still
+    /// report or fail while it winds down, leaves the state of the current one alone.
+    generation: u64,
+    /// The window last reported by the tracker, replayed to new subscribers.
+    current: Option<FocusedWindow>,
+    /// Why the tracker last failed, if it did.
+    error: Option<FerrousFocusError>,
+}
+
+impl fmt::Debug for HubState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HubState")
+            .field("subscribers", &self.subscribers.len())
+            .field("handle", &self.handle)
+            .field("error", &self.error)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Subscriber {
+    id: u64,
+    filter: Filter,
+    sender: Sender,
+}
+
+/// The sending half of a subscriber's channel.
+enum Sender {
+    Sync(mpsc::Sender<FocusedWindow>),
+    #[cfg(feature = "async")]
+    Async(futures::channel::mpsc::UnboundedSender<FocusedWindow>),
+}
+
+impl Subscriber {
+    /// Pass `window` on if the filter accepts it. Returns `false` once the subscriber is gone.
+    fn offer(&self, window: &FocusedWindow) -> bool {
+        if !(self.filter)(window) {
+            return true;
+        }
+        match &self.sender {
+            Sender::Sync(sender) => sender.send(window.clone()).is_ok(),
+            #[cfg(feature = "async")]
+            Sender::Async(sender) => sender.unbounded_send(window.clone()).is_ok(),
+        }
+    }
+}
+
+impl FocusHub {
+    pub fn new() -> Self {
+        Self::from_tracker(FocusTracker::new())
+    }
+
+    pub fn with_config(config: FocusTrackerConfig) -> Self {
+        Self::from_tracker(FocusTracker::with_config(config))
+    }
+
+    /// Share `tracker`, which may run a custom backend.
+    pub fn from_tracker(tracker: FocusTracker) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                tracker,
+                state: Mutex::new(HubState::default()),
+            }),
+        }
+    }
+
+    /// Receive every focus change over a channel.
+    pub fn subscribe(&self) -> FerrousFocusResult<Subscription> {
+        self.subscribe_filtered(|_| true)
+    }
+
+    /// Receive the focus changes accepted by `filter` over a channel.
+    pub fn subscribe_filtered<P>(&self, filter: P) -> FerrousFocusResult<Subscription>
+    where
+        P: Fn(&FocusedWindow) -> bool + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let membership = self.join(Box::new(filter), Sender::Sync(sender))?;
+        Ok(Subscription::from_hub(receiver, membership))
+    }
+
+    /// Receive every focus change in async code - requires the "async" feature
+    #[cfg(feature = "async")]
+    pub fn subscribe_async(&self) -> FerrousFocusResult<AsyncSubscription> {
+        self.subscribe_async_filtered(|_| true)
+    }
+
+    /// Receive the focus changes accepted by `filter` in async code - requires the "async"
+    /// feature
+    #[cfg(feature = "async")]
+    pub fn subscribe_async_filtered<P>(&self, filter: P) -> FerrousFocusResult<AsyncSubscription>
+    where
+        P: Fn(&FocusedWindow) -> bool + Send + 'static,
+    {
+        let (sender, receiver) = futures::channel::mpsc::unbounded();
+        let membership = self.join(Box::new(filter), Sender::Async(sender))?;
+        Ok(AsyncSubscription::new(receiver, membership))
+    }
+
+    /// Number of live subscribers.
+    pub fn subscriber_count(&self) -> usize {
+        self.shared.lock().subscribers.len()
+    }
+
+    /// Check if the shared tracker is running.
+    pub fn is_running(&self) -> bool {
+        self.shared
+            .lock()
+            .handle
+            .as_ref()
+            .is_some_and(TrackerHandle::is_running)
+    }
+
+    /// Take the error that ended the shared tracker, if it failed.
+    ///
+    /// A failure disconnects every subscriber; the next subscriber starts the tracker again.
+    pub fn take_error(&self) -> Option<FerrousFocusError> {
+        self.shared.lock().error.take()
+    }
+
+    /// Add a subscriber, starting the tracker if it is not running.
+    fn join(&self, filter: Filter, sender: Sender) -> FerrousFocusResult<HubMembership> {
+        let mut state = self.shared.state.lock()?;
+        let id = state.next_id;
+        state.next_id += 1;
+        let subscriber = Subscriber { id, filter, sender };
+
+        if state.handle.as_ref().is_some_and(TrackerHandle::is_running) {
+            if let Some(current) = &state.current {
+                subscriber.offer(current);
+            }
+        } else {
+            state.current = None;
+            state.generation += 1;
+            state.handle = Some(self.start(state.generation)?);
+        }
+        state.subscribers.push(subscriber);
+
+        Ok(HubMembership {
+            shared: Arc::clone(&self.shared),
+            id,
+        })
+    }
+
+    /// Start the tracker of `generation`.
+    fn start(&self, generation: u64) -> FerrousFocusResult<TrackerHandle> {
+        let on_focus = {
+            let shared = Arc::downgrade(&self.shared);
+            move |window: FocusedWindow| -> FerrousFocusResult<()> {
+                let Some(shared) = Weak::upgrade(&shared) else {
+                    return Ok(());
+                };
+                let mut state = shared.state.lock()?;
+                if state.generation != generation {
+                    return Ok(());
+                }
+                state
+                    .subscribers
+                    .retain(|subscriber| subscriber.offer(&window));
+                state.current = Some(window);
+                Ok(())
+            }
+        };
+
+        let on_exit = {
+            let shared = Arc::downgrade(&self.shared);
+            move |result: FerrousFocusResult<()>| {
+                if let Err(e) = result
+                    && let Some(shared) = Weak::upgrade(&shared)
+                {
+                    let mut state = shared.lock();
+                    if state.generation != generation {
+                        info!("Stopped focus hub tracker failed: {}", e);
+                        return Ok(());
+                    }
+                    info!("Focus hub tracker failed: {}", e);
+                    // Dropping the senders disconnects every subscriber
+                    state.subscribers.clear();
+                    state.current = None;
+                    state.error = Some(e);
+                }
+                Ok(())
+            }
+        };
+
+        self.shared
+            .tracker
+            .spawn_with_exit(window_handler(on_focus), on_exit)
+    }
+}
+
+impl Default for FocusHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, HubState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Keeps a subscriber registered with its hub; leaves the hub when dropped.
+#[derive(Debug)]
+pub(crate) struct HubMembership {
+    shared: Arc<Shared>,
+    id: u64,
+}
+
+impl HubMembership {
+    /// Check if the hub's tracker is running.
+    pub(crate) fn is_active(&self) -> bool {
+        self.shared
+            .lock()
+            .handle
+            .as_ref()
+            .is_some_and(TrackerHandle::is_running)
+    }
+}
+
+impl Drop for HubMembership {
+    fn drop(&mut self) {
+        let handle = {
+            let mut state = self.shared.lock();
+            state
+                .subscribers
+                .retain(|subscriber| subscriber.id != self.id);
+            if state.subscribers.is_empty() {
+                state.current = None;
+                state.generation += 1;
+                state.handle.take()
+            } else {
+                None
+            }
+        };
+        // Dropping the handle stops the tracker; done outside the lock the tracker needs
+        drop(handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FocusBackend, FocusEmitter};
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    /// Emits "Editor" when started, then "Browser" and "Terminal" as soon as two subscribers
+    /// are listening.
+    struct SharedBackend {
+        starts: Arc<AtomicUsize>,
+        subscribers: Arc<Mutex<Option<FocusHub>>>,
+    }
+
+    impl FocusBackend for SharedBackend {
+        fn name(&self) -> &str {
+            "shared"
+        }
+
+        fn run(
+            &self,
+            emitter: &mut FocusEmitter<'_>,
+            config: &FocusTrackerConfig,
+        ) -> FerrousFocusResult<()> {
+            self.starts.fetch_add(1, Ordering::SeqCst);
+            emitter.emit(window("Editor", "code"));
+
+            let count = || {
+                self.subscribers
+                    .lock()
+                    .unwrap()
+                    .as_ref()
+                    .map_or(0, FocusHub::subscriber_count)
+            };
+            while count() < 2 {
+                if emitter.sleep(config.poll_interval) {
+                    return Ok(());
+                }
+            }
+            emitter.emit(window("Browser", "firefox"));
+            emitter.emit(window("Terminal", "kitty"));
+
+            while !emitter.sleep(config.poll_interval) {}
+            Ok(())
+        }
+    }
+
+    /// Reports which run it is, and fails a while after it was asked to stop.
+    struct SlowFailingBackend {
+        starts: Arc<AtomicUsize>,
+    }
+
+    impl FocusBackend for SlowFailingBackend {
+        fn name(&self) -> &str {
+            "slow-failing"
+        }
+
+        fn run(
+            &self,
+            emitter: &mut FocusEmitter<'_>,
+            config: &FocusTrackerConfig,
+        ) -> FerrousFocusResult<()> {
+            let run = self.starts.fetch_add(1, Ordering::SeqCst) + 1;
+            emitter.emit(window(&format!("Run {run}"), "code"));
+            while !emitter.sleep(config.poll_interval) {}
+            std::thread::sleep(Duration::from_millis(200));
+            Err(FerrousFocusError::new("Display went away"))
+        }
+    }
+
+    fn window(title: &str, class: &str) -> FocusedWindow {
+        FocusedWindow {
+            window_title: Some(title.to_string()),
+            window_class: Some(class.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn title(window: FocusedWindow) -> String {
+        window.window_title.unwrap_or_default()
+    }
+
+    #[test]
+    fn test_broadcasts_one_tracker_to_filtered_subscribers() {
+        let starts = Arc::new(AtomicUsize::new(0));
+        let subscribers = Arc::new(Mutex::new(None));
+        let hub = FocusHub::from_tracker(FocusTracker::from_backend(
+            SharedBackend {
+                starts: Arc::clone(&starts),
+                subscribers: Arc::clone(&subscribers),
+            },
+            FocusTrackerConfig::new().with_poll_interval_ms(10),
+        ));
+        *subscribers.lock().unwrap() = Some(hub.clone());
+
+        let all = hub.subscribe().unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(title(all.recv_timeout(timeout).unwrap()), "Editor");
+
+        // Joins late: gets the current window first, then only what its filter accepts
+        let late = hub
+            .subscribe_filtered(|window| window.window_class.as_deref() != Some("firefox"))
+            .unwrap();
+        assert_eq!(title(late.recv_timeout(timeout).unwrap()), "Editor");
+        assert_eq!(title(late.recv_timeout(timeout).unwrap()), "Terminal");
+
+        assert_eq!(title(all.recv_timeout(timeout).unwrap()), "Browser");
+        assert_eq!(title(all.recv_timeout(timeout).unwrap()), "Terminal");
+        assert_eq!(starts.load(Ordering::SeqCst), 1);
+
+        // The tracker stops with the last subscriber, and the next one starts it again
+        drop(all);
+        assert!(hub.is_running());
+        assert!(late.unsubscribe().is_ok());
+        assert!(!hub.is_running());
+        assert_eq!(hub.subscriber_count(), 0);
+
+        let again = hub.subscribe().unwrap();
+        assert_eq!(title(again.recv_timeout(timeout).unwrap()), "Editor");
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+
+        // Break the reference cycle through the backend
+        subscribers.lock().unwrap().take();
+    }
+
+    #[test]
+    fn test_stopped_tracker_failing_late_leaves_next_one_alone() {
+        let hub = FocusHub::from_tracker(FocusTracker::from_backend(
+            SlowFailingBackend {
+                starts: Arc::default(),
+            },
+            FocusTrackerConfig::new().with_poll_interval_ms(10),
+        ));
+        let timeout = Duration::from_secs(5);
+
+        let first = hub.subscribe().unwrap();
+        assert_eq!(title(first.recv_timeout(timeout).unwrap()), "Run 1");
+        drop(first);
+
+        // Starts a second tracker while the first one still winds down
+        let second = hub.subscribe().unwrap();
+        assert_eq!(title(second.recv_timeout(timeout).unwrap()), "Run 2");
+        std::thread::sleep(Duration::from_millis(400));
+
+        assert!(hub.take_error().is_none());
+        assert_eq!(hub.subscriber_count(), 1);
+        assert!(hub.is_running());
+        assert!(matches!(second.try_recv(), Err(mpsc::TryRecvError::Empty)));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::{
+    Backend, FerrousFocusResult, FocusBackend, FocusTrackerConfig, FocusedWindow,
+    platform::impl_focus_tracker::ImplFocusQuery,
+};
+use std::{fmt, sync::Arc};
+
+/// Asks which window is focused right now, without tracking changes, created with
+/// [`FocusTracker::query`](crate::FocusTracker::query).
+///
+/// The connection to the backend stays open between calls to [`FocusQuery::current`], which
+/// makes repeated queries cheap; it is closed when the query is dropped.
+///
+/// ```no_run
+/// use ferrous_focus::FocusTracker;
+///
+/// let mut query = FocusTracker::new().query()?;
+/// if let Some(window) = query.current()? {
+///     println!("Focused: {:?}", window.window_title);
+/// }
+/// # Ok::<(), ferrous_focus::FerrousFocusError>(())
+/// ```
+pub struct FocusQuery {
+    source: QuerySource,
+    config: FocusTrackerConfig,
+}
+
+enum QuerySource {
+    Native {
+        backend: Backend,
+        query: Box<ImplFocusQuery>,
+    },
+    Custom(Arc<dyn FocusBackend>),
+}
+
+impl FocusQuery {
+    pub(crate) fn native(backend: Backend, config: FocusTrackerConfig) -> FerrousFocusResult<Self> {
+        Ok(Self {
+            source: QuerySource::Native {
+                backend,
+                query: Box::new(ImplFocusQuery::connect(backend)?),
+            },
+            config,
+        })
+    }
+
+    pub(crate) fn custom(backend: Arc<dyn FocusBackend>, config: FocusTrackerConfig) -> Self {
+        Self {
+            source: QuerySource::Custom(backend),
+            config,
+        }
+    }
+
+    /// Get the focused window, with its icon as configured, or `None` if nothing has focus or
+    /// the configured filter drops the focused window.
+    pub fn current(&mut self) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let window = match &mut self.source {
+            QuerySource::Native { query, .. } => query.current(&self.config)?,
+            QuerySource::Custom(backend) => backend.current(&self.config)?,
+        };
+        Ok(window.and_then(|window| self.config.filter.apply(window)))
+    }
+
+    /// The built-in backend queried, or `None` for a custom backend.
+    pub fn backend(&self) -> Option<Backend> {
+        match &self.source {
+            QuerySource::Native { backend, .. } => Some(*backend),
+            QuerySource::Custom(_) => None,
+        }
+    }
+}
+
+impl fmt::Debug for FocusQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let backend = match &self.source {
+            QuerySource::Native { backend, .. } => backend.to_string(),
+            QuerySource::Custom(backend) => backend.name().to_string(),
+        };
+        f.debug_struct("FocusQuery")
+            .field("backend", &backend)
+            .field("config", &self.config)
+            .finish()
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusEvent, FocusTracker, TrackerHandle,
+    focus_event::event_handler,
+};
+use futures::{
+    SinkExt, Stream, StreamExt,
+    channel::mpsc::{self, Receiver},
+    executor::block_on,
+};
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Events the tracker may queue up before it waits for the stream to catch up.
+const STREAM_CAPACITY: usize = 16;
+
+/// Focus events of a tracker running in the background, created with
+/// [`FocusTracker::stream`] - requires the "async" feature
+///
+/// The stream ends after yielding the error that stopped the backend, if any. The tracker waits
+/// while the stream is not polled and a few events are pending, and stops when the stream is
+/// dropped.
+#[derive(Debug)]
+pub struct FocusStream {
+    // Dropped first: the tracker is stopped before its channel disconnects
+    _handle: Option<TrackerHandle>,
+    receiver: Receiver<FerrousFocusResult<FocusEvent>>,
+}
+
+impl FocusStream {
+    pub(crate) fn new(tracker: &FocusTracker) -> Self {
+        let (sender, receiver) = mpsc::channel(STREAM_CAPACITY);
+
+        let on_event = {
+            let mut sender = sender.clone();
+            move |event: FocusEvent| -> FerrousFocusResult<()> {
+                // Waiting for room in the channel is what slows the tracker down
+                block_on(sender.send(Ok(event)))
+                    .map_err(|_| FerrousFocusError::new("Stream dropped"))
+            }
+        };
+        let on_exit = {
+            let mut sender = sender.clone();
+            move |result: FerrousFocusResult<()>| {
+                if let Err(e) = result {
+                    let _ = block_on(sender.send(Err(e)));
+                }
+                Ok(())
+            }
+        };
+
+        let handle = match tracker
+            .spawn_with_exit(event_handler(on_event, tracker.event_sequence()), on_exit)
+        {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                let _ = sender.clone().try_send(Err(e));
+                None
+            }
+        };
+
+        Self {
+            _handle: handle,
+            receiver,
+        }
+    }
+}
+
+impl Stream for FocusStream {
+    type Item = FerrousFocusResult<FocusEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
@@ -1,16 +1,78 @@
 use crate::{
-    FerrousFocusResult, FocusTrackerConfig, FocusedWindow,
+    AppId, Backend, FerrousFocusError, FerrousFocusResult, FocusBackend, FocusEvent, FocusQuery,
+    FocusTrackerConfig, FocusedWindow, Subscription, TrackerHandle,
+    backend::select_backend,
+    debounce,
+    focus_backend::FocusEmitter,
+    focus_event::{Change, Detection, event_handler, window_handler},
+    icon_cache::{self, IconKey},
     platform::impl_focus_tracker::ImplFocusTracker,
+    stop_signal::StopSignal,
 };
-use std::sync::{atomic::AtomicBool, mpsc};
+use image::RgbaImage;
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+};
+use tracing::info;
 
+#[cfg(feature = "async")]
+use crate::{
+    AsyncRuntime, FocusStream, async_runtime::default_runtime, focus_backend::run_blocking_async,
+};
 #[cfg(feature = "async")]
 use std::future::Future;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct FocusTracker {
-    impl_focus_tracker: ImplFocusTracker,
+    source: Source,
     config: FocusTrackerConfig,
+    /// Number of the next event, shared with clones of the tracker.
+    sequence: Arc<AtomicU64>,
+    /// Changes debounced or coalesced away, shared with clones of the tracker.
+    suppressed: Arc<AtomicU64>,
+    /// Runtime for the async API, or `None` to pick one per call.
+    #[cfg(feature = "async")]
+    async_runtime: Option<Arc<dyn AsyncRuntime>>,
+}
+
+impl fmt::Debug for FocusTracker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FocusTracker")
+            .field("source", &self.source)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Where the focus events of a tracker come from.
+#[derive(Clone)]
+enum Source {
+    /// The built-in backends of this platform.
+    Native {
+        impl_focus_tracker: ImplFocusTracker,
+        /// Backend started by the most recent tracking call, shared with clones of the tracker.
+        active_backend: Arc<Mutex<Option<Backend>>>,
+    },
+    /// A backend provided by the application.
+    Custom(Arc<dyn FocusBackend>),
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Native { active_backend, .. } => f
+                .debug_struct("Native")
+                .field("active_backend", active_backend)
+                .finish(),
+            Source::Custom(backend) => f.debug_tuple("Custom").field(&backend.name()).finish(),
+        }
+    }
 }
 
 impl FocusTracker {
@@ -20,10 +82,117 @@ impl FocusTracker {
 
     pub fn with_config(config: FocusTrackerConfig) -> Self {
         Self {
-            impl_focus_tracker: ImplFocusTracker::new(),
+            source: Source::Native {
+                impl_focus_tracker: ImplFocusTracker::new(),
+                active_backend: Arc::new(Mutex::new(None)),
+            },
             config,
+            sequence: Arc::default(),
+            suppressed: Arc::default(),
+            #[cfg(feature = "async")]
+            async_runtime: None,
+        }
+    }
+
+    /// Create a tracker that runs a custom backend instead of the platform ones.
+    ///
+    /// Backend selection in `config` does not apply; everything else does.
+    pub fn from_backend<B>(backend: B, config: FocusTrackerConfig) -> Self
+    where
+        B: FocusBackend + 'static,
+    {
+        Self {
+            source: Source::Custom(Arc::new(backend)),
+            config,
+            sequence: Arc::default(),
+            suppressed: Arc::default(),
+            #[cfg(feature = "async")]
+            async_runtime: None,
+        }
+    }
+
+    /// Run the async API on `runtime` - requires the "async" feature
+    ///
+    /// By default tokio is used from within a tokio runtime when the `tokio` feature is enabled,
+    /// else smol when the `smol` feature is enabled, else plain threads.
+    #[cfg(feature = "async")]
+    pub fn with_async_runtime<R>(mut self, runtime: R) -> Self
+    where
+        R: AsyncRuntime + 'static,
+    {
+        self.async_runtime = Some(Arc::new(runtime));
+        self
+    }
+
+    /// The built-in backend started by the most recent tracking call, or `None` if tracking has
+    /// not started yet or the tracker runs a custom backend.
+    pub fn active_backend(&self) -> Option<Backend> {
+        match &self.source {
+            Source::Native { active_backend, .. } => *active_backend
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            Source::Custom(_) => None,
+        }
+    }
+
+    /// Number of focus changes not reported because focus moved on before the debounce window
+    /// passed, or because they repeated the window reported last. Shared with clones of the
+    /// tracker.
+    pub fn suppressed_events(&self) -> u64 {
+        self.suppressed.load(Ordering::Relaxed)
+    }
+}
+
+/// The backend a tracking call runs.
+enum Runner<'a> {
+    Native(&'a ImplFocusTracker, Backend),
+    Custom(&'a dyn FocusBackend),
+}
+
+impl Runner<'_> {
+    fn name(&self) -> String {
+        match self {
+            Runner::Native(_, backend) => backend.to_string(),
+            Runner::Custom(backend) => backend.name().to_string(),
         }
     }
+
+    fn run(
+        &self,
+        on_change: &mut dyn FnMut(Change, Detection) -> FerrousFocusResult<()>,
+        stop_signal: &StopSignal,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        match self {
+            Runner::Native(impl_focus_tracker, backend) => {
+                impl_focus_tracker.track_focus_with_stop(on_change, *backend, stop_signal, config)
+            }
+            Runner::Custom(backend) => {
+                let mut emitter = FocusEmitter::new(on_change, Some(stop_signal));
+                let result = backend.run(&mut emitter, config);
+                emitter.finish(result)
+            }
+        }
+    }
+}
+
+/// Pass a change the backend did not observe itself to the handler.
+fn report<F>(on_change: &mut F, change: Change) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    on_change(change, Detection::now())
+}
+
+/// Select the built-in backend to run and remember it as the active one.
+fn start_backend(
+    active_backend: &Mutex<Option<Backend>>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<Backend> {
+    let backend = select_backend(config)?;
+    info!("Starting {} focus backend", backend);
+    *active_backend.lock()? = Some(backend);
+    Ok(backend)
 }
 
 impl Default for FocusTracker {
@@ -37,7 +206,7 @@ impl FocusTracker {
     where
         F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
     {
-        self.impl_focus_tracker.track_focus(on_focus, &self.config)
+        self.run(self.guarded(window_handler(on_focus)), None)
     }
 
     pub fn track_focus_with_stop<F>(
@@ -48,8 +217,184 @@ impl FocusTracker {
     where
         F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
     {
-        self.impl_focus_tracker
-            .track_focus_with_stop(on_focus, stop_signal, &self.config)
+        self.run(
+            self.guarded(window_handler(on_focus)),
+            Some(&StopSignal::with_external(stop_signal)),
+        )
+    }
+
+    /// Track focus, telling `on_event` what changed: which window gained focus, a new title or
+    /// icon of the focused window, or that nothing has focus anymore.
+    ///
+    /// ```no_run
+    /// use ferrous_focus::{FocusEvent, FocusTracker};
+    ///
+    /// FocusTracker::new().track_events(|event| {
+    ///     match event {
+    ///         FocusEvent::FocusChanged { current, .. } => {
+    ///             println!("Focused: {:?}", current.window_title)
+    ///         }
+    ///         FocusEvent::TitleChanged { current, .. } => {
+    ///             println!("Renamed: {:?}", current.window_title)
+    ///         }
+    ///         FocusEvent::FocusCleared { .. } => println!("Nothing focused"),
+    ///         _ => {}
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// # Ok::<(), ferrous_focus::FerrousFocusError>(())
+    /// ```
+    pub fn track_events<F>(&self, on_event: F) -> FerrousFocusResult<()>
+    where
+        F: FnMut(FocusEvent) -> FerrousFocusResult<()>,
+    {
+        self.run(
+            self.guarded(event_handler(on_event, self.event_sequence())),
+            None,
+        )
+    }
+
+    /// Like [`FocusTracker::track_events`], until `stop_signal` is set.
+    pub fn track_events_with_stop<F>(
+        &self,
+        on_event: F,
+        stop_signal: &AtomicBool,
+    ) -> FerrousFocusResult<()>
+    where
+        F: FnMut(FocusEvent) -> FerrousFocusResult<()>,
+    {
+        self.run(
+            self.guarded(event_handler(on_event, self.event_sequence())),
+            Some(&StopSignal::with_external(stop_signal)),
+        )
+    }
+
+    /// Track focus on a new thread until the returned handle is stopped or dropped.
+    ///
+    /// ```no_run
+    /// use ferrous_focus::FocusTracker;
+    ///
+    /// let handle = FocusTracker::new().spawn(|window| {
+    ///     println!("Focused: {:?}", window.window_title);
+    ///     Ok(())
+    /// })?;
+    ///
+    /// // ...
+    ///
+    /// handle.stop();
+    /// handle.join()?;
+    /// # Ok::<(), ferrous_focus::FerrousFocusError>(())
+    /// ```
+    pub fn spawn<F>(&self, on_focus: F) -> FerrousFocusResult<TrackerHandle>
+    where
+        F: FnMut(FocusedWindow) -> FerrousFocusResult<()> + Send + 'static,
+    {
+        self.spawn_with_exit(self.guarded(window_handler(on_focus)), |result| result)
+    }
+
+    /// Like [`FocusTracker::spawn`], telling `on_event` what changed as
+    /// [`FocusTracker::track_events`] does.
+    pub fn spawn_events<F>(&self, on_event: F) -> FerrousFocusResult<TrackerHandle>
+    where
+        F: FnMut(FocusEvent) -> FerrousFocusResult<()> + Send + 'static,
+    {
+        self.spawn_with_exit(
+            self.guarded(event_handler(on_event, self.event_sequence())),
+            |result| result,
+        )
+    }
+
+    /// Counter numbering the events of this tracker and its clones.
+    pub(crate) fn event_sequence(&self) -> Arc<AtomicU64> {
+        Arc::clone(&self.sequence)
+    }
+
+    /// Like [`FocusTracker::spawn`], passing the result of tracking through `on_exit` on the
+    /// tracker thread before it becomes the result of the handle.
+    pub(crate) fn spawn_with_exit<F, E>(
+        &self,
+        on_change: F,
+        on_exit: E,
+    ) -> FerrousFocusResult<TrackerHandle>
+    where
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()> + Send + 'static,
+        E: FnOnce(FerrousFocusResult<()>) -> FerrousFocusResult<()> + Send + 'static,
+    {
+        let stop_signal = Arc::new(StopSignal::new());
+        let tracker = self.clone();
+        let thread_stop = Arc::clone(&stop_signal);
+
+        let thread = thread::Builder::new()
+            .name("ferrous-focus".to_string())
+            .spawn(move || {
+                let mut on_change = on_change;
+                let result = tracker.run(&mut on_change, Some(&thread_stop));
+                // Errors after the tracker was asked to stop are those of its consumer going away
+                if !thread_stop.is_stopped() {
+                    tracker.config.error_policy.report_exit(&result);
+                }
+                // Consumers see tracking end once the handler is gone
+                drop(on_change);
+                on_exit(result)
+            })
+            .map_err(|e| FerrousFocusError::new(format!("Failed to spawn tracker thread: {e}")))?;
+
+        Ok(TrackerHandle::new(stop_signal, thread))
+    }
+
+    /// Apply the error policy of the configuration to a handler passed in by the application.
+    fn guarded<F>(
+        &self,
+        mut on_change: F,
+    ) -> impl FnMut(Change, Detection) -> FerrousFocusResult<()> + use<F>
+    where
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+    {
+        let error_policy = self.config.error_policy.clone();
+        move |change, detection| error_policy.handle(on_change(change, detection))
+    }
+
+    /// Run the backend until it is stopped or fails, or `on_change` fails.
+    fn run<F>(&self, mut on_change: F, stop_signal: Option<&StopSignal>) -> FerrousFocusResult<()>
+    where
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+    {
+        self.config.validate()?;
+
+        // Handler errors stop the backend through the signal, so there always is one
+        let own_stop_signal = StopSignal::new();
+        let stop_signal = stop_signal.unwrap_or(&own_stop_signal);
+
+        let runner = match &self.source {
+            Source::Native {
+                impl_focus_tracker,
+                active_backend,
+            } => Runner::Native(
+                impl_focus_tracker,
+                start_backend(active_backend, &self.config)?,
+            ),
+            Source::Custom(backend) => {
+                info!("Starting {} focus backend", backend.name());
+                Runner::Custom(&**backend)
+            }
+        };
+
+        report(&mut on_change, Change::Started(runner.name()))?;
+        debounce::run(
+            self.config.debounce,
+            &self.suppressed,
+            stop_signal,
+            &mut on_change,
+            |on_change| {
+                let mut on_change =
+                    |change, detection| match self.config.filter.apply_change(change) {
+                        Some(change) => on_change(change, detection),
+                        None => Ok(()),
+                    };
+                runner.run(&mut on_change, stop_signal, &self.config)
+            },
+        )?;
+        report(&mut on_change, Change::Stopped)
     }
 
     /// Async version of track_focus - requires the "async" feature
@@ -59,9 +404,7 @@ impl FocusTracker {
         F: FnMut(FocusedWindow) -> Fut,
         Fut: Future<Output = FerrousFocusResult<()>>,
     {
-        self.impl_focus_tracker
-            .track_focus_async(on_focus, &self.config)
-            .await
+        self.run_async(on_focus, None).await
     }
 
     /// Async version of track_focus_with_stop - requires the "async" feature
@@ -75,35 +418,109 @@ impl FocusTracker {
         F: FnMut(FocusedWindow) -> Fut,
         Fut: Future<Output = FerrousFocusResult<()>>,
     {
-        self.impl_focus_tracker
-            .track_focus_async_with_stop(on_focus, stop_signal, &self.config)
-            .await
+        self.run_async(on_focus, Some(stop_signal)).await
+    }
+
+    /// Stream focus events from a tracker running in the background - requires the "async"
+    /// feature
+    ///
+    /// Works with any executor. Dropping the stream stops the tracker.
+    ///
+    /// ```no_run
+    /// use ferrous_focus::{FocusEvent, FocusTracker};
+    /// use futures::StreamExt;
+    ///
+    /// # async fn run() -> ferrous_focus::FerrousFocusResult<()> {
+    /// let mut events = FocusTracker::new().stream();
+    /// while let Some(event) = events.next().await {
+    ///     if let FocusEvent::FocusChanged { current, .. } = event? {
+    ///         println!("Focused: {:?}", current.window_title);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "async")]
+    pub fn stream(&self) -> FocusStream {
+        FocusStream::new(self)
+    }
+
+    #[cfg(feature = "async")]
+    fn async_runtime(&self) -> Arc<dyn AsyncRuntime> {
+        self.async_runtime.clone().unwrap_or_else(default_runtime)
+    }
+
+    /// Track focus on a blocking thread of the async runtime for the async API.
+    #[cfg(feature = "async")]
+    async fn run_async<F, Fut>(
+        &self,
+        on_focus: F,
+        stop_signal: Option<&AtomicBool>,
+    ) -> FerrousFocusResult<()>
+    where
+        F: FnMut(FocusedWindow) -> Fut,
+        Fut: Future<Output = FerrousFocusResult<()>>,
+    {
+        let tracker = self.clone();
+        run_blocking_async(
+            &*self.async_runtime(),
+            move |on_change, stop_signal| tracker.run(on_change, Some(stop_signal)),
+            on_focus,
+            stop_signal,
+            &self.config.error_policy,
+        )
+        .await
+    }
+
+    /// Get the focused window right now, with its icon as configured, or `None` if nothing has
+    /// focus.
+    ///
+    /// Connects to the backend for this one query; use [`FocusTracker::query`] to ask
+    /// repeatedly.
+    pub fn current(&self) -> FerrousFocusResult<Option<FocusedWindow>> {
+        self.query()?.current()
+    }
+
+    /// Connect to the backend for asking which window is focused, as often as needed.
+    ///
+    /// The backend is selected like for tracking, but does not become the
+    /// [`active_backend`](FocusTracker::active_backend).
+    pub fn query(&self) -> FerrousFocusResult<FocusQuery> {
+        self.config.validate()?;
+        match &self.source {
+            Source::Native { .. } => {
+                FocusQuery::native(select_backend(&self.config)?, self.config.clone())
+            }
+            Source::Custom(backend) => {
+                Ok(FocusQuery::custom(Arc::clone(backend), self.config.clone()))
+            }
+        }
+    }
+
+    /// Get the icon of the app with `app_id` from the icon cache, at the configured size, or
+    /// `None` if it is not cached.
+    ///
+    /// Icons are cached whenever a tracker in this process reports a window of the app (see
+    /// [`IconConfig::cache_bytes`](crate::IconConfig::cache_bytes)); this never asks the backend.
+    /// It looks up icons of apps seen before, e.g. those of [`FocusedWindow::app_id`] kept by the
+    /// application.
+    pub fn cached_icon(&self, app_id: &AppId) -> Option<Arc<RgbaImage>> {
+        IconKey::for_app(app_id.clone(), &self.config.icon).and_then(|key| icon_cache::get(&key))
     }
 
     /// Subscribe to focus changes and receive them via a channel
-    pub fn subscribe_focus_changes(&self) -> FerrousFocusResult<mpsc::Receiver<FocusedWindow>> {
+    ///
+    /// Tracking runs on a background thread until the subscription is dropped or
+    /// [`Subscription::unsubscribe`] is called.
+    pub fn subscribe_focus_changes(&self) -> FerrousFocusResult<Subscription> {
         let (sender, receiver) = mpsc::channel();
-        let stop_signal = AtomicBool::new(false);
 
-        // Clone the tracker for the background thread
-        let tracker = self.clone();
+        let handle = self.spawn(move |window: FocusedWindow| -> FerrousFocusResult<()> {
+            sender
+                .send(window)
+                .map_err(|_| FerrousFocusError::Error("Receiver dropped".to_string()))
+        })?;
 
-        // Spawn a background thread to track focus changes
-        std::thread::spawn(move || {
-            let _ = tracker.track_focus_with_stop(
-                move |window: FocusedWindow| -> FerrousFocusResult<()> {
-                    if sender.send(window).is_err() {
-                        // Receiver has been dropped, stop tracking
-                        return Err(crate::FerrousFocusError::Error(
-                            "Receiver dropped".to_string(),
-                        ));
-                    }
-                    Ok(())
-                },
-                &stop_signal,
-            );
-        });
-
-        Ok(receiver)
+        Ok(Subscription::new(receiver, handle))
     }
 }
@@ -1,12 +1,89 @@
+use crate::{AppId, IconHandle, WindowId};
+use std::sync::Arc;
+
 /// Snapshot of the currently focused window.
-#[derive(Debug, Clone, PartialEq, Eq)]
+///
+/// With the `serde` feature, windows can be serialized; see
+/// [`IconEncoding`](crate::IconEncoding) for how the icon is written.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
 pub struct FocusedWindow {
+    /// Identifies the window for as long as it exists, when the backend reports it.
+    pub window_id: Option<WindowId>,
     /// Process ID of the focused window.
     pub process_id: Option<u32>,
     /// Reported process name (e.g. "firefox", "chrome", "code").
     pub process_name: Option<String>,
     /// Full window title/caption as provided by the OS.
     pub window_title: Option<String>,
-    /// Raw icon data (may be `None` if not retrievable on the platform).
-    pub icon: Option<image::RgbaImage>,
+    /// Window class or application ID (e.g. the Wayland `app_id` or the X11 `WM_CLASS`), when the
+    /// backend reports one.
+    pub window_class: Option<String>,
+    /// Name of the workspace the window lives on, when the backend reports one.
+    pub workspace: Option<String>,
+    /// Name of the output (monitor) the window is shown on, when the backend reports one.
+    pub output: Option<String>,
+    /// Whether the window is fullscreen, when the backend reports it.
+    pub fullscreen: Option<bool>,
+    /// Whether the window is an X11 client running under XWayland, when the backend knows.
+    ///
+    /// The XWayland backend only sees X11 clients; when focus moves to a native Wayland client it
+    /// reports [`FocusedWindow::unknown_wayland_client`] instead.
+    pub xwayland: Option<bool>,
+    /// Marks set on the window, when the backend reports them (only sway and i3 have marks).
+    pub marks: Option<Vec<String>>,
+    /// Whether this is the [`FocusedWindow::filtered`] placeholder for a window the
+    /// [`FocusFilter`](crate::FocusFilter) does not report.
+    pub filtered: bool,
+    /// Raw icon data (may be `None` if not retrievable on the platform), shared with the icon
+    /// cache and with other windows of the same app.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            with = "crate::icon_encoding",
+            skip_serializing_if = "crate::icon_encoding::skip"
+        )
+    )]
+    pub icon: Option<Arc<image::RgbaImage>>,
+    /// Fetches the icon on demand, with [`IconMode::Lazy`](crate::IconMode::Lazy); `icon` is
+    /// `None` then.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub icon_handle: Option<IconHandle>,
+}
+
+impl FocusedWindow {
+    /// Placeholder reported by the XWayland backend while a native Wayland client, which it
+    /// cannot identify, has focus.
+    pub fn unknown_wayland_client() -> Self {
+        Self {
+            xwayland: Some(false),
+            ..Default::default()
+        }
+    }
+
+    /// Placeholder reported for a window the [`FocusFilter`](crate::FocusFilter) does not
+    /// report, when it is set to report focus on such windows; it carries no details.
+    pub fn filtered() -> Self {
+        Self {
+            filtered: true,
+            ..Default::default()
+        }
+    }
+
+    /// The application the window belongs to: its window class, else its process name.
+    pub fn app_id(&self) -> Option<AppId> {
+        self.window_class
+            .as_deref()
+            .or(self.process_name.as_deref())
+            .map(AppId::new)
+    }
+
+    /// Check if this is the [`FocusedWindow::unknown_wayland_client`] placeholder.
+    pub fn is_unknown_wayland_client(&self) -> bool {
+        *self == Self::unknown_wayland_client()
+    }
 }
@@ -0,0 +1,232 @@
+use crate::{AppId, FerrousFocusResult, FocusedWindow, IconConfig};
+use image::{RgbaImage, imageops::FilterType};
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, PoisonError},
+};
+
+/// Icons of every tracker in the process, so that switching back to an app does not fetch and
+/// resize its icon again.
+static CACHE: LazyLock<Mutex<IconCache>> = LazyLock::new(Mutex::default);
+
+/// Identifies a cached icon: the app it belongs to, and the size and filter it was resized with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IconKey {
+    app_id: AppId,
+    size: Option<u32>,
+    filter_type: FilterType,
+}
+
+impl IconKey {
+    /// The key for the icon of `window` as configured, or `None` if the window has no app ID
+    /// or caching is off.
+    pub(crate) fn new(window: &FocusedWindow, config: &IconConfig) -> Option<Self> {
+        Self::for_app(window.app_id()?, config)
+    }
+
+    /// The key for the icon of `app_id` as configured, or `None` if caching is off.
+    pub(crate) fn for_app(app_id: AppId, config: &IconConfig) -> Option<Self> {
+        if config.cache_bytes == 0 {
+            return None;
+        }
+        Some(Self {
+            app_id,
+            size: config.size,
+            filter_type: config.filter_type,
+        })
+    }
+}
+
+/// Return the cached icon for `key`, or fetch it with `load` and cache it within `budget` bytes.
+///
+/// The budget bounds the whole cache, which is trimmed to it before the icon is added.
+///
+/// The cache is not locked while loading, so a slow backend does not hold up other trackers.
+pub(crate) fn get_or_load<L>(
+    key: Option<&IconKey>,
+    budget: usize,
+    load: L,
+) -> FerrousFocusResult<Arc<RgbaImage>>
+where
+    L: FnOnce() -> FerrousFocusResult<RgbaImage>,
+{
+    let Some(key) = key else {
+        return load().map(Arc::new);
+    };
+    if let Some(icon) = lock().get(key) {
+        return Ok(icon);
+    }
+    let icon = Arc::new(load()?);
+    lock().insert(key.clone(), Arc::clone(&icon), budget);
+    Ok(icon)
+}
+
+/// Return the cached icon for `key`, without fetching it when it is not cached.
+pub(crate) fn get(key: &IconKey) -> Option<Arc<RgbaImage>> {
+    lock().get(key)
+}
+
+/// Drop the cached icon for `key`, e.g. because the app changed its icon.
+pub(crate) fn forget(key: &IconKey) {
+    lock().remove(key);
+}
+
+fn lock() -> std::sync::MutexGuard<'static, IconCache> {
+    CACHE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn icon_bytes(icon: &RgbaImage) -> usize {
+    icon.as_raw().len()
+}
+
+#[derive(Debug)]
+struct Entry {
+    icon: Arc<RgbaImage>,
+    last_used: u64,
+}
+
+/// Least recently used icons, evicted once they take more bytes than the budget.
+#[derive(Debug, Default)]
+struct IconCache {
+    entries: HashMap<IconKey, Entry>,
+    bytes: usize,
+    clock: u64,
+}
+
+impl IconCache {
+    fn get(&mut self, key: &IconKey) -> Option<Arc<RgbaImage>> {
+        self.clock += 1;
+        let entry = self.entries.get_mut(key)?;
+        entry.last_used = self.clock;
+        Some(Arc::clone(&entry.icon))
+    }
+
+    fn insert(&mut self, key: IconKey, icon: Arc<RgbaImage>, budget: usize) {
+        self.remove(&key);
+        let bytes = icon_bytes(&icon);
+        if bytes > budget {
+            return;
+        }
+        while self.bytes + bytes > budget {
+            self.evict_oldest();
+        }
+        self.clock += 1;
+        self.bytes += bytes;
+        self.entries.insert(
+            key,
+            Entry {
+                icon,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &IconKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= icon_bytes(&entry.icon);
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.remove(&key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FocusTracker, FocusTrackerConfig};
+
+    fn key(app: &str) -> IconKey {
+        IconKey {
+            app_id: AppId::new(app),
+            size: Some(16),
+            filter_type: FilterType::Lanczos3,
+        }
+    }
+
+    fn icon() -> Arc<RgbaImage> {
+        // 16 * 16 * 4 = 1024 bytes
+        Arc::new(RgbaImage::new(16, 16))
+    }
+
+    #[test]
+    fn test_least_recently_used_icons_are_evicted() {
+        let mut cache = IconCache::default();
+        cache.insert(key("editor"), icon(), 2048);
+        cache.insert(key("browser"), icon(), 2048);
+        assert!(cache.get(&key("editor")).is_some());
+
+        cache.insert(key("terminal"), icon(), 2048);
+        assert_eq!(cache.bytes, 2048);
+        assert!(cache.get(&key("browser")).is_none());
+        assert!(cache.get(&key("editor")).is_some());
+        assert!(cache.get(&key("terminal")).is_some());
+
+        cache.insert(key("huge"), Arc::new(RgbaImage::new(64, 64)), 2048);
+        assert!(cache.get(&key("huge")).is_none());
+        assert_eq!(cache.entries.len(), 2);
+
+        cache.remove(&key("editor"));
+        assert_eq!(cache.bytes, 1024);
+    }
+
+    #[test]
+    fn test_icons_are_shared_per_app_size_and_filter() {
+        let window = FocusedWindow {
+            process_name: Some("icon-cache-test".to_string()),
+            ..Default::default()
+        };
+        let config = IconConfig::new().with_size(16);
+        let key = IconKey::new(&window, &config);
+        assert!(key.is_some());
+        let load = || Ok(RgbaImage::new(16, 16));
+
+        let first = get_or_load(key.as_ref(), config.cache_bytes, load).unwrap();
+        let second = get_or_load(key.as_ref(), config.cache_bytes, || unreachable!()).unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Trackers look the icon up by app, at their own size
+        let app_id = window.app_id().unwrap();
+        let tracker = |icon: IconConfig| {
+            FocusTracker::with_config(FocusTrackerConfig::new().with_icon_config(icon))
+        };
+        let cached = tracker(config.clone()).cached_icon(&app_id).unwrap();
+        assert!(Arc::ptr_eq(&first, &cached));
+        assert!(
+            tracker(config.clone().with_size(32))
+                .cached_icon(&app_id)
+                .is_none()
+        );
+
+        let other_size = IconKey::new(&window, &config.clone().with_size(32));
+        assert_ne!(key, other_size);
+        let other_filter = IconKey::new(
+            &window,
+            &IconConfig {
+                filter_type: FilterType::Nearest,
+                ..config.clone()
+            },
+        );
+        assert_ne!(key, other_filter);
+
+        forget(key.as_ref().unwrap());
+        assert!(tracker(config.clone()).cached_icon(&app_id).is_none());
+        let third = get_or_load(key.as_ref(), config.cache_bytes, load).unwrap();
+        assert!(!Arc::ptr_eq(&first, &third));
+
+        let uncached = IconConfig::new().with_cache_bytes(0);
+        assert_eq!(IconKey::new(&window, &uncached), None);
+        assert_eq!(
+            IconKey::for_app(AppId::new("icon-cache-test"), &uncached),
+            None
+        );
+    }
+}
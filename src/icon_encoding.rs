@@ -0,0 +1,396 @@
+//! How [`FocusedWindow::icon`](crate::FocusedWindow::icon) is written when windows and events are
+//! serialized.
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{cell::Cell, fmt, io::Cursor, rc::Rc, sync::Arc, thread::LocalKey};
+
+const DATA_URI_PREFIX: &str = "data:image/png;base64,";
+
+thread_local! {
+    static ENCODING: Cell<IconEncoding> = const { Cell::new(IconEncoding::Png) };
+    static RESOLVER: Cell<Option<Rc<IconResolver>>> = const { Cell::new(None) };
+}
+
+type IconResolver = dyn Fn(&str) -> Option<Arc<RgbaImage>>;
+
+/// How icons are written when serializing a [`FocusedWindow`](crate::FocusedWindow) or a
+/// [`FocusEvent`](crate::FocusEvent).
+///
+/// Serializing a window directly writes its icon as [`IconEncoding::Png`]; wrap it with
+/// [`IconEncoding::apply`] to choose another encoding. Deserializing accepts every encoding.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IconEncoding {
+    /// Leave the icon out.
+    Omit,
+    /// A base64 encoded PNG image.
+    #[default]
+    Png,
+    /// A `data:image/png;base64,...` URI, e.g. for an HTML `<img>`.
+    DataUri,
+    /// Width, height and the raw RGBA pixels, as `{"width", "height", "rgba"}`.
+    Rgba,
+    /// A reference to the icon by its [`icon_hash`], as `{"hash"}`, for storing each icon only
+    /// once. Deserialize with [`resolve_icons`] to look the icons up again.
+    Hash,
+}
+
+impl IconEncoding {
+    /// Wrap `value`, e.g. a window, an event or a list of them, to serialize its icons with this
+    /// encoding.
+    ///
+    /// ```
+    /// # fn run(event: ferrous_focus::FocusEvent) -> serde_json::Result<()> {
+    /// use ferrous_focus::IconEncoding;
+    ///
+    /// let json = serde_json::to_string(&IconEncoding::DataUri.apply(&event))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn apply<T: ?Sized>(self, value: &T) -> WithIconEncoding<'_, T> {
+        WithIconEncoding {
+            value,
+            encoding: self,
+        }
+    }
+}
+
+/// A value serialized with the icon encoding it was [`applied`](IconEncoding::apply) with.
+#[derive(Debug, Clone, Copy)]
+pub struct WithIconEncoding<'a, T: ?Sized> {
+    value: &'a T,
+    encoding: IconEncoding,
+}
+
+impl<T: Serialize + ?Sized> Serialize for WithIconEncoding<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Serializing runs to completion on this thread, within this call
+        let _encoding = Scoped::set(&ENCODING, self.encoding);
+        self.value.serialize(serializer)
+    }
+}
+
+/// Sets a thread-local for the duration of a call, and restores it when dropped, also when the
+/// call panics.
+struct Scoped<T: 'static> {
+    key: &'static LocalKey<Cell<T>>,
+    previous: Option<T>,
+}
+
+impl<T: 'static> Scoped<T> {
+    fn set(key: &'static LocalKey<Cell<T>>, value: T) -> Self {
+        Self {
+            key,
+            previous: Some(key.replace(value)),
+        }
+    }
+}
+
+impl<T: 'static> Drop for Scoped<T> {
+    fn drop(&mut self) {
+        if let Some(previous) = self.previous.take() {
+            self.key.set(previous);
+        }
+    }
+}
+
+/// Run `deserialize`, looking up icons written with [`IconEncoding::Hash`] with `resolve`; icons
+/// it does not know are left out.
+///
+/// ```
+/// # fn run(json: &str) -> serde_json::Result<()> {
+/// use ferrous_focus::{FocusedWindow, resolve_icons};
+///
+/// let window: FocusedWindow = resolve_icons(|_hash| None, || serde_json::from_str(json))?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn resolve_icons<T>(
+    resolve: impl Fn(&str) -> Option<Arc<RgbaImage>> + 'static,
+    deserialize: impl FnOnce() -> T,
+) -> T {
+    let _resolver = Scoped::set(&RESOLVER, Some(Rc::new(resolve) as Rc<IconResolver>));
+    deserialize()
+}
+
+/// Content hash of an icon, as written by [`IconEncoding::Hash`].
+///
+/// Stable across runs and platforms: the 128 bit FNV-1a hash of the size and pixels, in hex.
+pub fn icon_hash(icon: &RgbaImage) -> String {
+    const PRIME: u128 = 0x0000000001000000000000000000013b;
+    let mut hash: u128 = 0x6c62272e07bb014262b821756295c58d;
+    let size = [icon.width().to_le_bytes(), icon.height().to_le_bytes()];
+    for &byte in size.iter().flatten().chain(icon.as_raw()) {
+        hash ^= u128::from(byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    format!("{hash:032x}")
+}
+
+/// Check if the icon is left out of the serialized window.
+pub(crate) fn skip(icon: &Option<Arc<RgbaImage>>) -> bool {
+    icon.is_none() || ENCODING.get() == IconEncoding::Omit
+}
+
+pub(crate) fn serialize<S: Serializer>(
+    icon: &Option<Arc<RgbaImage>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let Some(icon) = icon else {
+        return serializer.serialize_none();
+    };
+    match ENCODING.get() {
+        IconEncoding::Omit => serializer.serialize_none(),
+        IconEncoding::Png => serializer.serialize_str(&STANDARD.encode(png(icon)?)),
+        IconEncoding::DataUri => {
+            let uri = format!("{DATA_URI_PREFIX}{}", STANDARD.encode(png(icon)?));
+            serializer.serialize_str(&uri)
+        }
+        IconEncoding::Rgba => Repr::Rgba {
+            width: icon.width(),
+            height: icon.height(),
+            rgba: Bytes(icon.as_raw().clone()),
+        }
+        .serialize(serializer),
+        IconEncoding::Hash => Repr::Hash {
+            hash: icon_hash(icon),
+        }
+        .serialize(serializer),
+    }
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Arc<RgbaImage>>, D::Error> {
+    let Some(repr) = Option::<Repr>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+    match repr {
+        Repr::Png(encoded) => {
+            let encoded = encoded.strip_prefix(DATA_URI_PREFIX).unwrap_or(&encoded);
+            let png = STANDARD.decode(encoded).map_err(de::Error::custom)?;
+            image::load_from_memory_with_format(&png, ImageFormat::Png)
+                .map(|image| Some(Arc::new(image.to_rgba8())))
+                .map_err(de::Error::custom)
+        }
+        Repr::Rgba {
+            width,
+            height,
+            rgba: Bytes(rgba),
+        } => RgbaImage::from_raw(width, height, rgba)
+            .map(|icon| Some(Arc::new(icon)))
+            .ok_or_else(|| de::Error::custom(format!("Icon pixels do not fill {width}x{height}"))),
+        Repr::Hash { hash } => {
+            // Cells hand out no references; take the resolver out and put a clone back
+            let resolver = RESOLVER.take();
+            RESOLVER.set(resolver.clone());
+            Ok(resolver.and_then(|resolve| resolve(&hash)))
+        }
+    }
+}
+
+/// Encode the icon as PNG.
+fn png<E: serde::ser::Error>(icon: &RgbaImage) -> Result<Vec<u8>, E> {
+    let mut png = Cursor::new(Vec::new());
+    icon.write_to(&mut png, ImageFormat::Png)
+        .map_err(E::custom)?;
+    Ok(png.into_inner())
+}
+
+/// Serialized form of an icon.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Repr {
+    /// A base64 encoded PNG image, or a `data:` URI of one.
+    Png(String),
+    Rgba {
+        width: u32,
+        height: u32,
+        rgba: Bytes,
+    },
+    Hash {
+        hash: String,
+    },
+}
+
+/// Raw bytes, written as bytes rather than as a sequence of numbers where the format has them.
+struct Bytes(Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BytesVisitor;
+
+        impl<'de> de::Visitor<'de> for BytesVisitor {
+            type Value = Bytes;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("icon pixels")
+            }
+
+            fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Bytes, E> {
+                Ok(Bytes(bytes.to_vec()))
+            }
+
+            fn visit_byte_buf<E: de::Error>(self, bytes: Vec<u8>) -> Result<Bytes, E> {
+                Ok(Bytes(bytes))
+            }
+
+            fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Bytes, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(byte) = seq.next_element()? {
+                    bytes.push(byte);
+                }
+                Ok(Bytes(bytes))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FocusedWindow;
+    use std::collections::HashMap;
+
+    fn window() -> FocusedWindow {
+        FocusedWindow {
+            process_id: Some(42),
+            window_title: Some("Editor".to_string()),
+            icon: Some(Arc::new(RgbaImage::from_fn(4, 3, |x, y| {
+                image::Rgba([x as u8 * 60, y as u8 * 80, 200, 255])
+            }))),
+            ..Default::default()
+        }
+    }
+
+    fn to_json(window: &FocusedWindow, encoding: IconEncoding) -> serde_json::Value {
+        serde_json::to_value(encoding.apply(window)).unwrap()
+    }
+
+    fn from_json(json: serde_json::Value) -> FocusedWindow {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_png_and_data_uri_round_trip() {
+        let window = window();
+
+        let json = to_json(&window, IconEncoding::Png);
+        assert!(json["icon"].as_str().unwrap().starts_with("iVBOR"));
+        assert_eq!(from_json(json), window);
+        assert_eq!(
+            serde_json::to_value(&window).unwrap()["icon"],
+            to_json(&window, IconEncoding::Png)["icon"]
+        );
+
+        let json = to_json(&window, IconEncoding::DataUri);
+        assert!(
+            json["icon"]
+                .as_str()
+                .unwrap()
+                .starts_with("data:image/png;base64,iVBOR")
+        );
+        assert_eq!(from_json(json), window);
+    }
+
+    #[test]
+    fn test_rgba_round_trip() {
+        let window = window();
+        let json = to_json(&window, IconEncoding::Rgba);
+        assert_eq!(json["icon"]["width"], 4);
+        assert_eq!(json["icon"]["rgba"].as_array().unwrap().len(), 4 * 3 * 4);
+        assert_eq!(from_json(json), window);
+
+        let mut json = to_json(&window, IconEncoding::Rgba);
+        json["icon"]["height"] = 5.into();
+        assert!(serde_json::from_value::<FocusedWindow>(json).is_err());
+    }
+
+    #[test]
+    fn test_omitted_icon_round_trip() {
+        let json = to_json(&window(), IconEncoding::Omit);
+        assert!(json.get("icon").is_none());
+        assert_eq!(
+            from_json(json),
+            FocusedWindow {
+                icon: None,
+                ..window()
+            }
+        );
+
+        // Nested values are written with the encoding too, and it ends with the wrapper
+        let json = serde_json::to_value(IconEncoding::Omit.apply(&[window()])).unwrap();
+        assert!(json[0].get("icon").is_none());
+        assert!(
+            serde_json::to_value(window())
+                .unwrap()
+                .get("icon")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_hash_round_trip() {
+        let window = window();
+        let icon = window.icon.clone().unwrap();
+        let json = to_json(&window, IconEncoding::Hash);
+        assert_eq!(json["icon"]["hash"], icon_hash(&icon));
+        assert_eq!(icon_hash(&icon).len(), 32);
+
+        let store = HashMap::from([(icon_hash(&icon), icon)]);
+        let resolved = resolve_icons(
+            move |hash| store.get(hash).cloned(),
+            || from_json(json.clone()),
+        );
+        assert_eq!(resolved, window);
+
+        // Without a resolver the icon is left out
+        assert_eq!(from_json(json).icon, None);
+    }
+
+    #[test]
+    fn test_icon_hash_tells_icons_apart() {
+        let icon = window().icon.unwrap();
+        let mut changed = (*icon).clone();
+        changed.put_pixel(0, 0, image::Rgba([0, 0, 0, 0]));
+        let reshaped = RgbaImage::from_raw(3, 4, icon.as_raw().clone()).unwrap();
+        assert_eq!(icon_hash(&icon), icon_hash(&icon.clone()));
+        assert_ne!(icon_hash(&icon), icon_hash(&changed));
+        assert_ne!(icon_hash(&icon), icon_hash(&reshaped));
+    }
+
+    #[test]
+    fn test_panics_leave_encoding_and_resolver_unset() {
+        struct Panicking;
+
+        impl Serialize for Panicking {
+            fn serialize<S: Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+                panic!("serializer failed");
+            }
+        }
+
+        let serialized =
+            std::panic::catch_unwind(|| serde_json::to_value(IconEncoding::Omit.apply(&Panicking)));
+        assert!(serialized.is_err());
+        assert!(serde_json::to_value(window()).unwrap()["icon"].is_string());
+
+        let json = to_json(&window(), IconEncoding::Hash);
+        let resolved = std::panic::catch_unwind(|| {
+            resolve_icons(
+                |_| Some(Arc::new(RgbaImage::new(1, 1))),
+                || -> FocusedWindow { panic!("deserializer failed") },
+            )
+        });
+        assert!(resolved.is_err());
+        assert_eq!(from_json(json).icon, None);
+    }
+}
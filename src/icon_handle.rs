@@ -0,0 +1,238 @@
+use crate::{
+    FerrousFocusResult, FocusTrackerConfig, FocusedWindow, IconMode, WindowId,
+    icon_cache::{self, IconKey},
+};
+use image::RgbaImage;
+use std::{
+    fmt,
+    sync::{Arc, OnceLock},
+};
+
+type IconLoader = dyn Fn() -> FerrousFocusResult<Arc<RgbaImage>> + Send + Sync;
+
+/// The icon of a window, fetched only when [`IconHandle::load`] is called.
+///
+/// Reported in [`FocusedWindow::icon_handle`] with [`IconMode::Lazy`]. Cheap to clone; clones
+/// share the icon once it is loaded, and so do the handles of other windows of the same app
+/// while the icon stays in the cache (see [`IconConfig::cache_bytes`](crate::IconConfig::cache_bytes)). A window keeps its handle until its icon changes, so two
+/// handles are equal only if they load the same icon.
+#[derive(Clone)]
+pub struct IconHandle {
+    window_id: Option<WindowId>,
+    loader: Arc<IconLoader>,
+    icon: Arc<OnceLock<Arc<RgbaImage>>>,
+}
+
+impl IconHandle {
+    /// A handle for the icon of the window with `window_id`, fetched with `load`.
+    pub(crate) fn new<L>(window_id: Option<WindowId>, load: L) -> Self
+    where
+        L: Fn() -> FerrousFocusResult<Arc<RgbaImage>> + Send + Sync + 'static,
+    {
+        Self {
+            window_id,
+            loader: Arc::new(load),
+            icon: Arc::default(),
+        }
+    }
+
+    /// The window the icon belongs to, when the backend reports its ID.
+    pub fn window_id(&self) -> Option<&WindowId> {
+        self.window_id.as_ref()
+    }
+
+    /// Fetch the icon, resized as configured, or return it if it was fetched before.
+    ///
+    /// # Errors
+    /// Returns an error if the window has no icon, or is gone and its icon cannot be fetched
+    /// anymore.
+    pub fn load(&self) -> FerrousFocusResult<Arc<RgbaImage>> {
+        if let Some(icon) = self.icon.get() {
+            return Ok(Arc::clone(icon));
+        }
+        let icon = (self.loader)()?;
+        Ok(Arc::clone(self.icon.get_or_init(|| icon)))
+    }
+
+    /// Check if the icon was fetched already.
+    pub fn is_loaded(&self) -> bool {
+        self.icon.get().is_some()
+    }
+}
+
+impl PartialEq for IconHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.icon, &other.icon)
+    }
+}
+
+impl Eq for IconHandle {}
+
+impl fmt::Debug for IconHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IconHandle")
+            .field("window_id", &self.window_id)
+            .field("loaded", &self.is_loaded())
+            .finish_non_exhaustive()
+    }
+}
+
+/// The icon reported with a focused window, as the configuration asks for it; backends keep it
+/// for the updates of the window that do not change its icon.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WindowIcon {
+    icon: Option<Arc<RgbaImage>>,
+    handle: Option<IconHandle>,
+}
+
+impl WindowIcon {
+    /// Fetch the icon of `window` with `load` now, or wrap `load` in a handle, depending on the
+    /// icon mode. Filtered windows get no icon. Icons of the same app are taken from the cache
+    /// when they are in it.
+    pub(crate) fn fetch<L>(window: &FocusedWindow, config: &FocusTrackerConfig, load: L) -> Self
+    where
+        L: Fn() -> FerrousFocusResult<RgbaImage> + Send + Sync + 'static,
+    {
+        if !config.wants_icon(window) {
+            return Self::default();
+        }
+        let key = IconKey::new(window, &config.icon);
+        let budget = config.icon.cache_bytes;
+        match config.icon.mode {
+            IconMode::Eager => Self {
+                icon: icon_cache::get_or_load(key.as_ref(), budget, load).ok(),
+                handle: None,
+            },
+            IconMode::Lazy => Self {
+                icon: None,
+                handle: Some(IconHandle::new(window.window_id.clone(), move || {
+                    icon_cache::get_or_load(key.as_ref(), budget, &load)
+                })),
+            },
+            IconMode::Disabled => Self::default(),
+        }
+    }
+
+    /// Like [`WindowIcon::fetch`], but replace the cached icon of the app, which has changed.
+    pub(crate) fn refetch<L>(window: &FocusedWindow, config: &FocusTrackerConfig, load: L) -> Self
+    where
+        L: Fn() -> FerrousFocusResult<RgbaImage> + Send + Sync + 'static,
+    {
+        if let Some(key) = IconKey::new(window, &config.icon) {
+            icon_cache::forget(&key);
+        }
+        Self::fetch(window, config, load)
+    }
+
+    /// Report the icon with `window`.
+    pub(crate) fn apply_to(&self, window: &mut FocusedWindow) {
+        window.icon = self.icon.clone();
+        window.icon_handle = self.handle.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FerrousFocusError, IconConfig};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn counting_loader(
+        loads: &Arc<AtomicUsize>,
+    ) -> impl Fn() -> FerrousFocusResult<RgbaImage> + Send + Sync + 'static {
+        let loads = Arc::clone(loads);
+        move || {
+            loads.fetch_add(1, Ordering::SeqCst);
+            Ok(RgbaImage::new(16, 16))
+        }
+    }
+
+    fn counting_handle(loads: &Arc<AtomicUsize>) -> IconHandle {
+        let load = counting_loader(loads);
+        IconHandle::new(Some(WindowId::from_number(7)), move || load().map(Arc::new))
+    }
+
+    fn window(process_name: &str) -> FocusedWindow {
+        FocusedWindow {
+            window_id: Some(WindowId::from_number(7)),
+            process_name: Some(process_name.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_handle_loads_once_on_demand() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let handle = counting_handle(&loads);
+        let clone = handle.clone();
+        assert_eq!(loads.load(Ordering::SeqCst), 0);
+        assert!(!clone.is_loaded());
+
+        assert_eq!(handle.load().unwrap().dimensions(), (16, 16));
+        assert_eq!(clone.load().unwrap().dimensions(), (16, 16));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        assert!(clone.is_loaded());
+        assert_eq!(handle.window_id(), Some(&WindowId::from_number(7)));
+
+        assert_eq!(handle, clone);
+        let other = counting_handle(&loads);
+        assert_ne!(handle, other);
+    }
+
+    #[test]
+    fn test_failed_load_is_retried() {
+        let handle = IconHandle::new(None, || Err(FerrousFocusError::Unsupported));
+        assert!(handle.load().is_err());
+        assert!(!handle.is_loaded());
+    }
+
+    #[test]
+    fn test_icon_modes() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let fetch = |icon: IconConfig| {
+            let config = FocusTrackerConfig::new().with_icon_config(icon.with_cache_bytes(0));
+            let mut window = window("editor");
+            WindowIcon::fetch(&window, &config, counting_loader(&loads)).apply_to(&mut window);
+            window
+        };
+
+        let eager = fetch(IconConfig::new());
+        assert!(eager.icon.is_some() && eager.icon_handle.is_none());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let lazy = fetch(IconConfig::lazy());
+        assert!(lazy.icon.is_none());
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        let handle = lazy.icon_handle.unwrap();
+        assert_eq!(handle.window_id(), Some(&WindowId::from_number(7)));
+        assert!(handle.load().is_ok());
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        let disabled = fetch(IconConfig::disabled());
+        assert!(disabled.icon.is_none() && disabled.icon_handle.is_none());
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_icons_are_cached_per_app() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let config = FocusTrackerConfig::new();
+        let window = window("icon-handle-test");
+        let fetch = || WindowIcon::fetch(&window, &config, counting_loader(&loads)).icon;
+
+        let first = fetch().unwrap();
+        let second = fetch().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+
+        let refetched = WindowIcon::refetch(&window, &config, counting_loader(&loads));
+        assert!(!Arc::ptr_eq(&first, refetched.icon.as_ref().unwrap()));
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+
+        let lazy = FocusTrackerConfig::new().with_icon_config(IconConfig::lazy());
+        let handle = WindowIcon::fetch(&window, &lazy, counting_loader(&loads)).handle;
+        let loaded = handle.unwrap().load().unwrap();
+        assert!(Arc::ptr_eq(&loaded, refetched.icon.as_ref().unwrap()));
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,7 +1,28 @@
+#[cfg(feature = "async")]
+mod async_runtime;
+mod backend;
 mod config;
+#[cfg(feature = "serde")]
+mod config_file;
+mod debounce;
 mod error;
+mod focus_backend;
+mod focus_event;
+mod focus_filter;
+mod focus_hub;
+mod focus_query;
+#[cfg(feature = "async")]
+mod focus_stream;
 mod focus_tracker;
 mod focused_window;
+mod icon_cache;
+#[cfg(feature = "serde")]
+mod icon_encoding;
+mod icon_handle;
+mod stop_signal;
+mod subscription;
+mod tracker_handle;
+mod window_id;
 
 #[cfg(target_os = "macos")]
 #[path = "macos/mod.rs"]
@@ -14,17 +35,47 @@ mod platform;
 #[path = "windows/mod.rs"]
 mod platform;
 
-pub use config::{FocusTrackerConfig, IconConfig};
-pub use error::{FerrousFocusError, FerrousFocusResult};
+#[cfg(feature = "async")]
+pub use async_runtime::AsyncRuntime;
+#[cfg(feature = "smol")]
+pub use async_runtime::SmolRuntime;
+#[cfg(feature = "async")]
+pub use async_runtime::ThreadRuntime;
+#[cfg(feature = "tokio")]
+pub use async_runtime::TokioRuntime;
+pub use backend::{Backend, BackendProbe, Capabilities, available_backends};
+pub use config::{ErrorPolicy, FocusTrackerConfig, IconConfig, IconMode};
+pub use error::{ConfigError, FerrousFocusError, FerrousFocusResult};
+pub use focus_backend::{FocusBackend, FocusEmitter};
+pub use focus_event::{EventMeta, FocusEvent};
+pub use focus_filter::{FilteredWindows, FocusFilter, Pattern, WindowField, WindowRule};
+pub use focus_hub::FocusHub;
+pub use focus_query::FocusQuery;
+#[cfg(feature = "async")]
+pub use focus_stream::FocusStream;
 pub use focus_tracker::FocusTracker;
 pub use focused_window::FocusedWindow;
+#[cfg(feature = "serde")]
+pub use icon_encoding::{IconEncoding, WithIconEncoding, icon_hash, resolve_icons};
+pub use icon_handle::IconHandle;
+#[cfg(feature = "async")]
+pub use subscription::AsyncSubscription;
+pub use subscription::Subscription;
+pub use tracker_handle::TrackerHandle;
+pub use window_id::{AppId, WindowId};
 
 // For platform specific util API's
 pub use platform::utils;
 
 /// Subscribe to focus changes and receive them via a channel
 /// This is a convenience function that creates a new FocusTracker with default config and subscribes to changes
-pub fn subscribe_focus_changes() -> FerrousFocusResult<std::sync::mpsc::Receiver<FocusedWindow>> {
+pub fn subscribe_focus_changes() -> FerrousFocusResult<Subscription> {
     let tracker = FocusTracker::new();
     tracker.subscribe_focus_changes()
 }
+
+/// Get the focused window right now, or `None` if nothing has focus
+/// This is a convenience function that queries once with a new FocusTracker with default config
+pub fn current_focused_window() -> FerrousFocusResult<Option<FocusedWindow>> {
+    FocusTracker::new().current()
+}
@@ -0,0 +1,505 @@
+//! Focus tracking on GNOME Wayland through the companion GNOME Shell extension.
+//!
+//! Mutter does not expose the focused window to Wayland clients, so the extension shipped in
+//! `extensions/gnome-shell` publishes it on the session bus instead: `GetFocusedWindow` returns
+//! the focused window and `FocusChanged` is emitted whenever focus, title or fullscreen state
+//! change. Windows are described as `a{sv}` dictionaries, empty when nothing is focused.
+
+use super::utils;
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    focus_event::{Change, Detection},
+    stop_signal::StopSignal,
+};
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+};
+use tracing::info;
+use zbus::{
+    blocking::{Connection, Proxy, fdo::DBusProxy},
+    names::BusName,
+    zvariant::OwnedValue,
+};
+
+pub const BUS_NAME: &str = "com.eurora_labs.FerrousFocus";
+pub const OBJECT_PATH: &str = "/com/eurora_labs/FerrousFocus";
+pub const INTERFACE: &str = "com.eurora_labs.FerrousFocus1";
+
+/// Window description as sent by the extension.
+type WindowProperties = HashMap<String, OwnedValue>;
+
+pub fn track_focus_with_stop<F>(
+    on_focus: F,
+    stop_signal: &StopSignal,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    run(on_focus, session_bus()?, Some(stop_signal), config)
+}
+
+/// Check that the extension is running on the session bus.
+pub fn probe() -> Result<(), String> {
+    session_bus()
+        .and_then(|connection| ensure_extension(&connection))
+        .map_err(|e| e.to_string())
+}
+
+/// Session bus connection for asking the extension which window is focused, kept open between
+/// queries.
+pub struct GnomeQuery {
+    proxy: Proxy<'static>,
+}
+
+impl GnomeQuery {
+    pub fn connect() -> FerrousFocusResult<Self> {
+        Self::new(&session_bus()?)
+    }
+
+    fn new(connection: &Connection) -> FerrousFocusResult<Self> {
+        ensure_extension(connection)?;
+        let proxy = Proxy::new(connection, BUS_NAME, OBJECT_PATH, INTERFACE).map_err(dbus_error)?;
+        Ok(Self { proxy })
+    }
+
+    /// Get the focused window, or `None` if nothing has focus.
+    pub fn current(&self) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let properties: WindowProperties = self
+            .proxy
+            .call("GetFocusedWindow", &())
+            .map_err(dbus_error)?;
+        Ok(window_from_properties(&properties))
+    }
+}
+
+fn run<F>(
+    on_focus: F,
+    connection: Connection,
+    stop_signal: Option<&StopSignal>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    // ── D-Bus setup ────────────────────────────────────────────────────────────
+    ensure_extension(&connection)?;
+    let proxy = Proxy::new(&connection, BUS_NAME, OBJECT_PATH, INTERFACE).map_err(dbus_error)?;
+
+    // Subscribe before the initial query so no change slips through in between
+    let signals = proxy.receive_signal("FocusChanged").map_err(dbus_error)?;
+    // `None` wakes the event loop up: the signal stream ended, or tracking was stopped
+    let (sender, receiver) = mpsc::channel::<Option<(WindowProperties, Detection)>>();
+    let _waker = stop_signal.map(|stop_signal| {
+        let sender = sender.clone();
+        stop_signal.on_stop(move || {
+            let _ = sender.send(None);
+        })
+    });
+    let forwarder = thread::spawn(move || {
+        for message in signals {
+            let detection = Detection::now();
+            match message.body().deserialize::<WindowProperties>() {
+                Ok(properties) => {
+                    if sender.send(Some((properties, detection))).is_err() {
+                        return;
+                    }
+                }
+                Err(e) => info!("Failed to parse FocusChanged signal: {}", e),
+            }
+        }
+        let _ = sender.send(None);
+    });
+
+    // Report from a separate function, so that the connection is closed however it ends
+    let result = report_changes(on_focus, &proxy, &receiver, stop_signal, config);
+
+    // Closing the connection ends the signal iterator of the forwarding thread
+    drop(proxy);
+    if let Err(e) = connection.close() {
+        info!("Failed to close session bus connection: {}", e);
+    }
+    let _ = forwarder.join();
+
+    result
+}
+
+/// Report the initial focused window, then the changes forwarded by `receiver`, until tracking
+/// is stopped or fails.
+fn report_changes<F>(
+    mut on_focus: F,
+    proxy: &Proxy<'_>,
+    receiver: &Receiver<Option<(WindowProperties, Detection)>>,
+    stop_signal: Option<&StopSignal>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    // Track the last reported window to skip notifications that change nothing we report
+    let mut current: Option<FocusedWindow> = None;
+
+    // ── Get initial focused window ─────────────────────────────────────────────
+    let detection = Detection::now();
+    match proxy.call::<_, _, WindowProperties>("GetFocusedWindow", &()) {
+        Ok(properties) => {
+            if let Some(window) = window_from_properties(&properties) {
+                current = Some(window.clone());
+                on_focus(Change::Focus(window), detection)?;
+            }
+        }
+        Err(e) => info!("Failed to get initial focused window: {}", e),
+    }
+
+    // ── Event loop ─────────────────────────────────────────────────────────────
+    loop {
+        if should_stop(stop_signal) {
+            return Ok(());
+        }
+
+        let (properties, detection) = match receiver.recv_timeout(config.poll_interval) {
+            Ok(Some(signal)) => signal,
+            Ok(None) if should_stop(stop_signal) => return Ok(()),
+            Err(RecvTimeoutError::Timeout) => continue,
+            Ok(None) | Err(RecvTimeoutError::Disconnected) => {
+                return Err(FerrousFocusError::Platform(
+                    "Lost connection to the session bus".to_string(),
+                ));
+            }
+        };
+
+        let window = window_from_properties(&properties);
+        if window == current {
+            // A notification that changes nothing we report
+            continue;
+        }
+
+        let change = match &window {
+            Some(window) => Change::between(current.as_ref(), window.clone()),
+            None => Change::Cleared,
+        };
+        current = window;
+        on_focus(change, detection)?;
+    }
+}
+
+/* ------------------------------------------------------------ */
+/* Helper functions                                              */
+/* ------------------------------------------------------------ */
+
+/// Check if the stop signal is set.
+fn should_stop(stop_signal: Option<&StopSignal>) -> bool {
+    stop_signal.is_some_and(StopSignal::is_stopped)
+}
+
+fn dbus_error(e: zbus::Error) -> FerrousFocusError {
+    FerrousFocusError::Platform(format!("D-Bus error: {e}"))
+}
+
+fn session_bus() -> FerrousFocusResult<Connection> {
+    Connection::session().map_err(dbus_error)
+}
+
+/// Fail with [`FerrousFocusError::GnomeExtensionUnavailable`] unless the extension owns its bus
+/// name.
+fn ensure_extension(connection: &Connection) -> FerrousFocusResult<()> {
+    let name = BusName::try_from(BUS_NAME).map_err(|e| dbus_error(e.into()))?;
+    let has_owner = DBusProxy::new(connection)
+        .and_then(|dbus| Ok(dbus.name_has_owner(name)?))
+        .map_err(dbus_error)?;
+
+    if has_owner {
+        Ok(())
+    } else {
+        Err(FerrousFocusError::GnomeExtensionUnavailable)
+    }
+}
+
+/// Build window info from the extension's description, or `None` if nothing is focused.
+fn window_from_properties(properties: &WindowProperties) -> Option<FocusedWindow> {
+    if properties.is_empty() {
+        return None;
+    }
+
+    let string = |key: &str| {
+        properties
+            .get(key)
+            .and_then(|value| <&str>::try_from(&**value).ok())
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+
+    let process_id = properties
+        .get("pid")
+        .and_then(|value| u32::try_from(&**value).ok());
+    let process_name = process_id.and_then(|pid| {
+        utils::process_name(pid)
+            .inspect_err(|e| info!("Failed to get process name for PID {}: {}", pid, e))
+            .ok()
+    });
+
+    Some(FocusedWindow {
+        window_id: properties
+            .get("id")
+            .and_then(|value| u64::try_from(&**value).ok())
+            .map(WindowId::from_number),
+        process_id,
+        process_name,
+        window_title: string("title"),
+        window_class: string("wm_class"),
+        workspace: string("workspace"),
+        output: None,
+        fullscreen: properties
+            .get("fullscreen")
+            .and_then(|value| bool::try_from(&**value).ok()),
+        xwayland: properties
+            .get("xwayland")
+            .and_then(|value| bool::try_from(&**value).ok()),
+        marks: None,
+        filtered: false,
+        icon: None,
+        icon_handle: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::test_bus::TestBus;
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+    use zbus::zvariant::Value;
+
+    /// Stand-in for the GNOME Shell extension.
+    struct MockExtension {
+        focused: WindowProperties,
+    }
+
+    #[zbus::interface(name = "com.eurora_labs.FerrousFocus1")]
+    impl MockExtension {
+        fn get_focused_window(&self) -> WindowProperties {
+            self.focused
+                .iter()
+                .map(|(key, value)| (key.clone(), value.try_clone().unwrap()))
+                .collect()
+        }
+    }
+
+    fn properties(id: u64, title: &str, wm_class: &str, workspace: &str) -> WindowProperties {
+        [
+            ("id", Value::from(id)),
+            ("title", Value::from(title)),
+            ("wm_class", Value::from(wm_class)),
+            ("workspace", Value::from(workspace)),
+            ("pid", Value::from(std::process::id())),
+            ("fullscreen", Value::from(false)),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_owned(), value.try_into().unwrap()))
+        .collect()
+    }
+
+    fn emit_focus_changed(extension: &Connection, properties: &WindowProperties) {
+        extension
+            .emit_signal(
+                None::<()>,
+                OBJECT_PATH,
+                INTERFACE,
+                "FocusChanged",
+                &(properties,),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn test_missing_extension() {
+        let Some(bus) = TestBus::start("gnome-missing") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let stop_signal = StopSignal::new();
+        let result = run(
+            |_, _| Ok(()),
+            bus.connect(),
+            Some(&stop_signal),
+            &FocusTrackerConfig::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(FerrousFocusError::GnomeExtensionUnavailable)
+        ));
+    }
+
+    #[test]
+    fn test_queries_focused_window() {
+        let Some(bus) = TestBus::start("gnome-query") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let _extension = bus
+            .builder()
+            .name(BUS_NAME)
+            .unwrap()
+            .serve_at(
+                OBJECT_PATH,
+                MockExtension {
+                    focused: properties(1, "Terminal", "org.gnome.Ptyxis", "Workspace 1"),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let query = GnomeQuery::new(&bus.connect()).unwrap();
+        for _ in 0..2 {
+            let window = query.current().unwrap().unwrap();
+            assert_eq!(window.window_title.as_deref(), Some("Terminal"));
+            assert_eq!(window.workspace.as_deref(), Some("Workspace 1"));
+        }
+    }
+
+    #[test]
+    fn test_tracks_focus_signals() {
+        let Some(bus) = TestBus::start("gnome-signals") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let extension = bus
+            .builder()
+            .name(BUS_NAME)
+            .unwrap()
+            .serve_at(
+                OBJECT_PATH,
+                MockExtension {
+                    focused: properties(1, "Terminal", "org.gnome.Ptyxis", "Workspace 1"),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let stop_signal = Arc::new(StopSignal::new());
+        let tracker = {
+            let changes = Arc::clone(&changes);
+            let stop_signal = Arc::clone(&stop_signal);
+            let connection = bus.connect();
+            thread::spawn(move || {
+                let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+                run(
+                    |change, _| {
+                        changes.lock()?.push(change);
+                        Ok(())
+                    },
+                    connection,
+                    Some(&stop_signal),
+                    &config,
+                )
+            })
+        };
+
+        // Wait for the initial window so the tracker is subscribed
+        while changes.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let browser = properties(2, "Firefox", "firefox", "Workspace 2");
+        emit_focus_changed(&extension, &browser);
+        // Repeated notification for the same window is ignored
+        emit_focus_changed(&extension, &browser);
+        emit_focus_changed(
+            &extension,
+            &properties(2, "Firefox*", "firefox", "Workspace 2"),
+        );
+        emit_focus_changed(&extension, &WindowProperties::new());
+        emit_focus_changed(&extension, &properties(3, "Docs", "firefox", "Workspace 2"));
+
+        while changes.lock().unwrap().len() < 5 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        stop_signal.stop();
+        assert!(tracker.join().unwrap().is_ok());
+
+        let changes = changes.lock().unwrap().clone();
+        assert!(matches!(
+            changes[..],
+            [
+                Change::Focus(_),
+                Change::Focus(_),
+                Change::Update(_),
+                Change::Cleared,
+                Change::Focus(_)
+            ]
+        ));
+
+        let windows: Vec<_> = changes
+            .into_iter()
+            .filter_map(Change::into_window)
+            .collect();
+        let titles: Vec<_> = windows.iter().map(|w| w.window_title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            [
+                Some("Terminal"),
+                Some("Firefox"),
+                Some("Firefox*"),
+                Some("Docs")
+            ]
+        );
+
+        let browser = &windows[1];
+        assert_eq!(browser.window_id, Some(WindowId::from_number(2)));
+        assert_eq!(windows[2].window_id, browser.window_id);
+        assert_ne!(windows[3].window_id, browser.window_id);
+        assert_eq!(browser.window_class.as_deref(), Some("firefox"));
+        assert_eq!(browser.workspace.as_deref(), Some("Workspace 2"));
+        assert_eq!(browser.fullscreen, Some(false));
+        assert_eq!(browser.process_id, Some(std::process::id()));
+    }
+    #[test]
+    fn test_closes_connection_when_handler_fails() {
+        let Some(bus) = TestBus::start("gnome-handler-error") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let _extension = bus
+            .builder()
+            .name(BUS_NAME)
+            .unwrap()
+            .serve_at(
+                OBJECT_PATH,
+                MockExtension {
+                    focused: properties(1, "Terminal", "org.gnome.Ptyxis", "Workspace 1"),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap();
+
+        let connection = bus.connect();
+        let tracker_name = connection.unique_name().unwrap().to_owned();
+        let result = run(
+            |_, _| Err(FerrousFocusError::new("Handler failed")),
+            connection,
+            None,
+            &FocusTrackerConfig::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(FerrousFocusError::Error(message)) if message == "Handler failed"
+        ));
+
+        // The forwarding thread let go of the connection, so it left the bus
+        let observer = bus.connect();
+        let dbus = DBusProxy::new(&observer).unwrap();
+        assert!(!dbus.name_has_owner(tracker_name.into()).unwrap());
+    }
+}
@@ -0,0 +1,557 @@
+//! Focus tracking through Hyprland's IPC sockets.
+//!
+//! Hyprland announces focus and title changes on its event socket (`.socket2.sock`), one
+//! `EVENT>>DATA` line per event. The details of the active window are then queried on the request
+//! socket (`.socket.sock`) with `j/activewindow`.
+
+use super::utils;
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    focus_event::{Change, Detection},
+    stop_signal::StopSignal,
+};
+use serde_json::Value;
+use std::{
+    env,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tracing::info;
+
+const EVENT_SOCKET: &str = ".socket2.sock";
+const REQUEST_SOCKET: &str = ".socket.sock";
+
+/// How long to wait for the reply to a request before giving up on the compositor.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Find the socket directory of the running Hyprland instance.
+///
+/// Returns `None` when `HYPRLAND_INSTANCE_SIGNATURE` is not set.
+pub fn socket_dir() -> Option<PathBuf> {
+    let signature = env::var_os("HYPRLAND_INSTANCE_SIGNATURE").filter(|sig| !sig.is_empty())?;
+
+    // Hyprland 0.40+ keeps its sockets under XDG_RUNTIME_DIR, older releases under /tmp
+    let candidates: Vec<PathBuf> = env::var_os("XDG_RUNTIME_DIR")
+        .map(|runtime_dir| PathBuf::from(runtime_dir).join("hypr"))
+        .into_iter()
+        .chain(Some(PathBuf::from("/tmp/hypr")))
+        .map(|base| base.join(&signature))
+        .collect();
+
+    candidates
+        .iter()
+        .find(|dir| dir.join(EVENT_SOCKET).exists())
+        .or(candidates.first())
+        .cloned()
+}
+
+pub fn track_focus_with_stop<F>(
+    on_focus: F,
+    socket_dir: &Path,
+    stop_signal: &StopSignal,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    run(on_focus, socket_dir, Some(stop_signal), config)
+}
+
+/// Get the focused window, or `None` if nothing has focus.
+///
+/// Hyprland closes request connections after every reply, so there is no connection to keep.
+pub fn current(socket_dir: &Path) -> FerrousFocusResult<Option<FocusedWindow>> {
+    let request_socket = socket_dir.join(REQUEST_SOCKET);
+    Ok(query_active_window(&request_socket)?.map(|mut active| {
+        resolve_output(&request_socket, &mut active, None);
+        active.window
+    }))
+}
+
+fn run<F>(
+    mut on_focus: F,
+    socket_dir: &Path,
+    stop_signal: Option<&StopSignal>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    // ── IPC setup ──────────────────────────────────────────────────────────────
+    let events = connect(&socket_dir.join(EVENT_SOCKET))?;
+    events
+        .set_read_timeout(Some(config.poll_interval))
+        .map_err(|e| FerrousFocusError::Platform(format!("Failed to set read timeout: {e}")))?;
+    let mut events = BufReader::new(events);
+    let request_socket = socket_dir.join(REQUEST_SOCKET);
+
+    // Track the state of the last reported window to detect what changed
+    let mut current: Option<ActiveWindow> = None;
+
+    // ── Get initial focused window ─────────────────────────────────────────────
+    let detection = Detection::now();
+    match query_active_window(&request_socket) {
+        Ok(Some(mut active)) => {
+            resolve_output(&request_socket, &mut active, None);
+            let focused_window = active.window.clone();
+            current = Some(active);
+            on_focus(Change::Focus(focused_window), detection)?;
+        }
+        Ok(None) => {}
+        Err(e) => info!("Failed to get initial active window: {}", e),
+    }
+
+    // ── Event loop ─────────────────────────────────────────────────────────────
+    // Shutting the socket down ends a pending read as soon as tracking is stopped
+    let _waker = match (stop_signal, events.get_ref().try_clone()) {
+        (Some(stop_signal), Ok(stream)) => Some(stop_signal.on_stop(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        })),
+        _ => None,
+    };
+    let mut line = Vec::new();
+    loop {
+        if should_stop(stop_signal) {
+            break;
+        }
+
+        // Partial lines stay in `line` until the rest arrives
+        let read = events.read_until(b'\n', &mut line);
+        if should_stop(stop_signal) {
+            break;
+        }
+        match read {
+            Ok(0) => {
+                return Err(FerrousFocusError::Platform(
+                    "Hyprland event socket closed".to_string(),
+                ));
+            }
+            Ok(_) if line.ends_with(b"\n") => {}
+            Ok(_) => continue,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => {
+                return Err(FerrousFocusError::Platform(format!(
+                    "Failed to read Hyprland event: {e}"
+                )));
+            }
+        }
+
+        let detection = Detection::now();
+        let event = String::from_utf8_lossy(&line).trim_end().to_owned();
+        line.clear();
+        let Some((name, data)) = event.split_once(">>") else {
+            continue;
+        };
+
+        match name {
+            // `activewindowv2>>ADDRESS`, with an empty address when nothing is focused
+            "activewindowv2" if data.trim_matches(',').is_empty() => {
+                if current.take().is_some() {
+                    on_focus(Change::Cleared, detection)?;
+                }
+                continue;
+            }
+            "activewindowv2" | "fullscreen" => {}
+            // `windowtitle>>ADDRESS` and `windowtitlev2>>ADDRESS,TITLE`
+            "windowtitle" | "windowtitlev2" => {
+                let address = data.split(',').next().unwrap_or_default();
+                if !current.as_ref().is_some_and(|c| c.has_address(address)) {
+                    continue;
+                }
+            }
+            _ => continue,
+        }
+
+        let mut active = match query_active_window(&request_socket) {
+            Ok(Some(active)) => active,
+            Ok(None) => continue,
+            Err(e) => {
+                info!("Failed to get active window: {}", e);
+                continue;
+            }
+        };
+
+        resolve_output(&request_socket, &mut active, current.as_ref());
+        if current
+            .as_ref()
+            .is_some_and(|c| c.address == active.address && c.window == active.window)
+        {
+            // Nothing we report has changed (e.g. activewindowv2 for the same window)
+            continue;
+        }
+
+        let change = match &current {
+            Some(current) if current.address == active.address => {
+                Change::Update(active.window.clone())
+            }
+            _ => Change::Focus(active.window.clone()),
+        };
+        current = Some(active);
+        on_focus(change, detection)?;
+    }
+
+    Ok(())
+}
+
+/* ------------------------------------------------------------ */
+/* Helper structs and functions                                  */
+/* ------------------------------------------------------------ */
+
+/// The active window as reported by `j/activewindow`.
+#[derive(Debug, Clone)]
+struct ActiveWindow {
+    /// Window address, e.g. `0x55d0f2a4b8c0`
+    address: String,
+    /// Monitor ID as reported by Hyprland, resolved to a name in `window.output`
+    monitor_id: Option<i64>,
+    window: FocusedWindow,
+}
+
+impl ActiveWindow {
+    /// Compare with an address from an event, which lacks the `0x` prefix.
+    fn has_address(&self, address: &str) -> bool {
+        self.address.trim_start_matches("0x") == address.trim_start_matches("0x")
+    }
+}
+
+/// Check if the stop signal is set.
+fn should_stop(stop_signal: Option<&StopSignal>) -> bool {
+    stop_signal.is_some_and(StopSignal::is_stopped)
+}
+
+fn connect(socket: &Path) -> FerrousFocusResult<UnixStream> {
+    UnixStream::connect(socket).map_err(|e| {
+        FerrousFocusError::Platform(format!(
+            "Failed to connect to Hyprland socket {}: {e}",
+            socket.display()
+        ))
+    })
+}
+
+/// Send a single request to the request socket and parse the JSON reply.
+///
+/// Fails if the reply stalls for longer than [`REPLY_TIMEOUT`].
+fn request(socket: &Path, command: &str) -> FerrousFocusResult<Value> {
+    let mut stream = connect(socket)?;
+    // Never block for good on a compositor that does not answer
+    stream
+        .set_read_timeout(Some(REPLY_TIMEOUT))
+        .map_err(|e| FerrousFocusError::Platform(format!("Failed to set read timeout: {e}")))?;
+    stream
+        .write_all(command.as_bytes())
+        .map_err(|e| FerrousFocusError::Platform(format!("Failed to send `{command}`: {e}")))?;
+
+    // Hyprland closes the connection after replying
+    let mut reply = Vec::new();
+    stream.read_to_end(&mut reply).map_err(|e| match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+            FerrousFocusError::Platform(format!("Hyprland did not reply to `{command}`"))
+        }
+        _ => FerrousFocusError::Platform(format!("Failed to read `{command}`: {e}")),
+    })?;
+
+    serde_json::from_slice(&reply)
+        .map_err(|e| FerrousFocusError::Platform(format!("Failed to parse `{command}`: {e}")))
+}
+
+/// Query the active window, returning `None` when no window is focused.
+fn query_active_window(socket: &Path) -> FerrousFocusResult<Option<ActiveWindow>> {
+    let reply = request(socket, "j/activewindow")?;
+    let Some(address) = reply["address"].as_str() else {
+        // Hyprland replies with `{}` when nothing is focused
+        return Ok(None);
+    };
+
+    let process_id = reply["pid"]
+        .as_i64()
+        .and_then(|pid| u32::try_from(pid).ok());
+    let process_name = process_id.and_then(|pid| {
+        utils::process_name(pid)
+            .inspect_err(|e| info!("Failed to get process name for PID {}: {}", pid, e))
+            .ok()
+    });
+    let monitor_id = reply["monitor"].as_i64();
+
+    // Older releases report fullscreen as a bool, newer ones as a mode number
+    let fullscreen = reply["fullscreen"]
+        .as_bool()
+        .or_else(|| reply["fullscreen"].as_u64().map(|mode| mode != 0));
+
+    Ok(Some(ActiveWindow {
+        address: address.to_owned(),
+        monitor_id,
+        window: FocusedWindow {
+            window_id: u64::from_str_radix(address.trim_start_matches("0x"), 16)
+                .ok()
+                .map(WindowId::from_number),
+            process_id,
+            process_name,
+            window_title: reply["title"].as_str().map(str::to_owned),
+            window_class: reply["class"]
+                .as_str()
+                .filter(|class| !class.is_empty())
+                .map(str::to_owned),
+            workspace: reply["workspace"]["name"].as_str().map(str::to_owned),
+            output: None,
+            fullscreen,
+            xwayland: reply["xwayland"].as_bool(),
+            marks: None,
+            filtered: false,
+            icon: None,
+            icon_handle: None,
+        },
+    }))
+}
+
+/// Fill in the output name of the active window, reusing the name already known for the
+/// previous window when it is on the same monitor.
+fn resolve_output(socket: &Path, active: &mut ActiveWindow, previous: Option<&ActiveWindow>) {
+    active.window.output = match previous {
+        Some(previous) if previous.monitor_id == active.monitor_id => {
+            previous.window.output.clone()
+        }
+        _ => active.monitor_id.map(|monitor_id| {
+            monitor_name(socket, monitor_id).unwrap_or_else(|| monitor_id.to_string())
+        }),
+    };
+}
+
+/// Look up the name of a monitor (e.g. `DP-1`) from its ID.
+fn monitor_name(socket: &Path, monitor_id: i64) -> Option<String> {
+    let monitors = request(socket, "j/monitors")
+        .inspect_err(|e| info!("Failed to get monitors: {}", e))
+        .ok()?;
+
+    monitors
+        .as_array()?
+        .iter()
+        .find(|monitor| monitor["id"].as_i64() == Some(monitor_id))
+        .and_then(|monitor| monitor["name"].as_str())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::{
+        os::unix::net::UnixListener,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    /// A stand-in for a Hyprland instance: answers `j/activewindow` and `j/monitors` on the
+    /// request socket, and plays back scripted events on the event socket, switching the active
+    /// window before each one.
+    struct FakeHyprland {
+        dir: PathBuf,
+    }
+
+    impl FakeHyprland {
+        fn start(name: &str, initial: Value, script: Vec<(Value, &'static str)>) -> Self {
+            let dir =
+                env::temp_dir().join(format!("ferrous-focus-{}-{}", name, std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+
+            let active = Arc::new(Mutex::new(initial));
+
+            let requests = UnixListener::bind(dir.join(REQUEST_SOCKET)).unwrap();
+            let requests_active = Arc::clone(&active);
+            thread::spawn(move || {
+                for stream in requests.incoming() {
+                    let Ok(mut stream) = stream else { break };
+                    let mut command = [0u8; 64];
+                    let n = stream.read(&mut command).unwrap_or(0);
+                    let reply = match &command[..n] {
+                        b"j/activewindow" => requests_active.lock().unwrap().clone(),
+                        b"j/monitors" => json!([
+                            {"id": 0, "name": "eDP-1"},
+                            {"id": 1, "name": "DP-2"},
+                        ]),
+                        _ => json!({}),
+                    };
+                    let _ = stream.write_all(reply.to_string().as_bytes());
+                }
+            });
+
+            let events = UnixListener::bind(dir.join(EVENT_SOCKET)).unwrap();
+            thread::spawn(move || {
+                let Ok((mut stream, _)) = events.accept() else {
+                    return;
+                };
+                for (window, event) in script {
+                    thread::sleep(Duration::from_millis(20));
+                    *active.lock().unwrap() = window;
+                    // Split each line to exercise partial reads
+                    let (head, tail) = event.split_at(event.len() / 2);
+                    let _ = stream.write_all(head.as_bytes());
+                    thread::sleep(Duration::from_millis(15));
+                    let _ = stream.write_all(format!("{tail}\n").as_bytes());
+                }
+                // Keep the socket open until the tracker goes away
+                let _ = stream.read(&mut [0u8; 1]);
+            });
+
+            Self { dir }
+        }
+    }
+
+    impl Drop for FakeHyprland {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn window(address: &str, class: &str, title: &str, monitor: u32) -> Value {
+        json!({
+            "address": address,
+            "class": class,
+            "title": title,
+            "pid": std::process::id(),
+            "workspace": {"id": monitor + 1, "name": format!("{}", monitor + 1)},
+            "monitor": monitor,
+            "fullscreen": 0,
+            "xwayland": false,
+        })
+    }
+
+    fn collect_changes(dir: &Path, count: usize) -> Vec<Change> {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let stop_signal = StopSignal::new();
+        let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+
+        let result = track_focus_with_stop(
+            |change, _| {
+                let mut changes = changes.lock()?;
+                changes.push(change);
+                if changes.len() >= count {
+                    stop_signal.stop();
+                }
+                Ok(())
+            },
+            dir,
+            &stop_signal,
+            &config,
+        );
+        assert!(result.is_ok(), "tracking failed: {result:?}");
+
+        Arc::try_unwrap(changes).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_tracks_focus_and_title_events() {
+        let terminal = window("0xa1", "kitty", "~", 0);
+        let browser = window("0xb2", "firefox", "Mozilla Firefox", 1);
+        let docs = window("0xb2", "firefox", "Docs - Mozilla Firefox", 1);
+
+        let hyprland = FakeHyprland::start(
+            "hyprland-events",
+            terminal,
+            vec![
+                (browser.clone(), "activewindow>>firefox,Mozilla Firefox"),
+                (browser.clone(), "activewindowv2>>b2"),
+                // Duplicate notification for an unchanged window is ignored
+                (browser, "activewindowv2>>b2"),
+                // Title change of another window is ignored
+                (docs.clone(), "windowtitlev2>>a1,~/src"),
+                (docs, "windowtitlev2>>b2,Docs - Mozilla Firefox"),
+            ],
+        );
+
+        let changes = collect_changes(&hyprland.dir, 3);
+        assert!(matches!(
+            changes[..],
+            [Change::Focus(_), Change::Focus(_), Change::Update(_)]
+        ));
+        let windows: Vec<_> = changes
+            .into_iter()
+            .filter_map(Change::into_window)
+            .collect();
+        let titles: Vec<_> = windows.iter().map(|w| w.window_title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            [
+                Some("~"),
+                Some("Mozilla Firefox"),
+                Some("Docs - Mozilla Firefox")
+            ]
+        );
+
+        assert_eq!(windows[0].output.as_deref(), Some("eDP-1"));
+        let browser = &windows[2];
+        assert_eq!(browser.window_id, Some(WindowId::from_number(0xb2)));
+        assert_eq!(windows[1].window_id, browser.window_id);
+        assert_ne!(windows[0].window_id, browser.window_id);
+        assert_eq!(browser.window_class.as_deref(), Some("firefox"));
+        assert_eq!(browser.workspace.as_deref(), Some("2"));
+        assert_eq!(browser.output.as_deref(), Some("DP-2"));
+        assert_eq!(browser.fullscreen, Some(false));
+        assert_eq!(browser.process_id, Some(std::process::id()));
+    }
+
+    #[test]
+    fn test_queries_focused_window() {
+        let hyprland = FakeHyprland::start(
+            "hyprland-query",
+            window("0xb2", "firefox", "Mozilla Firefox", 1),
+            Vec::new(),
+        );
+        let window = current(&hyprland.dir).unwrap().unwrap();
+        assert_eq!(window.window_title.as_deref(), Some("Mozilla Firefox"));
+        assert_eq!(window.output.as_deref(), Some("DP-2"));
+
+        let hyprland = FakeHyprland::start("hyprland-query-empty", json!({}), Vec::new());
+        assert!(current(&hyprland.dir).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ignores_empty_focus() {
+        let terminal = window("0xa1", "kitty", "~", 0);
+        let hyprland = FakeHyprland::start(
+            "hyprland-empty",
+            json!({}),
+            vec![
+                (json!({}), "activewindowv2>>,"),
+                (terminal, "activewindowv2>>a1"),
+            ],
+        );
+
+        let changes = collect_changes(&hyprland.dir, 1);
+        let Change::Focus(window) = &changes[0] else {
+            panic!("Unexpected change: {:?}", changes[0]);
+        };
+        assert_eq!(window.window_title.as_deref(), Some("~"));
+    }
+    #[test]
+    fn test_gives_up_on_unanswered_request() {
+        let dir = env::temp_dir().join(format!(
+            "ferrous-focus-hyprland-silent-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let listener = UnixListener::bind(dir.join(REQUEST_SOCKET)).unwrap();
+        // Accepts connections and never answers
+        thread::spawn(move || {
+            let mut connections = Vec::new();
+            for stream in listener.incoming() {
+                connections.push(stream);
+            }
+        });
+
+        let started = std::time::Instant::now();
+        let result = current(&dir);
+        assert!(matches!(
+            result,
+            Err(FerrousFocusError::Platform(message)) if message.contains("did not reply")
+        ));
+        assert!(started.elapsed() < REPLY_TIMEOUT * 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
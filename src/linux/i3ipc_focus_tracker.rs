@@ -0,0 +1,797 @@
+//! Focus tracking over the i3 IPC protocol, which is spoken by both sway and i3.
+//!
+//! The tracker subscribes to `window` and `workspace` events on one connection and uses a second
+//! connection to look up the workspace and output of the focused container in the layout tree.
+//! Switching to an empty workspace leaves nothing focused.
+
+use super::{utils, xorg_focus_tracker::X11WindowProperties};
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    focus_event::{Change, Detection},
+    icon_handle::WindowIcon,
+    stop_signal::StopSignal,
+};
+use serde_json::Value;
+use std::{
+    env,
+    io::{ErrorKind, Read, Write},
+    net::Shutdown,
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tracing::info;
+
+const MAGIC: &[u8] = b"i3-ipc";
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+const MESSAGE_SUBSCRIBE: u32 = 2;
+const MESSAGE_GET_TREE: u32 = 4;
+const EVENT_WORKSPACE: u32 = 0x8000_0000;
+const EVENT_WINDOW: u32 = 0x8000_0003;
+
+/// How long to wait for the reply to a request before giving up on the compositor.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Find the IPC socket of a running sway or i3 instance.
+pub fn socket_path() -> Option<PathBuf> {
+    ["SWAYSOCK", "I3SOCK"]
+        .into_iter()
+        .filter_map(env::var_os)
+        .find(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+pub fn track_focus_with_stop<F>(
+    on_focus: F,
+    socket: &Path,
+    stop_signal: &StopSignal,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    run(on_focus, socket, Some(stop_signal), config)
+}
+
+/// IPC connection for asking which window is focused, kept open between queries.
+pub struct I3IpcQuery {
+    commands: IpcConnection,
+    x11: Option<Arc<X11WindowProperties>>,
+}
+
+impl I3IpcQuery {
+    pub fn connect(socket: &Path) -> FerrousFocusResult<Self> {
+        Ok(Self {
+            commands: IpcConnection::connect(socket)?,
+            x11: X11WindowProperties::connect().ok().map(Arc::new),
+        })
+    }
+
+    /// Get the focused window, or `None` if nothing has focus.
+    pub fn current(
+        &mut self,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let tree = self.commands.request(MESSAGE_GET_TREE, b"")?;
+        Ok(
+            find_container(&tree, &|node| is_focused(node)).map(|(container, placement)| {
+                let mut focused_window =
+                    window_from_container(container, &placement, self.x11.as_deref());
+                window_icon(container, &focused_window, self.x11.as_ref(), config)
+                    .apply_to(&mut focused_window);
+                focused_window
+            }),
+        )
+    }
+}
+
+fn run<F>(
+    mut on_focus: F,
+    socket: &Path,
+    stop_signal: Option<&StopSignal>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    // ── IPC setup ──────────────────────────────────────────────────────────────
+    let mut events = IpcConnection::connect(socket)?;
+    let mut commands = IpcConnection::connect(socket)?;
+    events.set_read_timeout(config.poll_interval)?;
+    // Shutting the sockets down ends a pending read as soon as tracking is stopped, also while
+    // waiting for a reply
+    let _waker = stop_signal.and_then(|stop_signal| {
+        let events = events.stream.try_clone().ok()?;
+        let commands = commands.stream.try_clone().ok()?;
+        Some(stop_signal.on_stop(move || {
+            let _ = events.shutdown(Shutdown::Both);
+            let _ = commands.shutdown(Shutdown::Both);
+        }))
+    });
+
+    let reply = match events.request(MESSAGE_SUBSCRIBE, br#"["window","workspace"]"#) {
+        Err(_) if should_stop(stop_signal) => return Ok(()),
+        reply => reply?,
+    };
+    if reply.get("success").and_then(Value::as_bool) != Some(true) {
+        return Err(FerrousFocusError::Platform(format!(
+            "Failed to subscribe to window events: {reply}"
+        )));
+    }
+
+    // X11 windows (i3, or XWayland clients under sway) carry their icon and sometimes their PID
+    // as X11 properties only.
+    let x11 = X11WindowProperties::connect().ok().map(Arc::new);
+
+    // Track the currently focused container to filter title changes
+    let mut current_container: Option<i64> = None;
+    // Cache the icon for the currently focused window (only fetch on app change)
+    let mut cached_icon = WindowIcon::default();
+
+    // ── Get initial focused window ─────────────────────────────────────────────
+    let detection = Detection::now();
+    let tree = match commands.request(MESSAGE_GET_TREE, b"") {
+        Err(_) if should_stop(stop_signal) => return Ok(()),
+        tree => tree?,
+    };
+    if let Some((container, placement)) = find_container(&tree, &|node| is_focused(node)) {
+        let mut focused_window = window_from_container(container, &placement, x11.as_deref());
+        cached_icon = window_icon(container, &focused_window, x11.as_ref(), config);
+        cached_icon.apply_to(&mut focused_window);
+
+        current_container = container_id(container);
+        on_focus(Change::Focus(focused_window), detection)?;
+    }
+
+    // ── Event loop ─────────────────────────────────────────────────────────────
+    loop {
+        if should_stop(stop_signal) {
+            break;
+        }
+
+        let message = events.next_message();
+        if should_stop(stop_signal) {
+            break;
+        }
+        let Some((message_type, payload)) = message? else {
+            continue;
+        };
+        if !matches!(message_type, EVENT_WINDOW | EVENT_WORKSPACE) {
+            continue;
+        }
+        let detection = Detection::now();
+
+        let event: Value = match serde_json::from_slice(&payload) {
+            Ok(event) => event,
+            Err(e) => {
+                info!("Failed to parse IPC event: {}", e);
+                continue;
+            }
+        };
+
+        if message_type == EVENT_WORKSPACE {
+            // Focusing a workspace with windows is followed by a window event; an empty one
+            // has nothing to focus
+            if event["change"].as_str() == Some("focus")
+                && current_container.is_some()
+                && event["current"].is_object()
+                && find_container(&event["current"], &|_| true).is_none()
+            {
+                current_container = None;
+                cached_icon = WindowIcon::default();
+                on_focus(Change::Cleared, detection)?;
+            }
+            continue;
+        }
+
+        let container = &event["container"];
+        let id = container_id(container);
+
+        let is_focus_change = match event["change"].as_str() {
+            Some("focus") => true,
+            Some("title" | "fullscreen_mode" | "mark")
+                if id.is_some() && id == current_container =>
+            {
+                false
+            }
+            Some("close") if id.is_some() && id == current_container => {
+                current_container = None;
+                cached_icon = WindowIcon::default();
+                on_focus(Change::Cleared, detection)?;
+                continue;
+            }
+            _ => continue,
+        };
+
+        // The window event does not say where the container lives, so look it up in the tree
+        let placement = match commands.request(MESSAGE_GET_TREE, b"") {
+            Ok(tree) => find_container(&tree, &|node| container_id(node) == id)
+                .map(|(_, placement)| placement)
+                .unwrap_or_default(),
+            Err(e) => {
+                info!("Failed to get layout tree: {}", e);
+                Placement::default()
+            }
+        };
+
+        let mut focused_window = window_from_container(container, &placement, x11.as_deref());
+        // Only fetch icon when the focused app changes, not on title changes
+        if is_focus_change {
+            cached_icon = window_icon(container, &focused_window, x11.as_ref(), config);
+        }
+        cached_icon.apply_to(&mut focused_window);
+
+        current_container = id;
+        let change = if is_focus_change {
+            Change::Focus(focused_window)
+        } else {
+            Change::Update(focused_window)
+        };
+        on_focus(change, detection)?;
+    }
+
+    Ok(())
+}
+
+/* ------------------------------------------------------------ */
+/* Helper structs and functions                                  */
+/* ------------------------------------------------------------ */
+
+/// Check if the stop signal is set.
+fn should_stop(stop_signal: Option<&StopSignal>) -> bool {
+    stop_signal.is_some_and(StopSignal::is_stopped)
+}
+
+/// A connection to the IPC socket that frames and unframes i3 IPC messages.
+struct IpcConnection {
+    stream: UnixStream,
+    buffer: Vec<u8>,
+}
+
+impl IpcConnection {
+    fn connect(socket: &Path) -> FerrousFocusResult<Self> {
+        let stream = UnixStream::connect(socket).map_err(|e| {
+            FerrousFocusError::Platform(format!(
+                "Failed to connect to IPC socket {}: {e}",
+                socket.display()
+            ))
+        })?;
+
+        let connection = Self {
+            stream,
+            buffer: Vec::new(),
+        };
+        // Never block for good on a compositor that does not answer
+        connection.set_read_timeout(REPLY_TIMEOUT)?;
+        Ok(connection)
+    }
+
+    fn set_read_timeout(&self, timeout: Duration) -> FerrousFocusResult<()> {
+        self.stream
+            .set_read_timeout(Some(timeout))
+            .map_err(|e| FerrousFocusError::Platform(format!("Failed to set read timeout: {e}")))
+    }
+
+    /// Send a message and wait for its reply, skipping any events that arrive in between.
+    ///
+    /// Fails if no reply arrives within [`REPLY_TIMEOUT`].
+    fn request(&mut self, message_type: u32, payload: &[u8]) -> FerrousFocusResult<Value> {
+        self.send(message_type, payload)?;
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        loop {
+            match self.next_message()? {
+                Some((reply_type, reply)) if reply_type == message_type => {
+                    return serde_json::from_slice(&reply).map_err(|e| {
+                        FerrousFocusError::Platform(format!("Failed to parse IPC reply: {e}"))
+                    });
+                }
+                _ if Instant::now() >= deadline => {
+                    return Err(FerrousFocusError::Platform(
+                        "Compositor did not reply to IPC request".to_string(),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, message_type: u32, payload: &[u8]) -> FerrousFocusResult<()> {
+        let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+        message.extend_from_slice(MAGIC);
+        message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&message_type.to_ne_bytes());
+        message.extend_from_slice(payload);
+
+        self.stream
+            .write_all(&message)
+            .map_err(|e| FerrousFocusError::Platform(format!("Failed to send IPC message: {e}")))
+    }
+
+    /// Read the next complete message.
+    ///
+    /// Returns `Ok(None)` when the read timeout expires before a full message has arrived; any
+    /// partial data is kept for the next call.
+    fn next_message(&mut self) -> FerrousFocusResult<Option<(u32, Vec<u8>)>> {
+        loop {
+            if let Some(message) = self.take_message()? {
+                return Ok(Some(message));
+            }
+
+            let mut chunk = [0u8; 4096];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => {
+                    return Err(FerrousFocusError::Platform(
+                        "IPC socket closed by the compositor".to_string(),
+                    ));
+                }
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(None);
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    return Err(FerrousFocusError::Platform(format!(
+                        "Failed to read from IPC socket: {e}"
+                    )));
+                }
+            }
+        }
+    }
+
+    /// Split a complete message off the front of the buffer, if there is one.
+    fn take_message(&mut self) -> FerrousFocusResult<Option<(u32, Vec<u8>)>> {
+        if self.buffer.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        if !self.buffer.starts_with(MAGIC) {
+            return Err(FerrousFocusError::Platform(
+                "Invalid IPC message header".to_string(),
+            ));
+        }
+
+        let length = u32::from_ne_bytes(self.buffer[6..10].try_into().unwrap_or_default());
+        let message_type = u32::from_ne_bytes(self.buffer[10..14].try_into().unwrap_or_default());
+        let end = HEADER_LEN + length as usize;
+        if self.buffer.len() < end {
+            return Ok(None);
+        }
+
+        let payload = self.buffer[HEADER_LEN..end].to_vec();
+        self.buffer.drain(..end);
+        Ok(Some((message_type, payload)))
+    }
+}
+
+/// Workspace and output that contain a container.
+#[derive(Debug, Clone, Default)]
+struct Placement {
+    workspace: Option<String>,
+    output: Option<String>,
+}
+
+/// Walk the layout tree depth-first and return the first window container matching `predicate`,
+/// together with the workspace and output that contain it.
+fn find_container<'a>(
+    tree: &'a Value,
+    predicate: &dyn Fn(&Value) -> bool,
+) -> Option<(&'a Value, Placement)> {
+    fn walk<'a>(
+        node: &'a Value,
+        predicate: &dyn Fn(&Value) -> bool,
+        mut placement: Placement,
+    ) -> Option<(&'a Value, Placement)> {
+        let name = node["name"].as_str().map(str::to_owned);
+        match node["type"].as_str() {
+            Some("output") => placement.output = name,
+            Some("workspace") => placement.workspace = name,
+            Some("con" | "floating_con") if predicate(node) => return Some((node, placement)),
+            _ => {}
+        }
+
+        ["nodes", "floating_nodes"]
+            .iter()
+            .filter_map(|key| node[key].as_array())
+            .flatten()
+            .find_map(|child| walk(child, predicate, placement.clone()))
+    }
+
+    walk(tree, predicate, Placement::default())
+}
+
+fn is_focused(node: &Value) -> bool {
+    node["focused"].as_bool() == Some(true)
+}
+
+fn container_id(container: &Value) -> Option<i64> {
+    container["id"].as_i64()
+}
+
+/// The X11 window ID of a container, if it is backed by an X11 window.
+fn x11_window(container: &Value) -> Option<u32> {
+    container["window"]
+        .as_u64()
+        .and_then(|window| u32::try_from(window).ok())
+}
+
+/// Build window info (without the icon) from a container of the layout tree.
+fn window_from_container(
+    container: &Value,
+    placement: &Placement,
+    x11: Option<&X11WindowProperties>,
+) -> FocusedWindow {
+    // sway reports the PID directly, i3 only knows the X11 window
+    let process_id = container["pid"]
+        .as_u64()
+        .and_then(|pid| u32::try_from(pid).ok())
+        .or_else(|| {
+            x11.zip(x11_window(container))
+                .and_then(|(x11, w)| x11.process_id(w))
+        });
+
+    let process_name = process_id.and_then(|pid| {
+        utils::process_name(pid)
+            .inspect_err(|e| info!("Failed to get process name for PID {}: {}", pid, e))
+            .ok()
+    });
+
+    let window_class = container["app_id"]
+        .as_str()
+        .or_else(|| container["window_properties"]["class"].as_str())
+        .map(str::to_owned);
+
+    FocusedWindow {
+        // Containers keep their ID for as long as they exist
+        window_id: container_id(container).map(|id| WindowId::from_number(id as u64)),
+        process_id,
+        process_name,
+        window_title: container["name"].as_str().map(str::to_owned),
+        window_class,
+        workspace: placement.workspace.clone(),
+        output: placement.output.clone(),
+        fullscreen: container["fullscreen_mode"].as_u64().map(|mode| mode != 0),
+        // Only sway reports the shell, i3 is X11 only
+        xwayland: container["shell"].as_str().map(|shell| shell == "xwayland"),
+        marks: container["marks"].as_array().map(|marks| {
+            marks
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect()
+        }),
+        filtered: false,
+        icon: None,
+        icon_handle: None,
+    }
+}
+
+/// Get the icon of a container; only X11 windows publish one. Filtered windows get none.
+fn window_icon(
+    container: &Value,
+    window: &FocusedWindow,
+    x11: Option<&Arc<X11WindowProperties>>,
+    config: &FocusTrackerConfig,
+) -> WindowIcon {
+    let Some((x11, x11_window)) = x11.zip(x11_window(container)) else {
+        return WindowIcon::default();
+    };
+    let x11 = Arc::clone(x11);
+    let icon_config = config.icon.clone();
+    WindowIcon::fetch(window, config, move || x11.icon(x11_window, &icon_config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::{
+        os::unix::net::UnixListener,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    /// A stand-in for the sway IPC socket that replies to `SUBSCRIBE` and `GET_TREE` and then
+    /// pushes the scripted events, with their event types, on the subscribed connection.
+    struct FakeCompositor {
+        path: PathBuf,
+    }
+
+    impl FakeCompositor {
+        fn start(name: &str, tree: Value, events: Vec<(u32, Value)>) -> Self {
+            let path = env::temp_dir().join(format!(
+                "ferrous-focus-{}-{}.sock",
+                name,
+                std::process::id()
+            ));
+            let _ = std::fs::remove_file(&path);
+            let listener = UnixListener::bind(&path).unwrap();
+
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let tree = tree.clone();
+                    let events = events.clone();
+                    thread::spawn(move || serve(stream, tree, events));
+                }
+            });
+
+            Self { path }
+        }
+    }
+
+    impl Drop for FakeCompositor {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+
+    fn serve(stream: UnixStream, tree: Value, events: Vec<(u32, Value)>) {
+        let mut conn = IpcConnection {
+            stream,
+            buffer: Vec::new(),
+        };
+        while let Ok(Some((message_type, _))) = conn.next_message() {
+            match message_type {
+                MESSAGE_SUBSCRIBE => {
+                    conn.send(MESSAGE_SUBSCRIBE, br#"{"success":true}"#)
+                        .unwrap();
+                    for (event_type, event) in &events {
+                        thread::sleep(Duration::from_millis(20));
+                        conn.send(*event_type, event.to_string().as_bytes())
+                            .unwrap();
+                    }
+                }
+                MESSAGE_GET_TREE => {
+                    conn.send(MESSAGE_GET_TREE, tree.to_string().as_bytes())
+                        .unwrap();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn container(id: i64, title: &str, app_id: &str, focused: bool) -> Value {
+        json!({
+            "id": id,
+            "type": "con",
+            "name": title,
+            "app_id": app_id,
+            "pid": std::process::id(),
+            "focused": focused,
+            "fullscreen_mode": 0,
+            "nodes": [],
+            "floating_nodes": [],
+        })
+    }
+
+    fn window_event(change: &str, container: Value) -> (u32, Value) {
+        (
+            EVENT_WINDOW,
+            json!({"change": change, "container": container}),
+        )
+    }
+
+    fn layout() -> Value {
+        json!({
+            "id": 1,
+            "type": "root",
+            "name": "root",
+            "nodes": [{
+                "id": 2,
+                "type": "output",
+                "name": "HDMI-A-1",
+                "nodes": [
+                    {
+                        "id": 3,
+                        "type": "workspace",
+                        "name": "1",
+                        "nodes": [container(10, "Editor", "code", true)],
+                    },
+                    {
+                        "id": 4,
+                        "type": "workspace",
+                        "name": "2: web",
+                        "nodes": [],
+                        "floating_nodes": [container(11, "Browser", "firefox", false)],
+                    },
+                ],
+            }],
+        })
+    }
+
+    fn collect_changes(socket: &Path, count: usize) -> Vec<Change> {
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let stop_signal = StopSignal::new();
+        let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+
+        let result = track_focus_with_stop(
+            |change, _| {
+                let mut changes = changes.lock()?;
+                changes.push(change);
+                if changes.len() >= count {
+                    stop_signal.stop();
+                }
+                Ok(())
+            },
+            socket,
+            &stop_signal,
+            &config,
+        );
+        assert!(result.is_ok(), "tracking failed: {result:?}");
+
+        Arc::try_unwrap(changes).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_parses_framed_messages_across_reads() {
+        let (client, server) = UnixStream::pair().unwrap();
+        let mut sender = IpcConnection {
+            stream: server,
+            buffer: Vec::new(),
+        };
+        let mut receiver = IpcConnection {
+            stream: client,
+            buffer: Vec::new(),
+        };
+
+        sender.send(EVENT_WINDOW, br#"{"change":"focus"}"#).unwrap();
+        sender.send(MESSAGE_GET_TREE, b"{}").unwrap();
+
+        let (message_type, payload) = receiver.next_message().unwrap().unwrap();
+        assert_eq!(message_type, EVENT_WINDOW);
+        assert_eq!(payload, br#"{"change":"focus"}"#);
+        let (message_type, payload) = receiver.next_message().unwrap().unwrap();
+        assert_eq!(message_type, MESSAGE_GET_TREE);
+        assert_eq!(payload, b"{}");
+    }
+
+    #[test]
+    fn test_find_focused_container_placement() {
+        let tree = layout();
+        let (container, placement) = find_container(&tree, &is_focused).unwrap();
+
+        assert_eq!(container_id(container), Some(10));
+        assert_eq!(placement.workspace.as_deref(), Some("1"));
+        assert_eq!(placement.output.as_deref(), Some("HDMI-A-1"));
+    }
+
+    #[test]
+    fn test_tracks_focus_and_title_events() {
+        let events = vec![
+            window_event("focus", container(11, "Browser", "firefox", true)),
+            // Title change of an unfocused window is ignored
+            window_event("title", container(10, "Editor*", "code", false)),
+            window_event("title", container(11, "Docs - Browser", "firefox", true)),
+            window_event("mark", {
+                let mut browser = container(11, "Docs - Browser", "firefox", true);
+                browser["marks"] = json!(["web", "_scratch"]);
+                browser
+            }),
+        ];
+        let compositor = FakeCompositor::start("sway-events", layout(), events);
+
+        let changes = collect_changes(&compositor.path, 4);
+        assert!(matches!(
+            changes[..],
+            [
+                Change::Focus(_),
+                Change::Focus(_),
+                Change::Update(_),
+                Change::Update(_)
+            ]
+        ));
+        let windows: Vec<_> = changes
+            .into_iter()
+            .filter_map(Change::into_window)
+            .collect();
+        let titles: Vec<_> = windows.iter().map(|w| w.window_title.as_deref()).collect();
+        assert_eq!(
+            titles,
+            [
+                Some("Editor"),
+                Some("Browser"),
+                Some("Docs - Browser"),
+                Some("Docs - Browser")
+            ]
+        );
+
+        let browser = &windows[1];
+        assert_eq!(browser.window_id, Some(WindowId::from_number(11)));
+        assert_eq!(windows[2].window_id, browser.window_id);
+        assert_ne!(windows[0].window_id, browser.window_id);
+        assert_eq!(browser.window_class.as_deref(), Some("firefox"));
+        assert_eq!(browser.workspace.as_deref(), Some("2: web"));
+        assert_eq!(browser.output.as_deref(), Some("HDMI-A-1"));
+        assert_eq!(browser.fullscreen, Some(false));
+        assert_eq!(browser.process_id, Some(std::process::id()));
+        assert!(browser.process_name.is_some());
+        assert_eq!(browser.marks, None);
+        assert_eq!(
+            windows[3].marks.as_deref(),
+            Some(&["web".to_string(), "_scratch".to_string()][..])
+        );
+    }
+
+    #[test]
+    fn test_queries_focused_window() {
+        let compositor = FakeCompositor::start("sway-query", layout(), Vec::new());
+        let mut query = I3IpcQuery::connect(&compositor.path).unwrap();
+        let config = FocusTrackerConfig::new();
+
+        for _ in 0..2 {
+            let window = query.current(&config).unwrap().unwrap();
+            assert_eq!(window.window_title.as_deref(), Some("Editor"));
+            assert_eq!(window.workspace.as_deref(), Some("1"));
+        }
+    }
+
+    #[test]
+    fn test_stops_without_events() {
+        let compositor = FakeCompositor::start("sway-idle", layout(), Vec::new());
+        let changes = collect_changes(&compositor.path, 1);
+        assert_eq!(changes.len(), 1);
+    }
+
+    #[test]
+    fn test_empty_workspace_clears_focus() {
+        let workspace = |name: &str, nodes: Value| {
+            (
+                EVENT_WORKSPACE,
+                json!({
+                    "change": "focus",
+                    "current": {"id": 5, "type": "workspace", "name": name, "nodes": nodes},
+                }),
+            )
+        };
+        let events = vec![
+            // Followed by a window event for the window there
+            workspace("2: web", json!([container(11, "Browser", "firefox", true)])),
+            window_event("focus", container(11, "Browser", "firefox", true)),
+            workspace("3", json!([])),
+        ];
+        let compositor = FakeCompositor::start("sway-workspaces", layout(), events);
+
+        let changes = collect_changes(&compositor.path, 3);
+        assert!(matches!(
+            changes[..],
+            [Change::Focus(_), Change::Focus(_), Change::Cleared]
+        ));
+    }
+
+    #[test]
+    fn test_stop_interrupts_unanswered_subscribe() {
+        let path = env::temp_dir().join(format!(
+            "ferrous-focus-sway-silent-{}.sock",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        // Accepts connections and never answers
+        thread::spawn(move || {
+            let mut connections = Vec::new();
+            for stream in listener.incoming() {
+                connections.push(stream);
+            }
+        });
+
+        let stop_signal = Arc::new(StopSignal::new());
+        let tracker = {
+            let stop_signal = Arc::clone(&stop_signal);
+            let path = path.clone();
+            thread::spawn(move || {
+                let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+                track_focus_with_stop(|_, _| Ok(()), &path, &stop_signal, &config)
+            })
+        };
+
+        thread::sleep(Duration::from_millis(100));
+        let stopped = std::time::Instant::now();
+        stop_signal.stop();
+        assert!(tracker.join().unwrap().is_ok());
+        assert!(stopped.elapsed() < Duration::from_secs(1));
+        let _ = std::fs::remove_file(&path);
+    }
+}
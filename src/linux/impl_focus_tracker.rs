@@ -1,9 +1,18 @@
-use super::{utils::wayland_detect, xorg_focus_tracker};
-use crate::{FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow};
-use std::sync::atomic::AtomicBool;
-
-#[cfg(feature = "async")]
-use std::future::Future;
+use super::{
+    gnome_focus_tracker::{self, GnomeQuery},
+    hyprland_focus_tracker,
+    i3ipc_focus_tracker::{self, I3IpcQuery},
+    kde_focus_tracker::{self, KdeQuery},
+    utils::{Compositor, SessionInfo, wayland_detect},
+    xorg_focus_tracker::{self, XorgQuery},
+};
+use crate::{
+    Backend, FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow,
+    focus_event::{Change, Detection},
+    stop_signal::StopSignal,
+};
+use std::path::PathBuf;
+use tracing::info;
 
 #[derive(Debug, Clone)]
 pub struct ImplFocusTracker {}
@@ -15,69 +24,245 @@ impl ImplFocusTracker {
 }
 
 impl ImplFocusTracker {
-    pub fn track_focus<F>(&self, on_focus: F, config: &FocusTrackerConfig) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        if wayland_detect() {
-            // Wayland is not supported for the time being
-            Err(FerrousFocusError::Unsupported)
-        } else {
-            xorg_focus_tracker::track_focus(on_focus, config)
-        }
-    }
-
     pub fn track_focus_with_stop<F>(
         &self,
         on_focus: F,
-        stop_signal: &AtomicBool,
+        backend: Backend,
+        stop_signal: &StopSignal,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
-        if wayland_detect() {
-            // Wayland is not supported for the time being
-            Err(FerrousFocusError::Unsupported)
-        } else {
-            xorg_focus_tracker::track_focus_with_stop(on_focus, stop_signal, config)
-        }
+        LinuxBackend::resolve(backend)?.track_focus_with_stop(on_focus, stop_signal, config)
     }
+}
 
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async<F, Fut>(
-        &self,
-        on_focus: F,
+/// A connection to a backend for asking which window is focused, kept open between queries.
+pub enum ImplFocusQuery {
+    Xorg(XorgQuery),
+    I3Ipc(I3IpcQuery),
+    /// The IPC sockets in the given directory.
+    Hyprland(PathBuf),
+    Gnome(GnomeQuery),
+    Kde(Box<KdeQuery>),
+}
+
+impl ImplFocusQuery {
+    pub fn connect(backend: Backend) -> FerrousFocusResult<Self> {
+        Ok(match LinuxBackend::resolve(backend)? {
+            LinuxBackend::Xorg => Self::Xorg(XorgQuery::connect(false)?),
+            LinuxBackend::XWayland => Self::Xorg(XorgQuery::connect(true)?),
+            LinuxBackend::I3Ipc(socket) => Self::I3Ipc(I3IpcQuery::connect(&socket)?),
+            LinuxBackend::Hyprland(socket_dir) => Self::Hyprland(socket_dir),
+            LinuxBackend::Gnome => Self::Gnome(GnomeQuery::connect()?),
+            LinuxBackend::Kde => Self::Kde(Box::new(KdeQuery::connect()?)),
+        })
+    }
+
+    pub fn current(
+        &mut self,
         config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
-    {
+    ) -> FerrousFocusResult<Option<FocusedWindow>> {
+        match self {
+            Self::Xorg(query) => query.current(config),
+            Self::I3Ipc(query) => query.current(config),
+            Self::Hyprland(socket_dir) => hyprland_focus_tracker::current(socket_dir),
+            Self::Gnome(query) => query.current(),
+            Self::Kde(query) => query.current(),
+        }
+    }
+}
+
+/// Backends of this platform, in the order they are preferred.
+pub const BACKENDS: &[Backend] = &[
+    Backend::Hyprland,
+    Backend::I3Ipc,
+    Backend::Gnome,
+    Backend::Kde,
+    Backend::XWayland,
+    Backend::X11,
+];
+
+/// Pick the backend that matches the running session.
+pub fn detect() -> FerrousFocusResult<Backend> {
+    if hyprland_focus_tracker::socket_dir().is_some() {
+        Ok(Backend::Hyprland)
+    } else if i3ipc_focus_tracker::socket_path().is_some() {
+        Ok(Backend::I3Ipc)
+    } else if !wayland_detect() {
+        Ok(Backend::X11)
+    } else {
+        wayland_backend(&SessionInfo::detect(), gnome_focus_tracker::probe)
+    }
+}
+
+/// Pick the backend for a Wayland session without compositor IPC sockets; `probe_gnome` checks
+/// that the GNOME Shell extension is running.
+fn wayland_backend(
+    session: &SessionInfo,
+    probe_gnome: impl FnOnce() -> Result<(), String>,
+) -> FerrousFocusResult<Backend> {
+    let gnome = session.compositor == Some(Compositor::Mutter) || session.is_desktop("gnome");
+    let kde = session.compositor == Some(Compositor::KWin) || session.is_desktop("kde");
+
+    if gnome {
+        match probe_gnome() {
+            // Without the extension, X11 clients are all that can be tracked
+            Err(e) if session.xwayland => {
+                info!(
+                    "GNOME backend unavailable ({}), falling back to XWayland",
+                    e
+                );
+                Ok(Backend::XWayland)
+            }
+            // Without XWayland either, tracking fails with a hint to install the extension
+            _ => Ok(Backend::Gnome),
+        }
+    } else if kde {
+        Ok(Backend::Kde)
+    } else if session.xwayland {
+        // No native backend for this compositor, fall back to its X11 clients
+        Ok(Backend::XWayland)
+    } else {
+        Err(FerrousFocusError::Unsupported)
+    }
+}
+
+/// Check if a backend can run in this session, or explain why not.
+pub fn probe(backend: Backend) -> Result<(), String> {
+    let wayland_session = || {
         if wayland_detect() {
-            // Wayland is not supported for the time being
-            Err(FerrousFocusError::Unsupported)
+            Ok(())
         } else {
-            xorg_focus_tracker::track_focus_async(on_focus, config).await
+            Err("not a Wayland session".to_string())
+        }
+    };
+
+    match backend {
+        Backend::X11 if wayland_detect() => {
+            Err("Wayland session, X11 clients are covered by the XWayland backend".to_string())
         }
+        Backend::X11 => xorg_focus_tracker::probe(),
+        Backend::XWayland => wayland_session().and_then(|()| xorg_focus_tracker::probe()),
+        Backend::I3Ipc => match i3ipc_focus_tracker::socket_path() {
+            Some(socket) if socket.exists() => Ok(()),
+            Some(socket) => Err(format!("IPC socket {} does not exist", socket.display())),
+            None => Err("neither SWAYSOCK nor I3SOCK is set".to_string()),
+        },
+        Backend::Hyprland => match hyprland_focus_tracker::socket_dir() {
+            Some(socket_dir) if socket_dir.exists() => Ok(()),
+            Some(socket_dir) => Err(format!(
+                "socket directory {} does not exist",
+                socket_dir.display()
+            )),
+            None => Err("HYPRLAND_INSTANCE_SIGNATURE is not set".to_string()),
+        },
+        Backend::Gnome => wayland_session().and_then(|()| gnome_focus_tracker::probe()),
+        Backend::Kde => wayland_session().and_then(|()| kde_focus_tracker::probe()),
+        _ => Err("not supported on this platform".to_string()),
     }
+}
 
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async_with_stop<F, Fut>(
+/// A backend together with what it needs to connect.
+#[derive(Debug, Clone)]
+enum LinuxBackend {
+    Xorg,
+    XWayland,
+    /// The i3 IPC socket at the given path.
+    I3Ipc(PathBuf),
+    /// The IPC sockets in the given directory.
+    Hyprland(PathBuf),
+    Gnome,
+    Kde,
+}
+
+impl LinuxBackend {
+    fn resolve(backend: Backend) -> FerrousFocusResult<Self> {
+        let missing = |what: &str| {
+            FerrousFocusError::BackendUnavailable(format!("{backend}: {what} is not set"))
+        };
+
+        match backend {
+            Backend::X11 => Ok(Self::Xorg),
+            Backend::XWayland => Ok(Self::XWayland),
+            Backend::I3Ipc => i3ipc_focus_tracker::socket_path()
+                .map(Self::I3Ipc)
+                .ok_or_else(|| missing("SWAYSOCK/I3SOCK")),
+            Backend::Hyprland => hyprland_focus_tracker::socket_dir()
+                .map(Self::Hyprland)
+                .ok_or_else(|| missing("HYPRLAND_INSTANCE_SIGNATURE")),
+            Backend::Gnome => Ok(Self::Gnome),
+            Backend::Kde => Ok(Self::Kde),
+            _ => Err(FerrousFocusError::Unsupported),
+        }
+    }
+
+    fn track_focus_with_stop<F>(
         &self,
         on_focus: F,
-        stop_signal: &AtomicBool,
+        stop_signal: &StopSignal,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
-        if wayland_detect() {
-            // Wayland is not supported for the time being
-            Err(FerrousFocusError::Unsupported)
-        } else {
-            xorg_focus_tracker::track_focus_async_with_stop(on_focus, stop_signal, config).await
+        match self {
+            Self::Xorg => {
+                xorg_focus_tracker::track_focus_with_stop(on_focus, false, stop_signal, config)
+            }
+            Self::XWayland => {
+                xorg_focus_tracker::track_focus_with_stop(on_focus, true, stop_signal, config)
+            }
+            Self::I3Ipc(socket) => {
+                i3ipc_focus_tracker::track_focus_with_stop(on_focus, socket, stop_signal, config)
+            }
+            Self::Hyprland(socket_dir) => hyprland_focus_tracker::track_focus_with_stop(
+                on_focus,
+                socket_dir,
+                stop_signal,
+                config,
+            ),
+            Self::Gnome => {
+                gnome_focus_tracker::track_focus_with_stop(on_focus, stop_signal, config)
+            }
+            Self::Kde => kde_focus_tracker::track_focus_with_stop(on_focus, stop_signal, config),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::utils::SessionType;
+
+    fn session(desktop: &str, xwayland: bool) -> SessionInfo {
+        SessionInfo {
+            session_type: SessionType::Wayland,
+            current_desktop: vec![desktop.to_string()],
+            xwayland,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_gnome_without_extension_falls_back_to_xwayland() {
+        let extension_running = || Ok(());
+        let extension_missing = || Err("extension is not running".to_string());
+
+        let backend = wayland_backend(&session("GNOME", true), extension_running);
+        assert_eq!(backend.unwrap(), Backend::Gnome);
+        let backend = wayland_backend(&session("GNOME", true), extension_missing);
+        assert_eq!(backend.unwrap(), Backend::XWayland);
+        // Without XWayland the GNOME backend reports the missing extension
+        let backend = wayland_backend(&session("GNOME", false), extension_missing);
+        assert_eq!(backend.unwrap(), Backend::Gnome);
+
+        let backend = wayland_backend(&session("KDE", true), || unreachable!());
+        assert_eq!(backend.unwrap(), Backend::Kde);
+        let backend = wayland_backend(&session("niri", true), || unreachable!());
+        assert_eq!(backend.unwrap(), Backend::XWayland);
+        let backend = wayland_backend(&session("niri", false), || unreachable!());
+        assert!(matches!(backend, Err(FerrousFocusError::Unsupported)));
+    }
+}
@@ -0,0 +1,743 @@
+//! Focus tracking on KDE Plasma Wayland through KWin scripting.
+//!
+//! KWin does not expose the active window to Wayland clients, but it runs JavaScript loaded
+//! through `org.kde.kwin.Scripting`. The tracker exports a callback object on its own session
+//! bus connection, loads `kwin_focus_script.js` addressed to it, and the script reports every
+//! activation (and caption or fullscreen change of the active window) as a JSON description.
+//! The script is unloaded again when tracking stops.
+
+use super::utils;
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    focus_event::{Change, Detection},
+    stop_signal::StopSignal,
+};
+use serde_json::Value;
+use std::{
+    env,
+    fs::OpenOptions,
+    hash::{BuildHasher, RandomState},
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+    },
+    time::Duration,
+};
+use tracing::info;
+use zbus::{
+    blocking::{Connection, Proxy, fdo::DBusProxy},
+    message::Header,
+    names::{BusName, OwnedUniqueName},
+};
+
+const KWIN_SERVICE: &str = "org.kde.KWin";
+const SCRIPTING_PATH: &str = "/Scripting";
+const SCRIPTING_INTERFACE: &str = "org.kde.kwin.Scripting";
+const SCRIPT_INTERFACE: &str = "org.kde.kwin.Script";
+
+const OBJECT_PATH: &str = "/com/eurora_labs/FerrousFocus/KWin";
+const INTERFACE: &str = "com.eurora_labs.FerrousFocus1.KWin";
+
+const SCRIPT: &str = include_str!("kwin_focus_script.js");
+
+/// How long a query waits for the script to report the active window for the first time.
+const FIRST_REPORT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Distinguishes the scripts of several trackers in one process.
+static SCRIPT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+pub fn track_focus_with_stop<F>(
+    on_focus: F,
+    stop_signal: &StopSignal,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    run(on_focus, session_bus()?, Some(stop_signal), config)
+}
+
+/// Check that KWin is reachable on the session bus.
+pub fn probe() -> Result<(), String> {
+    let connection = session_bus().map_err(|e| e.to_string())?;
+    let has_owner = BusName::try_from(KWIN_SERVICE)
+        .map_err(zbus::Error::from)
+        .and_then(|name| Ok(DBusProxy::new(&connection)?.name_has_owner(name)?))
+        .map_err(|e| e.to_string())?;
+
+    if has_owner {
+        Ok(())
+    } else {
+        Err(format!("{KWIN_SERVICE} is not on the session bus"))
+    }
+}
+
+/// A focus script kept loaded in KWin between queries, which always knows the active window.
+pub struct KdeQuery {
+    connection: Connection,
+    receiver: Receiver<Option<Report>>,
+    script: Option<KWinScript>,
+    /// The active window as last reported; `None` until the script reported for the first time.
+    current: Option<Option<FocusedWindow>>,
+}
+
+impl KdeQuery {
+    pub fn connect() -> FerrousFocusResult<Self> {
+        Self::new(session_bus()?)
+    }
+
+    fn new(connection: Connection) -> FerrousFocusResult<Self> {
+        let (sender, receiver) = mpsc::channel();
+        Callback::export(&connection, sender)?;
+        let script = KWinScript::load(&connection)?;
+
+        Ok(Self {
+            connection,
+            receiver,
+            script: Some(script),
+            current: None,
+        })
+    }
+
+    /// Get the focused window, or `None` if nothing has focus.
+    pub fn current(&mut self) -> FerrousFocusResult<Option<FocusedWindow>> {
+        loop {
+            let description = match self.current {
+                // The script reports right after loading
+                None => match self.receiver.recv_timeout(FIRST_REPORT_TIMEOUT) {
+                    Ok(report) => report,
+                    Err(RecvTimeoutError::Timeout) => {
+                        return Err(FerrousFocusError::Platform(
+                            "KWin script did not report the active window".to_string(),
+                        ));
+                    }
+                    Err(RecvTimeoutError::Disconnected) => None,
+                },
+                Some(_) => match self.receiver.try_recv() {
+                    Ok(report) => report,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => None,
+                },
+            };
+
+            let Some((description, _)) = description else {
+                return Err(FerrousFocusError::Platform(
+                    "Lost connection to the session bus".to_string(),
+                ));
+            };
+            match serde_json::from_str::<Value>(&description) {
+                Ok(description) => self.current = Some(window_from_description(&description)),
+                Err(e) => info!("Failed to parse KWin window description: {}", e),
+            }
+        }
+
+        Ok(self.current.clone().flatten())
+    }
+}
+
+impl Drop for KdeQuery {
+    fn drop(&mut self) {
+        if let Some(script) = self.script.take() {
+            script.unload(&self.connection);
+        }
+        if let Err(e) = self
+            .connection
+            .object_server()
+            .remove::<Callback, _>(OBJECT_PATH)
+        {
+            info!("Failed to remove KWin callback object: {}", e);
+        }
+    }
+}
+
+fn run<F>(
+    mut on_focus: F,
+    connection: Connection,
+    stop_signal: Option<&StopSignal>,
+    config: &FocusTrackerConfig,
+) -> FerrousFocusResult<()>
+where
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
+{
+    // ── D-Bus setup ────────────────────────────────────────────────────────────
+    // Export the callback object before loading the script, which reports right away
+    // `None` wakes the event loop up when tracking is stopped
+    let (sender, receiver) = mpsc::channel::<Option<Report>>();
+    let _waker = stop_signal.map(|stop_signal| {
+        let sender = sender.clone();
+        stop_signal.on_stop(move || {
+            let _ = sender.send(None);
+        })
+    });
+    Callback::export(&connection, sender)?;
+
+    // The initial focused window is the first report of the script
+    let script = KWinScript::load(&connection)?;
+
+    // Track the last reported window to skip notifications that change nothing we report
+    let mut current: Option<FocusedWindow> = None;
+
+    // ── Event loop ─────────────────────────────────────────────────────────────
+    let result = loop {
+        if should_stop(stop_signal) {
+            break Ok(());
+        }
+
+        let (description, detection) = match receiver.recv_timeout(config.poll_interval) {
+            Ok(Some(report)) => report,
+            Ok(None) | Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                break Err(FerrousFocusError::Platform(
+                    "Lost connection to the session bus".to_string(),
+                ));
+            }
+        };
+
+        let window = match serde_json::from_str::<Value>(&description) {
+            Ok(description) => window_from_description(&description),
+            Err(e) => {
+                info!("Failed to parse KWin window description: {}", e);
+                continue;
+            }
+        };
+        if window == current {
+            // A notification that changes nothing we report
+            continue;
+        }
+
+        let change = match &window {
+            Some(window) => Change::between(current.as_ref(), window.clone()),
+            None => Change::Cleared,
+        };
+        current = window;
+        // Leave the loop rather than return, so that the script is unloaded on errors too
+        if let Err(e) = on_focus(change, detection) {
+            break Err(e);
+        }
+    };
+
+    script.unload(&connection);
+    if let Err(e) = connection
+        .object_server()
+        .remove::<Callback, _>(OBJECT_PATH)
+    {
+        info!("Failed to remove KWin callback object: {}", e);
+    }
+
+    result
+}
+
+/* ------------------------------------------------------------ */
+/* Helper structs and functions                                 */
+/* ------------------------------------------------------------ */
+
+/// A window description reported by the KWin script, and when it arrived.
+type Report = (String, Detection);
+
+/// D-Bus object the KWin script calls back into.
+struct Callback {
+    sender: Sender<Option<Report>>,
+    /// The unique bus name of KWin, the only client allowed to report.
+    kwin: OwnedUniqueName,
+}
+
+impl Callback {
+    /// Export the callback object on `connection`, accepting reports from KWin only.
+    fn export(connection: &Connection, sender: Sender<Option<Report>>) -> FerrousFocusResult<()> {
+        let kwin = BusName::try_from(KWIN_SERVICE)
+            .map_err(zbus::Error::from)
+            .and_then(|name| Ok(DBusProxy::new(connection)?.get_name_owner(name)?))
+            .map_err(dbus_error)?;
+        connection
+            .object_server()
+            .at(OBJECT_PATH, Callback { sender, kwin })
+            .map_err(dbus_error)?;
+        Ok(())
+    }
+}
+
+#[zbus::interface(name = "com.eurora_labs.FerrousFocus1.KWin")]
+impl Callback {
+    fn window_activated(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        description: String,
+    ) -> zbus::fdo::Result<()> {
+        // Any client on the session bus can call us; only KWin reports focus
+        if header.sender() != Some(&*self.kwin) {
+            return Err(zbus::fdo::Error::AccessDenied(format!(
+                "Only {KWIN_SERVICE} may report focus"
+            )));
+        }
+        // The receiver is gone once tracking stopped
+        let _ = self.sender.send(Some((description, Detection::now())));
+        Ok(())
+    }
+}
+
+/// A focus script loaded into KWin.
+struct KWinScript {
+    plugin_name: String,
+    file: PathBuf,
+}
+
+impl KWinScript {
+    /// Load and start the focus script, addressed to `connection`.
+    fn load(connection: &Connection) -> FerrousFocusResult<Self> {
+        let service = connection
+            .unique_name()
+            .ok_or_else(|| FerrousFocusError::Platform("Session bus has no unique name".into()))?
+            .to_string();
+
+        // A random part keeps the file name from being guessed and created beforehand
+        let number = SCRIPT_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let token = RandomState::new().hash_one(number);
+        let plugin_name = format!("ferrous-focus-{}-{number}-{token:016x}", std::process::id());
+        let file = script_dir().join(format!("{plugin_name}.js"));
+        let source = SCRIPT
+            .replace("@SERVICE@", &service)
+            .replace("@OBJECT_PATH@", OBJECT_PATH)
+            .replace("@INTERFACE@", INTERFACE);
+        write_private(&file, &source).map_err(|e| {
+            FerrousFocusError::new(format!("Failed to write {}: {e}", file.display()))
+        })?;
+
+        // Drop removes the file on failure from here on
+        let script = Self { plugin_name, file };
+
+        let scripting = Proxy::new(
+            connection,
+            KWIN_SERVICE,
+            SCRIPTING_PATH,
+            SCRIPTING_INTERFACE,
+        )
+        .map_err(dbus_error)?;
+        let id: i32 = scripting
+            .call(
+                "loadScript",
+                &(path_str(&script.file)?, &script.plugin_name),
+            )
+            .map_err(dbus_error)?;
+        if id < 0 {
+            return Err(FerrousFocusError::Platform(format!(
+                "KWin refused to load script {}",
+                script.plugin_name
+            )));
+        }
+
+        if let Err(e) = script.start(connection, id) {
+            script.unload(connection);
+            return Err(e);
+        }
+        Ok(script)
+    }
+
+    /// Run the loaded script, at its KWin 6 object path or else its KWin 5 one.
+    fn start(&self, connection: &Connection, id: i32) -> FerrousFocusResult<()> {
+        let mut result = Ok(());
+        for path in [format!("{SCRIPTING_PATH}/Script{id}"), format!("/{id}")] {
+            result = Proxy::new(connection, KWIN_SERVICE, path, SCRIPT_INTERFACE)
+                .and_then(|script| script.call::<_, _, ()>("run", &()));
+            if result.is_ok() {
+                break;
+            }
+        }
+        result.map_err(dbus_error)
+    }
+
+    fn unload(self, connection: &Connection) {
+        let unloaded = Proxy::new(
+            connection,
+            KWIN_SERVICE,
+            SCRIPTING_PATH,
+            SCRIPTING_INTERFACE,
+        )
+        .and_then(|scripting| scripting.call::<_, _, bool>("unloadScript", &self.plugin_name));
+        match unloaded {
+            Ok(true) => {}
+            Ok(false) => info!("KWin script {} was not loaded", self.plugin_name),
+            Err(e) => info!("Failed to unload KWin script {}: {}", self.plugin_name, e),
+        }
+        // Drop removes the script file
+    }
+}
+
+impl Drop for KWinScript {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.file);
+    }
+}
+
+/// Where scripts are written: the user's private runtime directory, or else the temp directory.
+fn script_dir() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .unwrap_or_else(env::temp_dir)
+}
+
+/// Write `contents` to a new file only the user can read, failing if `path` already exists,
+/// also as a symlink.
+fn write_private(path: &Path, contents: &str) -> std::io::Result<()> {
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(contents.as_bytes())
+}
+
+/// Check if the stop signal is set.
+fn should_stop(stop_signal: Option<&StopSignal>) -> bool {
+    stop_signal.is_some_and(StopSignal::is_stopped)
+}
+
+fn dbus_error(e: zbus::Error) -> FerrousFocusError {
+    FerrousFocusError::Platform(format!("D-Bus error: {e}"))
+}
+
+fn session_bus() -> FerrousFocusResult<Connection> {
+    Connection::session().map_err(dbus_error)
+}
+
+fn path_str(path: &Path) -> FerrousFocusResult<&str> {
+    path.to_str()
+        .ok_or_else(|| FerrousFocusError::new(format!("Non UTF-8 path {}", path.display())))
+}
+
+/// Build window info from the script's description, or `None` if nothing is active.
+fn window_from_description(description: &Value) -> Option<FocusedWindow> {
+    let description = description.as_object().filter(|d| !d.is_empty())?;
+
+    let string = |key: &str| {
+        description
+            .get(key)
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .map(str::to_owned)
+    };
+
+    let process_id = description
+        .get("pid")
+        .and_then(Value::as_u64)
+        .and_then(|pid| u32::try_from(pid).ok());
+    let process_name = process_id.and_then(|pid| {
+        utils::process_name(pid)
+            .inspect_err(|e| info!("Failed to get process name for PID {}: {}", pid, e))
+            .ok()
+    });
+
+    Some(FocusedWindow {
+        window_id: string("internalId").map(|id| WindowId::from_text(&id)),
+        process_id,
+        process_name,
+        window_title: string("caption"),
+        window_class: string("resourceClass"),
+        workspace: string("desktop"),
+        output: string("output"),
+        fullscreen: description.get("fullScreen").and_then(Value::as_bool),
+        xwayland: None,
+        marks: None,
+        filtered: false,
+        icon: None,
+        icon_handle: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::test_bus::TestBus;
+    use std::{
+        os::unix::fs::PermissionsExt,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    /// What the mock learned about the loaded script.
+    #[derive(Debug, Default)]
+    struct Loaded {
+        tracker: Option<String>,
+        source: String,
+        plugin_name: String,
+        running: bool,
+        unloaded: bool,
+    }
+
+    /// Stand-in for KWin's scripting interface.
+    struct MockScripting {
+        loaded: Arc<Mutex<Loaded>>,
+    }
+
+    #[zbus::interface(name = "org.kde.kwin.Scripting")]
+    impl MockScripting {
+        #[zbus(name = "loadScript")]
+        fn load_script(
+            &self,
+            #[zbus(header)] header: Header<'_>,
+            file_path: String,
+            plugin_name: String,
+        ) -> i32 {
+            let mut loaded = self.loaded.lock().unwrap();
+            loaded.tracker = header.sender().map(ToString::to_string);
+            let mode = std::fs::metadata(&file_path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            loaded.source = std::fs::read_to_string(file_path).unwrap();
+            loaded.plugin_name = plugin_name;
+            0
+        }
+
+        #[zbus(name = "unloadScript")]
+        fn unload_script(&self, plugin_name: String) -> bool {
+            let mut loaded = self.loaded.lock().unwrap();
+            loaded.unloaded = loaded.plugin_name == plugin_name;
+            loaded.unloaded
+        }
+    }
+
+    /// Stand-in for the script object of KWin 6.
+    struct MockScript {
+        loaded: Arc<Mutex<Loaded>>,
+    }
+
+    #[zbus::interface(name = "org.kde.kwin.Script")]
+    impl MockScript {
+        #[zbus(name = "run")]
+        fn run(&self) {
+            self.loaded.lock().unwrap().running = true;
+        }
+    }
+
+    /// Serve KWin's scripting interfaces on `bus`, recording into `loaded`.
+    fn mock_kwin(bus: &TestBus, loaded: &Arc<Mutex<Loaded>>) -> Connection {
+        bus.builder()
+            .name(KWIN_SERVICE)
+            .unwrap()
+            .serve_at(
+                SCRIPTING_PATH,
+                MockScripting {
+                    loaded: Arc::clone(loaded),
+                },
+            )
+            .unwrap()
+            .serve_at(
+                "/Scripting/Script0",
+                MockScript {
+                    loaded: Arc::clone(loaded),
+                },
+            )
+            .unwrap()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_query_keeps_script_loaded() {
+        let Some(bus) = TestBus::start("kde-query") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let loaded = Arc::new(Mutex::new(Loaded::default()));
+        let kwin = mock_kwin(&bus, &loaded);
+        let mut query = KdeQuery::new(bus.connect()).unwrap();
+        assert!(loaded.lock().unwrap().running);
+
+        // Act as the script
+        let tracker_name = loaded.lock().unwrap().tracker.clone().unwrap();
+        let report = |description: &str| {
+            kwin.call_method(
+                Some(tracker_name.as_str()),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "WindowActivated",
+                &description,
+            )
+            .unwrap();
+        };
+
+        // Other clients cannot report focus
+        let intruder = bus.connect();
+        assert!(
+            intruder
+                .call_method(
+                    Some(tracker_name.as_str()),
+                    OBJECT_PATH,
+                    Some(INTERFACE),
+                    "WindowActivated",
+                    &r#"{"caption":"Fake"}"#,
+                )
+                .is_err()
+        );
+
+        report(r#"{"caption":"Konsole","resourceClass":"org.kde.konsole"}"#);
+        let window = query.current().unwrap().unwrap();
+        assert_eq!(window.window_title.as_deref(), Some("Konsole"));
+        // Still active without further reports
+        assert_eq!(query.current().unwrap(), Some(window));
+
+        report("{}");
+        assert_eq!(query.current().unwrap(), None);
+
+        drop(query);
+        assert!(loaded.lock().unwrap().unloaded);
+    }
+
+    #[test]
+    fn test_tracks_script_callbacks() {
+        let Some(bus) = TestBus::start("kde-callbacks") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let loaded = Arc::new(Mutex::new(Loaded::default()));
+        let kwin = mock_kwin(&bus, &loaded);
+
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let stop_signal = Arc::new(StopSignal::new());
+        let tracker = {
+            let changes = Arc::clone(&changes);
+            let stop_signal = Arc::clone(&stop_signal);
+            let connection = bus.connect();
+            thread::spawn(move || {
+                let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+                run(
+                    |change, _| {
+                        changes.lock()?.push(change);
+                        Ok(())
+                    },
+                    connection,
+                    Some(&stop_signal),
+                    &config,
+                )
+            })
+        };
+
+        while !loaded.lock().unwrap().running {
+            if tracker.is_finished() {
+                panic!("Tracker stopped: {:?}", tracker.join().unwrap());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let tracker_name = {
+            let loaded = loaded.lock().unwrap();
+            assert!(loaded.source.contains("windowActivated"));
+            assert!(loaded.source.contains(OBJECT_PATH));
+            let tracker_name = loaded.tracker.clone().unwrap();
+            assert!(loaded.source.contains(&tracker_name));
+            tracker_name
+        };
+
+        // Act as the script
+        let report = |description: &str| {
+            kwin.call_method(
+                Some(tracker_name.as_str()),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "WindowActivated",
+                &description,
+            )
+            .unwrap();
+        };
+        let pid = std::process::id();
+        report(&format!(
+            r#"{{"internalId":"{{4a1f}}","caption":"Konsole","resourceClass":"org.kde.konsole","pid":{pid},"fullScreen":false,"desktop":"Desktop 1","output":"DP-1"}}"#
+        ));
+        report("{}");
+        report(r#"{"caption":"Dolphin","resourceClass":"org.kde.dolphin","fullScreen":true}"#);
+        // Repeated notification for the same window is ignored
+        report(r#"{"caption":"Dolphin","resourceClass":"org.kde.dolphin","fullScreen":true}"#);
+
+        while changes.lock().unwrap().len() < 3 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        stop_signal.stop();
+        assert!(tracker.join().unwrap().is_ok());
+        assert!(loaded.lock().unwrap().unloaded);
+
+        let changes = changes.lock().unwrap().clone();
+        assert!(matches!(
+            changes[..],
+            [Change::Focus(_), Change::Cleared, Change::Focus(_)]
+        ));
+
+        let windows: Vec<_> = changes
+            .into_iter()
+            .filter_map(Change::into_window)
+            .collect();
+
+        let konsole = &windows[0];
+        assert_eq!(konsole.window_id, Some(WindowId::from_text("{4a1f}")));
+        assert_eq!(konsole.window_title.as_deref(), Some("Konsole"));
+        assert_eq!(konsole.window_class.as_deref(), Some("org.kde.konsole"));
+        assert_eq!(konsole.process_id, Some(pid));
+        assert_eq!(konsole.workspace.as_deref(), Some("Desktop 1"));
+        assert_eq!(konsole.output.as_deref(), Some("DP-1"));
+
+        let dolphin = &windows[1];
+        assert_eq!(dolphin.window_title.as_deref(), Some("Dolphin"));
+        assert_eq!(dolphin.fullscreen, Some(true));
+        assert_eq!(dolphin.window_id, None);
+        assert_eq!(dolphin.process_id, None);
+    }
+    #[test]
+    fn test_unloads_script_when_handler_fails() {
+        let Some(bus) = TestBus::start("kde-handler-error") else {
+            info!("Skipping test - dbus-daemon not available");
+            return;
+        };
+
+        let loaded = Arc::new(Mutex::new(Loaded::default()));
+        let kwin = mock_kwin(&bus, &loaded);
+
+        let tracker = {
+            let connection = bus.connect();
+            thread::spawn(move || {
+                let config = FocusTrackerConfig::new().with_poll_interval_ms(10);
+                run(
+                    |_, _| Err(FerrousFocusError::new("Handler failed")),
+                    connection,
+                    None,
+                    &config,
+                )
+            })
+        };
+
+        while !loaded.lock().unwrap().running {
+            if tracker.is_finished() {
+                panic!("Tracker stopped: {:?}", tracker.join().unwrap());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let tracker_name = loaded.lock().unwrap().tracker.clone().unwrap();
+        kwin.call_method(
+            Some(tracker_name.as_str()),
+            OBJECT_PATH,
+            Some(INTERFACE),
+            "WindowActivated",
+            &r#"{"caption":"Konsole"}"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            tracker.join().unwrap(),
+            Err(FerrousFocusError::Error(message)) if message == "Handler failed"
+        ));
+        assert!(loaded.lock().unwrap().unloaded);
+        // The callback object is gone as well
+        assert!(
+            kwin.call_method(
+                Some(tracker_name.as_str()),
+                OBJECT_PATH,
+                Some(INTERFACE),
+                "WindowActivated",
+                &"{}",
+            )
+            .is_err()
+        );
+    }
+}
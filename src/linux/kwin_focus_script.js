@@ -0,0 +1,75 @@
+// KWin script loaded at runtime by the ferrous-focus KDE backend.
+//
+// Reports the active window to the tracker that loaded it by calling `WindowActivated` with a
+// JSON description, `{}` when nothing is active. The placeholders below are filled in by the
+// tracker before the script is loaded.
+
+const SERVICE = "@SERVICE@";
+const OBJECT_PATH = "@OBJECT_PATH@";
+const INTERFACE = "@INTERFACE@";
+
+// KWin 6 renamed clients to windows
+const activated = workspace.windowActivated || workspace.clientActivated;
+
+function activeWindow() {
+    return workspace.activeWindow !== undefined ? workspace.activeWindow : workspace.activeClient;
+}
+
+function describe(window) {
+    if (!window)
+        return {};
+
+    const description = {
+        internalId: String(window.internalId),
+        caption: window.caption,
+        resourceClass: String(window.resourceClass),
+        resourceName: String(window.resourceName),
+        fullScreen: window.fullScreen,
+    };
+
+    if (window.pid > 0)
+        description.pid = window.pid;
+
+    if (window.desktops !== undefined) {
+        if (window.desktops.length > 0)
+            description.desktop = window.desktops[0].name;
+    } else if (window.desktop > 0) {
+        description.desktop = String(window.desktop);
+    }
+
+    if (window.output)
+        description.output = window.output.name;
+
+    return description;
+}
+
+function report(window) {
+    callDBus(SERVICE, OBJECT_PATH, INTERFACE, "WindowActivated", JSON.stringify(describe(window)));
+}
+
+// Follow caption and fullscreen changes of the active window only
+let watched = null;
+
+function onChanged() {
+    report(watched);
+}
+
+function watch(window) {
+    if (watched) {
+        watched.captionChanged.disconnect(onChanged);
+        watched.fullScreenChanged.disconnect(onChanged);
+    }
+    watched = window;
+    if (watched) {
+        watched.captionChanged.connect(onChanged);
+        watched.fullScreenChanged.connect(onChanged);
+    }
+}
+
+activated.connect(function (window) {
+    watch(window);
+    report(window);
+});
+
+watch(activeWindow());
+report(activeWindow());
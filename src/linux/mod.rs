@@ -1,4 +1,12 @@
+mod gnome_focus_tracker;
+mod hyprland_focus_tracker;
+mod i3ipc_focus_tracker;
+mod kde_focus_tracker;
 pub mod utils;
+mod wayland_registry;
 mod xorg_focus_tracker;
 
 pub mod impl_focus_tracker;
+
+#[cfg(test)]
+mod test_bus;
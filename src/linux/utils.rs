@@ -1,4 +1,21 @@
-use std::env::var_os;
+use super::{wayland_registry, xorg_focus_tracker};
+use crate::{FerrousFocusError, FerrousFocusResult};
+use std::{env::var_os, path::PathBuf};
+use tracing::info;
+
+/// UUID of the companion GNOME Shell extension used on GNOME Wayland.
+pub const GNOME_EXTENSION_UUID: &str = "ferrous-focus@eurora-labs.com";
+
+const GNOME_EXTENSION_FILES: [(&str, &str); 2] = [
+    (
+        "metadata.json",
+        include_str!("../../extensions/gnome-shell/ferrous-focus@eurora-labs.com/metadata.json"),
+    ),
+    (
+        "extension.js",
+        include_str!("../../extensions/gnome-shell/ferrous-focus@eurora-labs.com/extension.js"),
+    ),
+];
 
 pub fn wayland_detect() -> bool {
     let xdg_session_type = var_os("XDG_SESSION_TYPE")
@@ -13,3 +30,222 @@ pub fn wayland_detect() -> bool {
 
     xdg_session_type.eq("wayland") || wayland_display.to_lowercase().contains("wayland")
 }
+
+/// Kind of graphical session, from `XDG_SESSION_TYPE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SessionType {
+    Wayland,
+    X11,
+    Tty,
+    #[default]
+    Unknown,
+}
+
+/// A Wayland compositor, as far as it can be told apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compositor {
+    /// GNOME Shell.
+    Mutter,
+    /// KDE Plasma.
+    KWin,
+    Sway,
+    Hyprland,
+    River,
+    Wayfire,
+    Cosmic,
+    Weston,
+    /// Some other compositor built on wlroots.
+    Wlroots,
+}
+
+impl Compositor {
+    /// Recognize a compositor from the interface names of its Wayland globals.
+    pub fn from_globals<S: AsRef<str>>(globals: &[S]) -> Option<Self> {
+        let has = |matches: fn(&str) -> bool| globals.iter().any(|global| matches(global.as_ref()));
+
+        // Specific protocols first, the wlroots ones are shared by many compositors
+        if has(|g| g.starts_with("hyprland_")) {
+            Some(Self::Hyprland)
+        } else if has(|g| g.starts_with("org_kde_plasma") || g == "kde_output_device_v2") {
+            Some(Self::KWin)
+        } else if has(|g| g == "gtk_shell1") {
+            Some(Self::Mutter)
+        } else if has(|g| g.starts_with("zcosmic_")) {
+            Some(Self::Cosmic)
+        } else if has(|g| g.starts_with("river_") || g.starts_with("zriver_")) {
+            Some(Self::River)
+        } else if has(|g| g.starts_with("wayfire_") || g.starts_with("zwf_")) {
+            Some(Self::Wayfire)
+        } else if has(|g| g.starts_with("weston_")) {
+            Some(Self::Weston)
+        } else if has(|g| g.starts_with("zwlr_")) {
+            Some(Self::Wlroots)
+        } else {
+            None
+        }
+    }
+}
+
+/// What is known about the graphical session.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// Session type, falling back to `WAYLAND_DISPLAY`/`DISPLAY` when `XDG_SESSION_TYPE` is unset.
+    pub session_type: SessionType,
+    /// Entries of `XDG_CURRENT_DESKTOP` (e.g. `["ubuntu", "GNOME"]`).
+    pub current_desktop: Vec<String>,
+    /// `DESKTOP_SESSION` (e.g. `plasma`).
+    pub desktop_session: Option<String>,
+    /// The Wayland compositor, from its IPC sockets or its registry globals.
+    pub compositor: Option<Compositor>,
+    /// Interface names of the globals in the Wayland registry.
+    pub wayland_globals: Vec<String>,
+    /// Name of the EWMH window manager on the X server (`_NET_SUPPORTING_WM_CHECK`/`_NET_WM_NAME`).
+    pub x11_wm_name: Option<String>,
+    /// Whether the X server named by `DISPLAY` is XWayland: it has the `XWAYLAND` extension, or
+    /// it is reachable from a Wayland session.
+    pub xwayland: bool,
+}
+
+impl SessionInfo {
+    /// Inspect the environment, the Wayland compositor and the X server.
+    ///
+    /// Probing is best effort: whatever cannot be reached is left empty.
+    pub fn detect() -> Self {
+        let env_string = |name: &str| {
+            var_os(name)
+                .map(|value| value.to_string_lossy().into_owned())
+                .filter(|value| !value.is_empty())
+        };
+
+        let session_type = match env_string("XDG_SESSION_TYPE").as_deref() {
+            Some("wayland") => SessionType::Wayland,
+            Some("x11") => SessionType::X11,
+            Some("tty") => SessionType::Tty,
+            _ if env_string("WAYLAND_DISPLAY").is_some() => SessionType::Wayland,
+            _ if env_string("DISPLAY").is_some() => SessionType::X11,
+            _ => SessionType::Unknown,
+        };
+
+        let wayland_globals = wayland_registry::socket_path()
+            .map(|socket| {
+                wayland_registry::globals(&socket)
+                    .inspect_err(|e| info!("Failed to list Wayland globals: {}", e))
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        // The IPC sockets are unambiguous, the registry is a heuristic
+        let compositor = if var_os("HYPRLAND_INSTANCE_SIGNATURE").is_some() {
+            Some(Compositor::Hyprland)
+        } else if var_os("SWAYSOCK").is_some() {
+            Some(Compositor::Sway)
+        } else {
+            Compositor::from_globals(&wayland_globals)
+        };
+
+        let (x11_wm_name, xwayland) = match env_string("DISPLAY") {
+            Some(_) => xorg_focus_tracker::server_info()
+                // Older XWayland releases lack the XWAYLAND extension
+                .map(|server| {
+                    let xwayland = server.xwayland || session_type == SessionType::Wayland;
+                    (server.wm_name, xwayland)
+                })
+                .inspect_err(|e| info!("Failed to query the X server: {}", e))
+                .unwrap_or_default(),
+            None => (None, false),
+        };
+
+        Self {
+            session_type,
+            current_desktop: env_string("XDG_CURRENT_DESKTOP")
+                .map(|desktops| desktops.split(':').map(str::to_owned).collect())
+                .unwrap_or_default(),
+            desktop_session: env_string("DESKTOP_SESSION"),
+            compositor,
+            wayland_globals,
+            x11_wm_name,
+            xwayland,
+        }
+    }
+
+    /// Check if `XDG_CURRENT_DESKTOP` lists the given desktop (case insensitive).
+    pub fn is_desktop(&self, desktop: &str) -> bool {
+        self.current_desktop
+            .iter()
+            .any(|current| current.eq_ignore_ascii_case(desktop))
+    }
+}
+
+/// Install the companion GNOME Shell extension for the current user.
+///
+/// The extension is copied to `~/.local/share/gnome-shell/extensions` and the install directory
+/// is returned. It still has to be enabled (`gnome-extensions enable ferrous-focus@eurora-labs.com`),
+/// which on Wayland only takes effect after logging out and back in.
+pub fn install_gnome_extension() -> FerrousFocusResult<PathBuf> {
+    let data_dir = var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .ok_or_else(|| FerrousFocusError::new("Neither XDG_DATA_HOME nor HOME is set"))?;
+
+    let dir = data_dir
+        .join("gnome-shell/extensions")
+        .join(GNOME_EXTENSION_UUID);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| FerrousFocusError::new(format!("Failed to create {}: {e}", dir.display())))?;
+
+    for (name, contents) in GNOME_EXTENSION_FILES {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).map_err(|e| {
+            FerrousFocusError::new(format!("Failed to write {}: {e}", path.display()))
+        })?;
+    }
+
+    Ok(dir)
+}
+
+/// Read the executable name of a process from `/proc`.
+pub(crate) fn process_name(pid: u32) -> FerrousFocusResult<String> {
+    // read /proc/<pid>/comm (single line: executable name)
+    std::fs::read_to_string(format!("/proc/{pid}/comm"))
+        .or_else(|_| {
+            std::fs::read_link(format!("/proc/{pid}/exe")).map(|p| p.to_string_lossy().into())
+        })
+        .map(|name| name.trim_end_matches('\n').to_owned())
+        .map_err(|e| FerrousFocusError::Platform(format!("Failed to get process name: {e}")))
+}
+
+/// Resolve the executable a process runs from `/proc`.
+pub(crate) fn executable_path(pid: u32) -> Option<PathBuf> {
+    std::fs::read_link(format!("/proc/{pid}/exe")).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compositor_from_globals() {
+        let kwin = [
+            "wl_compositor",
+            "org_kde_kwin_server_decoration_manager",
+            "org_kde_plasma_shell",
+        ];
+        assert_eq!(Compositor::from_globals(&kwin), Some(Compositor::KWin));
+
+        // sway implements the KDE decoration protocol too
+        let sway = [
+            "wl_compositor",
+            "org_kde_kwin_server_decoration_manager",
+            "zwlr_layer_shell_v1",
+        ];
+        assert_eq!(Compositor::from_globals(&sway), Some(Compositor::Wlroots));
+
+        let mutter = ["wl_compositor", "gtk_shell1"];
+        assert_eq!(Compositor::from_globals(&mutter), Some(Compositor::Mutter));
+
+        assert_eq!(Compositor::from_globals(&["wl_compositor"]), None);
+    }
+}
@@ -0,0 +1,175 @@
+//! Minimal Wayland client that lists the globals of the compositor's registry.
+//!
+//! Only `wl_display.get_registry` and `wl_display.sync` are sent, which is enough to read every
+//! `wl_registry.global` event without depending on libwayland.
+
+use crate::{FerrousFocusError, FerrousFocusResult};
+use std::{
+    env::var_os,
+    io::{Read, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const DISPLAY_ID: u32 = 1;
+const REGISTRY_ID: u32 = 2;
+const CALLBACK_ID: u32 = 3;
+
+const DISPLAY_SYNC: u16 = 0;
+const DISPLAY_GET_REGISTRY: u16 = 1;
+const DISPLAY_ERROR: u16 = 0;
+const REGISTRY_GLOBAL: u16 = 0;
+const CALLBACK_DONE: u16 = 0;
+
+/// Give up on compositors that do not answer.
+const TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Get the path of the compositor socket from `WAYLAND_DISPLAY` and `XDG_RUNTIME_DIR`.
+pub fn socket_path() -> Option<PathBuf> {
+    let display = var_os("WAYLAND_DISPLAY").filter(|display| !display.is_empty())?;
+    let display = Path::new(&display);
+    if display.is_absolute() {
+        Some(display.to_path_buf())
+    } else {
+        var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join(display))
+    }
+}
+
+/// List the interface names of all globals advertised by the compositor at `socket`.
+pub fn globals(socket: &Path) -> FerrousFocusResult<Vec<String>> {
+    let mut stream = UnixStream::connect(socket).map_err(|e| {
+        FerrousFocusError::Platform(format!("Failed to connect to {}: {e}", socket.display()))
+    })?;
+    stream.set_read_timeout(Some(TIMEOUT)).map_err(io_error)?;
+
+    let mut request = Vec::with_capacity(24);
+    encode_request(&mut request, DISPLAY_ID, DISPLAY_GET_REGISTRY, REGISTRY_ID);
+    encode_request(&mut request, DISPLAY_ID, DISPLAY_SYNC, CALLBACK_ID);
+    stream.write_all(&request).map_err(io_error)?;
+
+    // The sync callback fires once every global has been announced
+    let mut globals = Vec::new();
+    loop {
+        let (object, opcode, body) = read_event(&mut stream)?;
+        match (object, opcode) {
+            (REGISTRY_ID, REGISTRY_GLOBAL) => {
+                if let Some(interface) = decode_string(body.get(4..).unwrap_or_default()) {
+                    globals.push(interface);
+                }
+            }
+            (CALLBACK_ID, CALLBACK_DONE) => break,
+            (DISPLAY_ID, DISPLAY_ERROR) => {
+                return Err(FerrousFocusError::Platform(
+                    "Wayland compositor reported a protocol error".to_string(),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(globals)
+}
+
+/* ------------------------------------------------------------ */
+/* Helper functions                                              */
+/* ------------------------------------------------------------ */
+
+fn io_error(e: std::io::Error) -> FerrousFocusError {
+    FerrousFocusError::Platform(format!("Wayland socket error: {e}"))
+}
+
+/// Encode a request with a single `new_id` argument.
+fn encode_request(buffer: &mut Vec<u8>, object: u32, opcode: u16, new_id: u32) {
+    let size: u32 = 12;
+    buffer.extend_from_slice(&object.to_ne_bytes());
+    buffer.extend_from_slice(&(size << 16 | u32::from(opcode)).to_ne_bytes());
+    buffer.extend_from_slice(&new_id.to_ne_bytes());
+}
+
+/// Read one event as (object ID, opcode, arguments).
+fn read_event(stream: &mut UnixStream) -> FerrousFocusResult<(u32, u16, Vec<u8>)> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).map_err(io_error)?;
+    let object = u32::from_ne_bytes([header[0], header[1], header[2], header[3]]);
+    let size_opcode = u32::from_ne_bytes([header[4], header[5], header[6], header[7]]);
+
+    let size = (size_opcode >> 16) as usize;
+    if size < header.len() {
+        return Err(FerrousFocusError::Platform(format!(
+            "Invalid Wayland message size {size}"
+        )));
+    }
+
+    let mut body = vec![0u8; size - header.len()];
+    stream.read_exact(&mut body).map_err(io_error)?;
+    Ok((object, (size_opcode & 0xffff) as u16, body))
+}
+
+/// Decode a wire string: its length including the terminating NUL, then the padded bytes.
+fn decode_string(data: &[u8]) -> Option<String> {
+    let length = u32::from_ne_bytes(data.get(..4)?.try_into().ok()?) as usize;
+    let bytes = data.get(4..4 + length)?;
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    Some(String::from_utf8_lossy(bytes).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{os::unix::net::UnixListener, thread};
+
+    fn encode_global(buffer: &mut Vec<u8>, name: u32, interface: &str) {
+        let mut body = name.to_ne_bytes().to_vec();
+        body.extend_from_slice(&(interface.len() as u32 + 1).to_ne_bytes());
+        body.extend_from_slice(interface.as_bytes());
+        body.push(0);
+        body.resize(body.len().next_multiple_of(4), 0);
+        body.extend_from_slice(&1u32.to_ne_bytes());
+
+        let size = (8 + body.len()) as u32;
+        buffer.extend_from_slice(&REGISTRY_ID.to_ne_bytes());
+        buffer.extend_from_slice(&(size << 16 | u32::from(REGISTRY_GLOBAL)).to_ne_bytes());
+        buffer.extend_from_slice(&body);
+    }
+
+    #[test]
+    fn test_lists_globals() {
+        let dir =
+            std::env::temp_dir().join(format!("ferrous-focus-wayland-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("wayland-test");
+        let _ = std::fs::remove_file(&socket);
+        let listener = UnixListener::bind(&socket).unwrap();
+
+        let compositor = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut requests = [0u8; 24];
+            stream.read_exact(&mut requests).unwrap();
+
+            let mut events = Vec::new();
+            encode_global(&mut events, 1, "wl_compositor");
+            encode_global(&mut events, 2, "hyprland_toplevel_export_manager_v1");
+            // wl_callback.done with its callback data
+            events.extend_from_slice(&CALLBACK_ID.to_ne_bytes());
+            events.extend_from_slice(&(12u32 << 16 | u32::from(CALLBACK_DONE)).to_ne_bytes());
+            events.extend_from_slice(&0u32.to_ne_bytes());
+            stream.write_all(&events).unwrap();
+            requests
+        });
+
+        let globals = globals(&socket).unwrap();
+        assert_eq!(
+            globals,
+            ["wl_compositor", "hyprland_toplevel_export_manager_v1"]
+        );
+
+        let requests = compositor.join().unwrap();
+        let mut expected = Vec::new();
+        encode_request(&mut expected, DISPLAY_ID, DISPLAY_GET_REGISTRY, REGISTRY_ID);
+        encode_request(&mut expected, DISPLAY_ID, DISPLAY_SYNC, CALLBACK_ID);
+        assert_eq!(requests.as_slice(), expected.as_slice());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
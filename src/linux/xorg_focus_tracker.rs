@@ -1,9 +1,14 @@
-use crate::{FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow};
-use std::sync::atomic::{AtomicBool, Ordering};
+use super::utils;
+use crate::{
+    FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, IconMode, WindowId,
+    config::IconConfig,
+    focus_event::{Change, Detection},
+    icon_handle::WindowIcon,
+    stop_signal::StopSignal,
+};
+use std::sync::Arc;
 use tracing::info;
 
-#[cfg(feature = "async")]
-use std::future::Future;
 use x11rb::{
     connection::Connection,
     protocol::{
@@ -15,326 +20,129 @@ use x11rb::{
     rust_connection::RustConnection,
 };
 
-pub fn track_focus<F>(on_focus: F, config: &FocusTrackerConfig) -> FerrousFocusResult<()>
-where
-    F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-{
-    run(on_focus, None, config)
-}
-
+/// Track focus through `_NET_ACTIVE_WINDOW`.
+///
+/// With `xwayland` set the X server is XWayland inside a Wayland session: windows are flagged as
+/// XWayland clients, and [`FocusedWindow::unknown_wayland_client`] is reported whenever no X11
+/// client has focus, which is what XWayland sees while a native Wayland client is focused.
 pub fn track_focus_with_stop<F>(
     on_focus: F,
-    stop_signal: &AtomicBool,
+    xwayland: bool,
+    stop_signal: &StopSignal,
     config: &FocusTrackerConfig,
 ) -> FerrousFocusResult<()>
 where
-    F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
 {
-    run(on_focus, Some(stop_signal), config)
+    run(on_focus, xwayland, Some(stop_signal), config)
 }
 
-#[cfg(feature = "async")]
-pub async fn track_focus_async<F, Fut>(
-    on_focus: F,
-    config: &FocusTrackerConfig,
-) -> FerrousFocusResult<()>
-where
-    F: FnMut(FocusedWindow) -> Fut,
-    Fut: Future<Output = FerrousFocusResult<()>>,
-{
-    run_async(on_focus, None, config).await
+/// Check that the X server named by `DISPLAY` accepts connections.
+pub fn probe() -> Result<(), String> {
+    connect_to_x11()
+        .map(|_| ())
+        .map_err(|e| format!("cannot connect to the X server: {e}"))
 }
 
-#[cfg(feature = "async")]
-pub async fn track_focus_async_with_stop<F, Fut>(
-    on_focus: F,
-    stop_signal: &AtomicBool,
-    config: &FocusTrackerConfig,
-) -> FerrousFocusResult<()>
-where
-    F: FnMut(FocusedWindow) -> Fut,
-    Fut: Future<Output = FerrousFocusResult<()>>,
-{
-    run_async(on_focus, Some(stop_signal), config).await
+/// X11 connection for asking which window is focused, kept open between queries.
+pub struct XorgQuery {
+    conn: Arc<RustConnection>,
+    root: u32,
+    atoms: X11Atoms,
+    xwayland: bool,
 }
 
-#[cfg(feature = "async")]
-async fn run_async<F, Fut>(
-    mut on_focus: F,
-    stop_signal: Option<&AtomicBool>,
-    config: &FocusTrackerConfig,
-) -> FerrousFocusResult<()>
-where
-    F: FnMut(FocusedWindow) -> Fut,
-    Fut: Future<Output = FerrousFocusResult<()>>,
-{
-    use std::sync::Arc;
-    use tokio::sync::mpsc;
-
-    // Create a channel for communicating focus events from blocking thread to async context
-    let (tx, mut rx) = mpsc::unbounded_channel::<FocusedWindow>();
-
-    // Clone config for the blocking task
-    let config_clone = config.clone();
-
-    // Create an internal stop signal for the blocking task
-    let internal_stop = Arc::new(AtomicBool::new(false));
-    let thread_stop = Arc::clone(&internal_stop);
-    let cleanup_stop = Arc::clone(&internal_stop);
-
-    // Spawn a blocking task for X11 operations (X11 is inherently blocking)
-    let blocking_handle = tokio::task::spawn_blocking(move || -> FerrousFocusResult<()> {
-        // ── X11 setup ──────────────────────────────────────────────────────────────
+impl XorgQuery {
+    /// Connect to the X server named by `DISPLAY`; `xwayland` as for [`track_focus`].
+    pub fn connect(xwayland: bool) -> FerrousFocusResult<Self> {
         let (conn, screen_num) = connect_to_x11()?;
-        let screen = &conn.setup().roots[screen_num];
-        let root = screen.root;
-
+        let root = conn.setup().roots[screen_num].root;
         let atoms = setup_atoms(&conn)?;
-        setup_root_window_monitoring(&conn, root)?;
-
-        // Track the currently focused window to monitor its title changes
-        let mut current_focused_window: Option<u32> = None;
-        // Cache the icon for the currently focused window (only fetch on app change)
-        let mut cached_icon: Option<image::RgbaImage> = None;
-
-        // ── Get initial focused window ─────────────────────────────────────────────
-        // Fire an immediate event with the currently focused window (like Windows/macOS)
-        if let Ok(Some(window)) = get_active_window(&conn, root, atoms.net_active_window) {
-            match get_window_info(&conn, window, &atoms) {
-                Ok(mut focused_window) => {
-                    // Initial window - fetch icon
-                    let icon =
-                        get_icon_data(&conn, window, atoms.net_wm_icon, &config_clone.icon).ok();
-                    cached_icon = icon.clone();
-                    focused_window.icon = icon;
-
-                    // Send initial window info to async context via channel
-                    if tx.send(focused_window).is_err() {
-                        // Channel closed, async task has been dropped
-                        info!("Async task dropped before initial event, stopping X11 event loop");
-                        return Ok(());
-                    }
-                    // Set up monitoring for this window
-                    current_focused_window = Some(window);
-                    let _ = conn.change_window_attributes(
-                        window,
-                        &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
-                    );
-                    let _ = flush_connection(&conn);
-                }
-                Err(e) => {
-                    info!("Failed to get initial window info: {}", e);
-                }
-            }
-        }
-
-        // ── Event loop ─────────────────────────────────────────────────────────────
-        loop {
-            // Check stop signal (either internal or external)
-            if thread_stop.load(Ordering::Acquire) {
-                break;
-            }
-
-            // Use non-blocking poll to allow checking stop signal
-            let event = match conn.poll_for_event() {
-                Ok(Some(e)) => e,
-                Ok(None) => {
-                    // No event available, sleep briefly
-                    // Note: We use std::thread::sleep here because we're in a blocking context
-                    std::thread::sleep(config_clone.poll_interval);
-                    continue;
-                }
-                Err(e) => {
-                    info!("X11 error: {e}");
-                    // Note: We use std::thread::sleep here because we're in a blocking context
-                    std::thread::sleep(std::time::Duration::from_secs(1));
-                    continue;
-                }
-            };
-
-            if let Event::PropertyNotify(PropertyNotifyEvent { atom, window, .. }) = event {
-                let mut should_emit_focus_event = false;
-                let mut new_window: Option<u32> = None;
-                let mut is_focus_change = false;
-
-                // Check if this is an active window change
-                if atom == atoms.net_active_window && window == root {
-                    // Active window changed
-                    match get_active_window(&conn, root, atoms.net_active_window) {
-                        Ok(win) => {
-                            new_window = win;
-                            should_emit_focus_event = true;
-                            is_focus_change = true;
-
-                            // Update monitoring for the new focused window
-                            update_window_monitoring(
-                                &conn,
-                                &mut current_focused_window,
-                                new_window,
-                            );
-                        }
-                        Err(e) => {
-                            info!("Failed to get active window: {}", e);
-                            continue;
-                        }
-                    }
-                }
-                // Check if this is a title change on the currently focused window
-                else if atom == atoms.net_wm_name && Some(window) == current_focused_window {
-                    // Title changed on the focused window - don't fetch icon again
-                    new_window = current_focused_window;
-                    should_emit_focus_event = true;
-                    is_focus_change = false;
-                }
-
-                if should_emit_focus_event && let Some(window) = new_window {
-                    match get_window_info(&conn, window, &atoms) {
-                        Ok(mut focused_window) => {
-                            // Only fetch icon when the focused app changes, not on title changes
-                            if is_focus_change {
-                                let icon = get_icon_data(
-                                    &conn,
-                                    window,
-                                    atoms.net_wm_icon,
-                                    &config_clone.icon,
-                                )
-                                .ok();
-                                cached_icon = icon.clone();
-                                focused_window.icon = icon;
-                            } else {
-                                focused_window.icon = cached_icon.clone();
-                            }
-
-                            // Send to async context via channel
-                            if tx.send(focused_window).is_err() {
-                                // Channel closed, async task has been dropped
-                                info!("Async task dropped, stopping X11 event loop");
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            info!("Failed to get window info for window {}: {}", window, e);
-                        }
-                    }
-                }
-            }
-
-            if let Err(e) = flush_connection(&conn) {
-                info!("Failed to flush connection: {}", e);
-            }
-        }
-
-        Ok(())
-    });
-
-    // Process focus events in async context
-    // When this task is cancelled (dropped), cleanup will happen via the guard below
-    let result = async {
-        loop {
-            // Check external stop signal if provided
-            if let Some(external_stop) = stop_signal
-                && external_stop.load(Ordering::Acquire)
-            {
-                info!("External stop signal detected");
-                break;
-            }
-
-            // Use timeout to periodically check the stop signal
-            match tokio::time::timeout(std::time::Duration::from_millis(50), rx.recv()).await {
-                Ok(Some(focused_window)) => {
-                    if let Err(e) = on_focus(focused_window).await {
-                        info!("Focus event handler failed: {}", e);
-                        // Continue processing instead of propagating the error
-                    }
-                }
-                Ok(None) => {
-                    // Channel closed
-                    break;
-                }
-                Err(_) => {
-                    // Timeout - continue to check stop signal
-                    continue;
-                }
-            }
-        }
-        Ok::<(), FerrousFocusError>(())
+        Ok(Self {
+            conn: Arc::new(conn),
+            root,
+            atoms,
+            xwayland,
+        })
     }
-    .await;
-
-    // Signal the X11 thread to stop (this runs whether we complete normally or get cancelled)
-    info!("Async task ending, signaling X11 thread to stop");
-    cleanup_stop.store(true, Ordering::Release);
-
-    // Drop the receiver to close the channel, which will also signal the thread
-    drop(rx);
-
-    // Give the thread a brief moment to notice the stop signal and exit cleanly
-    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
 
-    // Wait for blocking task to finish and get result
-    match blocking_handle.await {
-        Ok(Ok(())) => {
-            info!("X11 event loop completed successfully");
-            result
-        }
-        Ok(Err(e)) => {
-            info!("X11 event loop error: {}", e);
-            Err(e)
-        }
-        Err(e) => {
-            let err_msg = format!("X11 blocking task failed: {}", e);
-            info!("{}", err_msg);
-            Err(FerrousFocusError::Platform(err_msg))
+    /// Get the focused window, or `None` if nothing has focus.
+    pub fn current(
+        &self,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<Option<FocusedWindow>> {
+        match get_active_window(&*self.conn, self.root, self.atoms.net_active_window)? {
+            Some(window) => {
+                let mut focused_window = get_window_info(&*self.conn, window, &self.atoms)?;
+                focused_window.xwayland = Some(self.xwayland);
+                let load = icon_loader(&self.conn, window, &self.atoms, &config.icon);
+                WindowIcon::fetch(&focused_window, config, load).apply_to(&mut focused_window);
+                Ok(Some(focused_window))
+            }
+            // A native Wayland client (or nothing) has focus
+            None if self.xwayland => Ok(Some(FocusedWindow::unknown_wayland_client())),
+            None => Ok(None),
         }
     }
 }
 
 fn run<F>(
     mut on_focus: F,
-    stop_signal: Option<&AtomicBool>,
+    xwayland: bool,
+    stop_signal: Option<&StopSignal>,
     config: &FocusTrackerConfig,
 ) -> FerrousFocusResult<()>
 where
-    F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
+    F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
 {
     // ── X11 setup ──────────────────────────────────────────────────────────────
     let (conn, screen_num) = connect_to_x11()?;
+    // Shared with the handles of lazily fetched icons
+    let shared_conn = Arc::new(conn);
+    let conn = &*shared_conn;
     let screen = &conn.setup().roots[screen_num];
     let root = screen.root;
 
-    let atoms = setup_atoms(&conn)?;
-    setup_root_window_monitoring(&conn, root)?;
+    let atoms = setup_atoms(conn)?;
+    setup_root_window_monitoring(conn, root)?;
 
     // Track the currently focused window to monitor its title changes
     let mut current_focused_window: Option<u32> = None;
     // Cache the icon for the currently focused window (only fetch on app change)
-    let mut cached_icon: Option<image::RgbaImage> = None;
+    let mut cached_icon = WindowIcon::default();
 
     // ── Get initial focused window ─────────────────────────────────────────────
     // Fire an immediate event with the currently focused window (like Windows/macOS)
-    if let Ok(Some(window)) = get_active_window(&conn, root, atoms.net_active_window) {
-        match get_window_info(&conn, window, &atoms) {
+    let detection = Detection::now();
+    match get_active_window(conn, root, atoms.net_active_window) {
+        Ok(Some(window)) => match get_window_info(conn, window, &atoms) {
             Ok(mut focused_window) => {
-                // Initial window - fetch icon
-                let icon = get_icon_data(&conn, window, atoms.net_wm_icon, &config.icon).ok();
-                cached_icon = icon.clone();
-                focused_window.icon = icon;
+                focused_window.xwayland = Some(xwayland);
+                // Initial window - fetch icon, unless the window is filtered
+                let load = icon_loader(&shared_conn, window, &atoms, &config.icon);
+                cached_icon = WindowIcon::fetch(&focused_window, config, load);
+                cached_icon.apply_to(&mut focused_window);
 
-                if let Err(e) = on_focus(focused_window) {
-                    info!("Initial focus event handler failed: {}", e);
-                }
+                on_focus(Change::Focus(focused_window), detection)?;
                 // Set up monitoring for this window
                 current_focused_window = Some(window);
                 let _ = conn.change_window_attributes(
                     window,
                     &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
                 );
-                let _ = flush_connection(&conn);
+                let _ = flush_connection(conn);
             }
             Err(e) => {
                 info!("Failed to get initial window info: {}", e);
             }
+        },
+        Ok(None) if xwayland => {
+            // A native Wayland client (or nothing) has focus
+            let change = Change::Focus(FocusedWindow::unknown_wayland_client());
+            on_focus(change, detection)?;
         }
+        Ok(None) => {}
+        Err(e) => info!("Failed to get initial active window: {}", e),
     }
 
     // ── Event loop ─────────────────────────────────────────────────────────────
@@ -344,24 +152,41 @@ where
             break;
         }
 
-        let event = get_next_event(&conn, stop_signal, config)?;
+        let Some(event) = get_next_event(conn, stop_signal, config)? else {
+            break;
+        };
 
-        if let Event::PropertyNotify(PropertyNotifyEvent { atom, window, .. }) = event {
+        if let Event::PropertyNotify(PropertyNotifyEvent {
+            atom, window, time, ..
+        }) = event
+        {
+            let detection = Detection::now().with_server_time(time);
             let mut should_emit_focus_event = false;
             let mut new_window: Option<u32> = None;
             let mut is_focus_change = false;
+            let mut is_icon_change = false;
 
             // Check if this is an active window change
             if atom == atoms.net_active_window && window == root {
                 // Active window changed
-                match get_active_window(&conn, root, atoms.net_active_window) {
+                match get_active_window(conn, root, atoms.net_active_window) {
                     Ok(win) => {
+                        if win.is_none() && current_focused_window.is_some() {
+                            let change = if xwayland {
+                                // XWayland lost focus, presumably to a native Wayland client
+                                Change::Focus(FocusedWindow::unknown_wayland_client())
+                            } else {
+                                Change::Cleared
+                            };
+                            on_focus(change, detection)?;
+                        }
+
                         new_window = win;
                         should_emit_focus_event = true;
                         is_focus_change = true;
 
                         // Update monitoring for the new focused window
-                        update_window_monitoring(&conn, &mut current_focused_window, new_window);
+                        update_window_monitoring(conn, &mut current_focused_window, new_window);
                     }
                     Err(e) => {
                         info!("Failed to get active window: {}", e);
@@ -376,24 +201,40 @@ where
                 should_emit_focus_event = true;
                 is_focus_change = false;
             }
+            // Check if this is an icon change on the currently focused window
+            else if atom == atoms.net_wm_icon
+                && Some(window) == current_focused_window
+                && config.icon.mode != IconMode::Disabled
+            {
+                new_window = current_focused_window;
+                should_emit_focus_event = true;
+                is_icon_change = true;
+            }
 
             if should_emit_focus_event && let Some(window) = new_window {
-                match get_window_info(&conn, window, &atoms) {
+                match get_window_info(conn, window, &atoms) {
                     Ok(mut focused_window) => {
-                        // Only fetch icon when the focused app changes, not on title changes
-                        if is_focus_change {
-                            let icon =
-                                get_icon_data(&conn, window, atoms.net_wm_icon, &config.icon).ok();
-                            cached_icon = icon.clone();
-                            focused_window.icon = icon;
-                        } else {
-                            focused_window.icon = cached_icon.clone();
+                        focused_window.xwayland = Some(xwayland);
+                        // Only fetch icon when the focused app or its icon changes, not on title
+                        // changes
+                        if is_focus_change || is_icon_change {
+                            let load = icon_loader(&shared_conn, window, &atoms, &config.icon);
+                            cached_icon = if is_icon_change {
+                                WindowIcon::refetch(&focused_window, config, load)
+                            } else {
+                                WindowIcon::fetch(&focused_window, config, load)
+                            };
                         }
+                        cached_icon.apply_to(&mut focused_window);
 
-                        if let Err(e) = on_focus(focused_window) {
-                            info!("Focus event handler failed: {}", e);
-                            // Continue processing instead of propagating the error
-                        }
+                        let change = if is_focus_change {
+                            Change::Focus(focused_window)
+                        } else if is_icon_change {
+                            Change::Icon(focused_window)
+                        } else {
+                            Change::Update(focused_window)
+                        };
+                        on_focus(change, detection)?;
                     }
                     Err(e) => {
                         info!("Failed to get window info for window {}: {}", window, e);
@@ -402,7 +243,7 @@ where
             }
         }
 
-        flush_connection(&conn)?;
+        flush_connection(conn)?;
     }
 
     Ok(())
@@ -421,9 +262,79 @@ struct X11Atoms {
     net_wm_icon: u32,
 }
 
+/// X11 connection used to look up properties of windows that another backend told us about
+/// (e.g. the X11 window ID of a container in the i3 tree).
+pub(super) struct X11WindowProperties {
+    conn: RustConnection,
+    atoms: X11Atoms,
+}
+
+impl X11WindowProperties {
+    /// Connect to the X server named by `DISPLAY`.
+    pub(super) fn connect() -> FerrousFocusResult<Self> {
+        let (conn, _) = connect_to_x11()?;
+        let atoms = setup_atoms(&conn)?;
+        Ok(Self { conn, atoms })
+    }
+
+    /// Get the process ID stored in `_NET_WM_PID` of a window.
+    pub(super) fn process_id(&self, window: u32) -> Option<u32> {
+        get_process_info(&self.conn, window, self.atoms.net_wm_pid)
+            .map(|(pid, _)| pid)
+            .ok()
+    }
+
+    /// Get the `_NET_WM_ICON` of a window.
+    pub(super) fn icon(
+        &self,
+        window: u32,
+        icon_config: &IconConfig,
+    ) -> FerrousFocusResult<image::RgbaImage> {
+        get_icon_data(&self.conn, window, self.atoms.net_wm_icon, icon_config)
+    }
+}
+
+/// What the X server reveals about the session.
+pub(super) struct X11ServerInfo {
+    /// `_NET_WM_NAME` of the `_NET_SUPPORTING_WM_CHECK` window, if an EWMH window manager runs.
+    pub(super) wm_name: Option<String>,
+    /// Whether the server is XWayland.
+    pub(super) xwayland: bool,
+}
+
+/// Query the window manager name and server kind of the X server named by `DISPLAY`.
+pub(super) fn server_info() -> FerrousFocusResult<X11ServerInfo> {
+    let (conn, screen_num) = connect_to_x11()?;
+    let root = conn.setup().roots[screen_num].root;
+    let atoms = setup_atoms(&conn)?;
+    let supporting_wm_check = get_atom(&conn, b"_NET_SUPPORTING_WM_CHECK")?;
+
+    let wm_window = conn
+        .get_property(false, root, supporting_wm_check, AtomEnum::WINDOW, 0, 1)
+        .map_err(|e| FerrousFocusError::Platform(e.to_string()))?
+        .reply()
+        .map_err(|e| FerrousFocusError::Platform(e.to_string()))?
+        .value32()
+        .and_then(|mut v| v.next());
+    let wm_name = wm_window.and_then(|window| {
+        try_get_property_string(&conn, window, atoms.net_wm_name, atoms.utf8_string)
+            .ok()
+            .flatten()
+    });
+
+    let xwayland = conn
+        .query_extension(b"XWAYLAND")
+        .map_err(|e| FerrousFocusError::Platform(e.to_string()))?
+        .reply()
+        .map_err(|e| FerrousFocusError::Platform(e.to_string()))?
+        .present;
+
+    Ok(X11ServerInfo { wm_name, xwayland })
+}
+
 /// Check if the stop signal is set.
-fn should_stop(stop_signal: Option<&AtomicBool>) -> bool {
-    stop_signal.is_some_and(|stop| stop.load(Ordering::Acquire))
+fn should_stop(stop_signal: Option<&StopSignal>) -> bool {
+    stop_signal.is_some_and(StopSignal::is_stopped)
 }
 
 /// Connect to X11 server with proper error handling.
@@ -468,27 +379,28 @@ fn setup_root_window_monitoring<C: Connection>(conn: &C, root: u32) -> FerrousFo
 }
 
 /// Get the next X11 event, handling both polling and blocking modes.
+///
+/// Returns `Ok(None)` once the stop signal is set.
 fn get_next_event<C: Connection>(
     conn: &C,
-    stop_signal: Option<&AtomicBool>,
+    stop_signal: Option<&StopSignal>,
     config: &FocusTrackerConfig,
-) -> FerrousFocusResult<Event> {
+) -> FerrousFocusResult<Option<Event>> {
     match stop_signal {
-        Some(_) => {
+        Some(stop_signal) => {
             // Use polling when stop signal is available
             loop {
-                match conn.poll_for_event() {
-                    Ok(Some(e)) => return Ok(e),
-                    Ok(None) => {
-                        // No event available, sleep briefly to avoid busy waiting
-                        std::thread::sleep(config.poll_interval);
-                        continue;
-                    }
+                let stopped = match conn.poll_for_event() {
+                    Ok(Some(e)) => return Ok(Some(e)),
+                    // No event available, sleep briefly to avoid busy waiting
+                    Ok(None) => stop_signal.sleep(config.poll_interval),
                     Err(e) => {
                         info!("X11 error: {e}");
-                        std::thread::sleep(std::time::Duration::from_secs(1));
-                        continue;
+                        stop_signal.sleep(std::time::Duration::from_secs(1))
                     }
+                };
+                if stopped {
+                    return Ok(None);
                 }
             }
         }
@@ -496,7 +408,7 @@ fn get_next_event<C: Connection>(
             // Use blocking wait when no stop signal
             loop {
                 match conn.wait_for_event() {
-                    Ok(e) => return Ok(e),
+                    Ok(e) => return Ok(Some(e)),
                     Err(e) => {
                         info!("X11 error: {e}");
                         std::thread::sleep(std::time::Duration::from_secs(1));
@@ -561,10 +473,12 @@ fn get_window_info<C: Connection>(
         });
 
     Ok(FocusedWindow {
+        window_id: Some(WindowId::from_number(window.into())),
         process_id,
         process_name,
         window_title: Some(title),
         icon: None,
+        ..Default::default()
     })
 }
 
@@ -664,13 +578,7 @@ fn get_process_info<C: Connection>(
         .and_then(|mut v| v.next())
         .ok_or_else(|| FerrousFocusError::Platform("No PID found for window".to_string()))?;
 
-    // read /proc/<pid>/comm (single line: executable name)
-    let process_name = std::fs::read_to_string(format!("/proc/{pid}/comm"))
-        .or_else(|_| {
-            std::fs::read_link(format!("/proc/{pid}/exe")).map(|p| p.to_string_lossy().into())
-        })
-        .map(|name| name.trim_end_matches('\n').to_owned())
-        .map_err(|e| FerrousFocusError::Platform(format!("Failed to get process name: {e}")))?;
+    let process_name = utils::process_name(pid)?;
 
     Ok((pid, process_name))
 }
@@ -689,12 +597,26 @@ fn resize_icon(
     image::imageops::resize(&image, target_size, target_size, filter_type)
 }
 
+/// Fetch the `_NET_WM_ICON` of a window over `conn` whenever called, e.g. later by an
+/// [`IconHandle`](crate::IconHandle).
+fn icon_loader(
+    conn: &Arc<RustConnection>,
+    window: u32,
+    atoms: &X11Atoms,
+    icon_config: &IconConfig,
+) -> impl Fn() -> FerrousFocusResult<image::RgbaImage> + Send + Sync + 'static {
+    let conn = Arc::clone(conn);
+    let net_wm_icon = atoms.net_wm_icon;
+    let icon_config = icon_config.clone();
+    move || get_icon_data(&*conn, window, net_wm_icon, &icon_config)
+}
+
 /// Get icon data for a window and return it as an image::RgbaImage.
 fn get_icon_data<C: Connection>(
     conn: &C,
     window: u32,
     net_wm_icon: u32,
-    icon_config: &crate::config::IconConfig,
+    icon_config: &IconConfig,
 ) -> FerrousFocusResult<image::RgbaImage> {
     let cookie = conn
         .get_property(
@@ -1,10 +1,12 @@
-use crate::{FerrousFocusResult, FocusTrackerConfig, FocusedWindow};
-use std::sync::atomic::{AtomicBool, Ordering};
+use crate::{
+    Backend, FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    config::IconConfig,
+    focus_event::{Change, Detection},
+    icon_handle::WindowIcon,
+    stop_signal::{self, StopSignal},
+};
 use tracing::debug;
 
-#[cfg(feature = "async")]
-use std::future::Future;
-
 use super::utils;
 
 #[derive(Debug, Clone)]
@@ -16,99 +18,102 @@ impl ImplFocusTracker {
     }
 }
 
+/// Asks which window is focused; there is no connection to keep on macOS.
+pub(crate) struct ImplFocusQuery {}
+
+impl ImplFocusQuery {
+    pub(crate) fn connect(_backend: Backend) -> FerrousFocusResult<Self> {
+        Ok(Self {})
+    }
+
+    pub(crate) fn current(
+        &mut self,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let mut window = utils::get_frontmost_window_basic_info()?;
+        if let Some(pid) = window.process_id {
+            WindowIcon::fetch(&window, config, icon_loader(pid, &config.icon))
+                .apply_to(&mut window);
+        }
+        Ok(Some(window))
+    }
+}
+
+/// Backends of this platform, in the order they are preferred.
+pub(crate) const BACKENDS: &[Backend] = &[Backend::MacOs];
+
+/// Pick the backend that matches the running session.
+pub(crate) fn detect() -> FerrousFocusResult<Backend> {
+    Ok(Backend::MacOs)
+}
+
+/// Check if a backend can run in this session, or explain why not.
+pub(crate) fn probe(backend: Backend) -> Result<(), String> {
+    match backend {
+        Backend::MacOs => Ok(()),
+        _ => Err("not supported on this platform".to_string()),
+    }
+}
+
 /// Tracks the previous focus state for change detection.
 /// Uses references to avoid cloning strings on every poll.
 #[derive(Default)]
 struct FocusState {
+    window_id: Option<WindowId>,
     process_name: Option<String>,
     window_title: Option<String>,
 }
 
 impl FocusState {
     /// Check if the window has changed compared to the current state.
-    /// Returns true if process_name or window_title differs.
+    /// Returns true if window_id, process_name or window_title differs.
     fn has_changed(&self, window: &FocusedWindow) -> bool {
-        self.process_name.as_deref() != window.process_name.as_deref()
-            || self.window_title.as_deref() != window.window_title.as_deref()
+        self.focus_changed(window) || self.window_title.as_deref() != window.window_title.as_deref()
+    }
+
+    /// Check if another window than the one in the current state is frontmost.
+    fn focus_changed(&self, window: &FocusedWindow) -> bool {
+        self.window_id != window.window_id
+            || self.process_name.as_deref() != window.process_name.as_deref()
     }
 
     /// Update the state from the given window.
     /// Only clones when necessary (when focus actually changed).
     fn update_from(&mut self, window: &FocusedWindow) {
+        self.window_id = window.window_id.clone();
         self.process_name = window.process_name.clone();
         self.window_title = window.window_title.clone();
     }
 }
 
-/// Check if the stop signal is set.
-#[inline]
-fn should_stop(stop_signal: Option<&AtomicBool>) -> bool {
-    stop_signal.is_some_and(|stop| stop.load(Ordering::Relaxed))
-}
-
 impl ImplFocusTracker {
-    pub fn track_focus<F>(&self, on_focus: F, config: &FocusTrackerConfig) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        self.run(on_focus, None, config)
-    }
-
     pub fn track_focus_with_stop<F>(
         &self,
-        on_focus: F,
-        stop_signal: &AtomicBool,
-        config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        self.run(on_focus, Some(stop_signal), config)
-    }
-
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async<F, Fut>(
-        &self,
-        on_focus: F,
-        config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
-    {
-        self.run_async(on_focus, None, config).await
-    }
-
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async_with_stop<F, Fut>(
-        &self,
-        on_focus: F,
-        stop_signal: &AtomicBool,
+        on_change: F,
+        _backend: Backend,
+        stop_signal: &StopSignal,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
-        self.run_async(on_focus, Some(stop_signal), config).await
+        self.run(on_change, Some(stop_signal), config)
     }
 
-    #[cfg(feature = "async")]
-    async fn run_async<F, Fut>(
+    fn run<F>(
         &self,
-        mut on_focus: F,
-        stop_signal: Option<&AtomicBool>,
+        mut on_change: F,
+        stop_signal: Option<&StopSignal>,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
         let mut prev_state = FocusState::default();
 
         loop {
             // Check stop signal before processing
-            if should_stop(stop_signal) {
+            if stop_signal.is_some_and(StopSignal::is_stopped) {
                 debug!("Stop signal received, exiting focus tracking loop");
                 break;
             }
@@ -118,15 +123,21 @@ impl ImplFocusTracker {
                 Ok(mut window) => {
                     // Only fetch icon and report when focus actually changed
                     if prev_state.has_changed(&window) {
-                        // Fetch icon only when focus changed (expensive operation)
+                        let detection = Detection::now();
+                        // Fetch icon only when focus changed (expensive operation), and never
+                        // for filtered windows
                         if let Some(pid) = window.process_id {
-                            match utils::fetch_icon_for_pid(pid as i32, &config.icon) {
-                                Ok(icon) => window.icon = icon,
-                                Err(e) => debug!("Error fetching icon: {}", e),
-                            }
+                            WindowIcon::fetch(&window, config, icon_loader(pid, &config.icon))
+                                .apply_to(&mut window);
                         }
+                        let focus_changed = prev_state.focus_changed(&window);
                         prev_state.update_from(&window);
-                        on_focus(window).await?;
+                        let change = if focus_changed {
+                            Change::Focus(window)
+                        } else {
+                            Change::Update(window)
+                        };
+                        on_change(change, detection)?;
                     }
                 }
                 Err(e) => {
@@ -134,54 +145,26 @@ impl ImplFocusTracker {
                 }
             }
 
-            tokio::time::sleep(config.poll_interval).await;
-        }
-
-        Ok(())
-    }
-
-    fn run<F>(
-        &self,
-        mut on_focus: F,
-        stop_signal: Option<&AtomicBool>,
-        config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        let mut prev_state = FocusState::default();
-
-        loop {
-            // Check stop signal before processing
-            if should_stop(stop_signal) {
+            if stop_signal::sleep(stop_signal, config.poll_interval) {
                 debug!("Stop signal received, exiting focus tracking loop");
                 break;
             }
-
-            // Get basic window info first (without icon - fast)
-            match utils::get_frontmost_window_basic_info() {
-                Ok(mut window) => {
-                    // Only fetch icon and report when focus actually changed
-                    if prev_state.has_changed(&window) {
-                        // Fetch icon only when focus changed (expensive operation)
-                        if let Some(pid) = window.process_id {
-                            match utils::fetch_icon_for_pid(pid as i32, &config.icon) {
-                                Ok(icon) => window.icon = icon,
-                                Err(e) => debug!("Error fetching icon: {}", e),
-                            }
-                        }
-                        prev_state.update_from(&window);
-                        on_focus(window)?;
-                    }
-                }
-                Err(e) => {
-                    debug!("Error getting window info: {}", e);
-                }
-            }
-
-            std::thread::sleep(config.poll_interval);
         }
 
         Ok(())
     }
 }
+
+/// Fetch the icon of the application with the given process ID whenever called, e.g. later by an
+/// [`IconHandle`](crate::IconHandle).
+fn icon_loader(
+    pid: u32,
+    icon_config: &IconConfig,
+) -> impl Fn() -> FerrousFocusResult<image::RgbaImage> + Send + Sync + 'static {
+    let icon_config = icon_config.clone();
+    move || {
+        utils::fetch_icon_for_pid(pid as i32, &icon_config)
+            .inspect_err(|e| debug!("Error fetching icon: {}", e))?
+            .ok_or(FerrousFocusError::Unsupported)
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{FocusedWindow, config::IconConfig, error::FerrousFocusResult};
+use crate::{FocusedWindow, WindowId, config::IconConfig, error::FerrousFocusResult};
 use core_foundation::array::{CFArray, CFArrayRef};
 use core_foundation::base::{CFType, TCFType};
 use core_foundation::dictionary::CFDictionary;
@@ -13,7 +13,7 @@ use objc2_app_kit::{
     NSWorkspace,
 };
 use objc2_foundation::{NSDictionary, NSPoint, NSRect, NSSize, NSString, ns_string};
-use std::ffi::c_void;
+use std::{ffi::c_void, path::PathBuf};
 
 #[link(name = "ApplicationServices", kind = "framework")]
 unsafe extern "C" {
@@ -53,7 +53,7 @@ const K_CG_NULL_WINDOW_ID: u32 = 0;
 /// Get basic window info without icon (for change detection)
 pub fn get_frontmost_window_basic_info() -> FerrousFocusResult<FocusedWindow> {
     autoreleasepool(|_pool| {
-        let pid = get_frontmost_window_pid()?;
+        let (pid, window_number) = get_frontmost_window()?;
 
         let running_app = NSRunningApplication::runningApplicationWithProcessIdentifier(pid);
 
@@ -67,10 +67,12 @@ pub fn get_frontmost_window_basic_info() -> FerrousFocusResult<FocusedWindow> {
         let window_title = get_window_title_via_accessibility(pid)?;
 
         Ok(FocusedWindow {
+            window_id: window_number.map(|number| WindowId::from_number(number.into())),
             process_id: Some(pid as u32),
             window_title,
             process_name,
             icon: None,
+            ..Default::default()
         })
     })
 }
@@ -90,7 +92,18 @@ pub fn fetch_icon_for_pid(
     })
 }
 
-fn get_frontmost_window_pid() -> FerrousFocusResult<i32> {
+/// Get the path of the executable the given process runs
+pub(crate) fn executable_path(pid: u32) -> Option<PathBuf> {
+    autoreleasepool(|_pool| {
+        let running_app =
+            NSRunningApplication::runningApplicationWithProcessIdentifier(pid as i32)?;
+        let path = running_app.executableURL()?.path()?;
+        Some(PathBuf::from(path.to_string()))
+    })
+}
+
+/// Get the owner PID and the window number (CGWindowID) of the frontmost normal window.
+fn get_frontmost_window() -> FerrousFocusResult<(i32, Option<u32>)> {
     unsafe {
         // Get list of all on-screen windows, ordered by front-to-back
         let options =
@@ -113,6 +126,7 @@ fn get_frontmost_window_pid() -> FerrousFocusResult<i32> {
 
         let layer_key = CFString::from_static_string("kCGWindowLayer");
         let pid_key = CFString::from_static_string("kCGWindowOwnerPID");
+        let number_key = CFString::from_static_string("kCGWindowNumber");
 
         // Find the first window at layer 0 (normal application windows)
         for i in 0..window_list.len() {
@@ -154,7 +168,15 @@ fn get_frontmost_window_pid() -> FerrousFocusResult<i32> {
                 )
             })?;
 
-            return Ok(pid);
+            let window_number = window_info
+                .find(number_key.as_CFTypeRef() as *const _)
+                .and_then(|number_ptr| {
+                    CFType::wrap_under_get_rule(number_ptr.cast()).downcast::<CFNumber>()
+                })
+                .and_then(|number| number.to_i64())
+                .and_then(|number| u32::try_from(number).ok());
+
+            return Ok((pid, window_number));
         }
 
         Err(crate::error::FerrousFocusError::Platform(
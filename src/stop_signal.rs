@@ -0,0 +1,183 @@
+use std::{
+    sync::{
+        Condvar, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+type Waker = Box<dyn FnOnce() + Send>;
+
+/// Tells a running backend to stop.
+///
+/// [`StopSignal::stop`] takes effect right away: it interrupts [`StopSignal::sleep`] and runs the
+/// wakers backends register to unblock their reads. A caller's `AtomicBool` (from the
+/// `*_with_stop` APIs) can be observed as well, but is only noticed the next time the backend
+/// checks.
+#[derive(Default)]
+pub(crate) struct StopSignal<'a> {
+    external: Option<&'a AtomicBool>,
+    state: Mutex<State>,
+    condvar: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+    stopped: bool,
+    next_id: usize,
+    wakers: Vec<(usize, Waker)>,
+}
+
+impl<'a> StopSignal<'a> {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// A signal that is also set by the caller's `stop_signal`.
+    pub(crate) fn with_external(stop_signal: &'a AtomicBool) -> Self {
+        Self {
+            external: Some(stop_signal),
+            ..Self::default()
+        }
+    }
+
+    /// Ask the backend to stop and wake it up.
+    pub(crate) fn stop(&self) {
+        let wakers = {
+            let mut state = self.lock();
+            if state.stopped {
+                return;
+            }
+            state.stopped = true;
+            std::mem::take(&mut state.wakers)
+        };
+        self.condvar.notify_all();
+
+        for (_, waker) in wakers {
+            waker();
+        }
+    }
+
+    pub(crate) fn is_stopped(&self) -> bool {
+        self.external
+            .is_some_and(|stop| stop.load(Ordering::Acquire))
+            || self.lock().stopped
+    }
+
+    /// Sleep for `duration`, or less if the signal is stopped in the meantime.
+    ///
+    /// Returns `true` if the backend should stop.
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let state = self.lock();
+        let _ = self
+            .condvar
+            .wait_timeout_while(state, duration, |state| !state.stopped)
+            .unwrap_or_else(PoisonError::into_inner);
+        self.is_stopped()
+    }
+
+    /// Run `waker` when the signal is stopped, e.g. to unblock a read, for as long as the
+    /// returned guard lives. Runs it right away if the signal is already stopped.
+    pub(crate) fn on_stop(&self, waker: impl FnOnce() + Send + 'static) -> WakerGuard<'_> {
+        let mut state = self.lock();
+        let id = state.next_id;
+        state.next_id += 1;
+        if state.stopped {
+            drop(state);
+            waker();
+        } else {
+            state.wakers.push((id, Box::new(waker)));
+        }
+        WakerGuard { signal: self, id }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Sleep for `duration`, or less if `stop_signal` is stopped in the meantime.
+///
+/// Returns `true` if the backend should stop.
+pub(crate) fn sleep(stop_signal: Option<&StopSignal>, duration: Duration) -> bool {
+    match stop_signal {
+        Some(stop_signal) => stop_signal.sleep(duration),
+        None => {
+            std::thread::sleep(duration);
+            false
+        }
+    }
+}
+
+/// Unregisters a waker of a [`StopSignal`] when dropped.
+#[must_use = "the waker is unregistered when the guard is dropped"]
+pub(crate) struct WakerGuard<'s> {
+    signal: &'s StopSignal<'s>,
+    id: usize,
+}
+
+impl Drop for WakerGuard<'_> {
+    fn drop(&mut self) {
+        self.signal.lock().wakers.retain(|(id, _)| *id != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{Arc, atomic::AtomicUsize},
+        thread,
+        time::Instant,
+    };
+
+    #[test]
+    fn test_stop_interrupts_sleep() {
+        let signal = Arc::new(StopSignal::new());
+        let sleeper = {
+            let signal = Arc::clone(&signal);
+            thread::spawn(move || {
+                let start = Instant::now();
+                (signal.sleep(Duration::from_secs(30)), start.elapsed())
+            })
+        };
+
+        thread::sleep(Duration::from_millis(20));
+        signal.stop();
+        let (stopped, elapsed) = sleeper.join().unwrap();
+        assert!(stopped);
+        assert!(elapsed < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_wakers_run_once_while_registered() {
+        let signal = StopSignal::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let waker = |calls: &Arc<AtomicUsize>| {
+            let calls = Arc::clone(calls);
+            move || {
+                calls.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+
+        drop(signal.on_stop(waker(&calls)));
+        let _guard = signal.on_stop(waker(&calls));
+        signal.stop();
+        signal.stop();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Already stopped
+        let _guard = signal.on_stop(waker(&calls));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_external_flag() {
+        let flag = AtomicBool::new(false);
+        let signal = StopSignal::with_external(&flag);
+        assert!(!signal.is_stopped());
+        flag.store(true, Ordering::Release);
+        assert!(signal.is_stopped());
+        assert!(signal.sleep(Duration::from_millis(1)));
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{FerrousFocusResult, FocusedWindow, TrackerHandle, focus_hub::HubMembership};
+use std::{
+    sync::mpsc::{Iter, Receiver, RecvError, RecvTimeoutError, TryIter, TryRecvError},
+    time::Duration,
+};
+
+#[cfg(feature = "async")]
+use futures::{Stream, StreamExt, channel::mpsc::UnboundedReceiver};
+#[cfg(feature = "async")]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// Focus changes delivered over a channel by a tracker running in the background, created with
+/// [`FocusTracker::subscribe_focus_changes`](crate::FocusTracker::subscribe_focus_changes) or
+/// [`FocusHub::subscribe`](crate::FocusHub::subscribe).
+///
+/// Receiving works like [`std::sync::mpsc::Receiver`]. The channel disconnects once tracking has
+/// ended; [`Subscription::unsubscribe`] then tells why. Dropping the subscription stops the
+/// tracker, or leaves the hub.
+#[derive(Debug)]
+pub struct Subscription {
+    // Dropped first: the tracker is stopped before its channel disconnects
+    owner: Owner,
+    receiver: Receiver<FocusedWindow>,
+}
+
+/// What keeps the events of a subscription coming.
+#[derive(Debug)]
+enum Owner {
+    /// A tracker of its own.
+    Tracker(TrackerHandle),
+    /// A tracker shared through a hub.
+    Hub(HubMembership),
+}
+
+impl Subscription {
+    pub(crate) fn new(receiver: Receiver<FocusedWindow>, handle: TrackerHandle) -> Self {
+        Self {
+            receiver,
+            owner: Owner::Tracker(handle),
+        }
+    }
+
+    pub(crate) fn from_hub(receiver: Receiver<FocusedWindow>, membership: HubMembership) -> Self {
+        Self {
+            receiver,
+            owner: Owner::Hub(membership),
+        }
+    }
+
+    /// Wait for the next focus change.
+    pub fn recv(&self) -> Result<FocusedWindow, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Wait at most `timeout` for the next focus change.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<FocusedWindow, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// Get the next focus change if there is one, without waiting.
+    pub fn try_recv(&self) -> Result<FocusedWindow, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Iterate over focus changes until tracking ends.
+    pub fn iter(&self) -> Iter<'_, FocusedWindow> {
+        self.receiver.iter()
+    }
+
+    /// Iterate over the focus changes received so far, without waiting.
+    pub fn try_iter(&self) -> TryIter<'_, FocusedWindow> {
+        self.receiver.try_iter()
+    }
+
+    /// Check if the tracker is still running, i.e. more focus changes may arrive.
+    pub fn is_active(&self) -> bool {
+        match &self.owner {
+            Owner::Tracker(handle) => handle.is_running(),
+            Owner::Hub(membership) => membership.is_active(),
+        }
+    }
+
+    /// Stop tracking and return how it ended.
+    ///
+    /// Returns the backend's error if tracking had already failed, e.g. because the display
+    /// connection was lost; focus changes still in the channel are discarded. A hub subscription
+    /// leaves the hub instead and returns `Ok`; see
+    /// [`FocusHub::take_error`](crate::FocusHub::take_error) for failures of the shared tracker.
+    pub fn unsubscribe(self) -> FerrousFocusResult<()> {
+        match self.owner {
+            Owner::Tracker(handle) => {
+                handle.stop();
+                handle.join()
+            }
+            Owner::Hub(_) => Ok(()),
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Subscription {
+    type Item = FocusedWindow;
+    type IntoIter = Iter<'a, FocusedWindow>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Focus changes delivered to async code by a [`FocusHub`](crate::FocusHub) - requires the
+/// "async" feature
+///
+/// Also a [`Stream`](futures::Stream) of focus changes. Dropping the subscription leaves the hub.
+#[cfg(feature = "async")]
+#[derive(Debug)]
+pub struct AsyncSubscription {
+    receiver: UnboundedReceiver<FocusedWindow>,
+    _membership: HubMembership,
+}
+
+#[cfg(feature = "async")]
+impl AsyncSubscription {
+    pub(crate) fn new(
+        receiver: UnboundedReceiver<FocusedWindow>,
+        membership: HubMembership,
+    ) -> Self {
+        Self {
+            receiver,
+            _membership: membership,
+        }
+    }
+
+    /// Wait for the next focus change, or `None` once the hub's tracker has failed.
+    pub async fn recv(&mut self) -> Option<FocusedWindow> {
+        self.receiver.next().await
+    }
+
+    /// Get the next focus change if there is one, without waiting.
+    pub fn try_recv(&mut self) -> Option<FocusedWindow> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+#[cfg(feature = "async")]
+impl Stream for AsyncSubscription {
+    type Item = FocusedWindow;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
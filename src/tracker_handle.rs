@@ -0,0 +1,68 @@
+use crate::{FerrousFocusError, FerrousFocusResult, stop_signal::StopSignal};
+use std::{fmt, sync::Arc, thread::JoinHandle};
+
+/// A focus tracker running on its own thread, started with
+/// [`FocusTracker::spawn`](crate::FocusTracker::spawn).
+///
+/// Dropping the handle stops the tracker without waiting for its thread to finish; call
+/// [`TrackerHandle::join`] to wait for it and get its result.
+pub struct TrackerHandle {
+    stop_signal: Arc<StopSignal<'static>>,
+    thread: Option<JoinHandle<FerrousFocusResult<()>>>,
+}
+
+impl TrackerHandle {
+    pub(crate) fn new(
+        stop_signal: Arc<StopSignal<'static>>,
+        thread: JoinHandle<FerrousFocusResult<()>>,
+    ) -> Self {
+        Self {
+            stop_signal,
+            thread: Some(thread),
+        }
+    }
+
+    /// Ask the tracker to stop.
+    ///
+    /// Backends are woken up right away instead of at their next poll. Does not wait for the
+    /// thread to finish; stopping twice has no further effect.
+    pub fn stop(&self) {
+        self.stop_signal.stop();
+    }
+
+    /// Check if the tracker thread is still running.
+    ///
+    /// The tracker also finishes by itself when its backend fails, e.g. when the display server
+    /// goes away.
+    pub fn is_running(&self) -> bool {
+        self.thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+    }
+
+    /// Wait for the tracker thread to finish and return how tracking ended.
+    ///
+    /// This does not stop the tracker; call [`TrackerHandle::stop`] first unless it is expected
+    /// to end by itself.
+    pub fn join(mut self) -> FerrousFocusResult<()> {
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => Err(FerrousFocusError::new("Focus tracker thread panicked")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Debug for TrackerHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackerHandle")
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+impl Drop for TrackerHandle {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
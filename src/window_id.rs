@@ -0,0 +1,116 @@
+use std::{fmt, sync::Arc};
+
+/// Identifies a window for as long as it exists.
+///
+/// Opaque: compare, hash or print it, e.g. to keep state per window. Two windows with the same
+/// title and application still have different IDs. IDs come from the backend that reported the
+/// window (the X11 window, the sway container, the HWND, ...), so they are only comparable
+/// between windows reported by the same backend.
+///
+/// [`IconHandle::window_id`](crate::IconHandle::window_id) tells which window a lazily loaded
+/// icon belongs to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct WindowId(RawId);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+enum RawId {
+    Number(u64),
+    Text(Arc<str>),
+}
+
+impl WindowId {
+    /// An ID the backend reports as a number, e.g. an X11 window.
+    pub(crate) fn from_number(id: u64) -> Self {
+        Self(RawId::Number(id))
+    }
+
+    /// An ID the backend reports as a string, e.g. a KWin UUID.
+    pub(crate) fn from_text(id: &str) -> Self {
+        Self(RawId::Text(id.into()))
+    }
+}
+
+impl fmt::Display for WindowId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            RawId::Number(id) => write!(f, "{id:#x}"),
+            RawId::Text(id) => f.write_str(id),
+        }
+    }
+}
+
+/// Identifies an application, grouping its windows.
+///
+/// Opaque: compare, hash or print it. Taken from the window class (the Wayland `app_id`, the
+/// X11 `WM_CLASS`) when the backend reports one, else from the process name.
+///
+/// [`FocusTracker::cached_icon`](crate::FocusTracker::cached_icon) looks up the icon of an app
+/// by it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct AppId(Arc<str>);
+
+impl AppId {
+    pub(crate) fn new(id: &str) -> Self {
+        Self(id.into())
+    }
+}
+
+impl fmt::Display for AppId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FocusedWindow;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_ids_tell_windows_apart() {
+        let ids: HashSet<_> = [
+            WindowId::from_number(0x3a00007),
+            WindowId::from_number(0x3a00007),
+            WindowId::from_number(0x3a00008),
+            WindowId::from_text("{4a1f}"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(ids.len(), 3);
+        assert_eq!(WindowId::from_number(0x3a00007).to_string(), "0x3a00007");
+        assert_eq!(WindowId::from_text("{4a1f}").to_string(), "{4a1f}");
+    }
+
+    #[test]
+    fn test_app_id_prefers_window_class() {
+        let window = FocusedWindow {
+            process_name: Some("firefox-bin".to_string()),
+            window_class: Some("firefox".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(window.app_id(), Some(AppId::new("firefox")));
+
+        let window = FocusedWindow {
+            window_class: None,
+            ..window
+        };
+        assert_eq!(window.app_id(), Some(AppId::new("firefox-bin")));
+        assert_eq!(FocusedWindow::unknown_wayland_client().app_id(), None);
+    }
+}
@@ -1,8 +1,10 @@
-use crate::{FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow};
-use std::sync::atomic::{AtomicBool, Ordering};
-
-#[cfg(feature = "async")]
-use std::future::Future;
+use crate::{
+    Backend, FerrousFocusError, FerrousFocusResult, FocusTrackerConfig, FocusedWindow, WindowId,
+    config::IconConfig,
+    focus_event::{Change, Detection},
+    icon_handle::WindowIcon,
+    stop_signal::{self, StopSignal},
+};
 use windows_sys::Win32::{
     Foundation::{HWND, WPARAM},
     Graphics::Gdi::{
@@ -26,170 +28,78 @@ impl ImplFocusTracker {
     }
 }
 
-impl ImplFocusTracker {
-    pub fn track_focus<F>(&self, on_focus: F, config: &FocusTrackerConfig) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        self.run(on_focus, None, config)
-    }
+/// Asks which window is focused; there is no connection to keep on Windows.
+pub(crate) struct ImplFocusQuery {}
 
-    pub fn track_focus_with_stop<F>(
-        &self,
-        on_focus: F,
-        stop_signal: &AtomicBool,
-        config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
-    {
-        self.run(on_focus, Some(stop_signal), config)
+impl ImplFocusQuery {
+    pub(crate) fn connect(_backend: Backend) -> FerrousFocusResult<Self> {
+        if !utils::is_interactive_session()? {
+            return Err(FerrousFocusError::NotInteractiveSession);
+        }
+        Ok(Self {})
     }
 
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async<F, Fut>(
-        &self,
-        on_focus: F,
+    pub(crate) fn current(
+        &mut self,
         config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
-    {
-        self.run_async(on_focus, None, config).await
+    ) -> FerrousFocusResult<Option<FocusedWindow>> {
+        let Some(hwnd) = utils::get_foreground_window() else {
+            return Ok(None);
+        };
+
+        let (title, process) = unsafe { utils::get_window_info(hwnd) }?;
+        let process_id = unsafe { utils::get_window_process_id(hwnd) }.unwrap_or_default();
+        let mut window = FocusedWindow {
+            window_id: Some(window_id(hwnd as isize)),
+            process_id: Some(process_id),
+            process_name: Some(process),
+            window_title: Some(title),
+            ..Default::default()
+        };
+        let load = icon_loader(hwnd as isize, &config.icon);
+        WindowIcon::fetch(&window, config, load).apply_to(&mut window);
+        Ok(Some(window))
     }
+}
 
-    #[cfg(feature = "async")]
-    pub async fn track_focus_async_with_stop<F, Fut>(
-        &self,
-        on_focus: F,
-        stop_signal: &AtomicBool,
-        config: &FocusTrackerConfig,
-    ) -> FerrousFocusResult<()>
-    where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
-    {
-        self.run_async(on_focus, Some(stop_signal), config).await
+/// Backends of this platform, in the order they are preferred.
+pub(crate) const BACKENDS: &[Backend] = &[Backend::Windows];
+
+/// Pick the backend that matches the running session.
+pub(crate) fn detect() -> FerrousFocusResult<Backend> {
+    Ok(Backend::Windows)
+}
+
+/// Check if a backend can run in this session, or explain why not.
+pub(crate) fn probe(backend: Backend) -> Result<(), String> {
+    match backend {
+        Backend::Windows => Ok(()),
+        _ => Err("not supported on this platform".to_string()),
     }
+}
 
-    #[cfg(feature = "async")]
-    async fn run_async<F, Fut>(
+impl ImplFocusTracker {
+    pub fn track_focus_with_stop<F>(
         &self,
-        mut on_focus: F,
-        stop_signal: Option<&AtomicBool>,
+        on_change: F,
+        _backend: Backend,
+        stop_signal: &StopSignal,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> Fut,
-        Fut: Future<Output = FerrousFocusResult<()>>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
-        // Check if we're in an interactive session
-        if !utils::is_interactive_session()? {
-            return Err(FerrousFocusError::NotInteractiveSession);
-        }
-
-        // Track the previously focused window to avoid duplicate events
-        // Store HWND as isize to ensure Send trait is satisfied for async contexts
-        let mut prev_hwnd: Option<isize> = None;
-        let mut prev_title: Option<String> = None;
-        // Cache the icon for the currently focused window (only fetch on app change)
-        let mut cached_icon: Option<image::RgbaImage> = None;
-
-        // Get initial focused window - use helper function to avoid non-Send types
-        if let Some(window_info) = get_window_info_without_icon() {
-            // Initial window - fetch icon
-            let icon = get_window_icon_by_hwnd(window_info.hwnd_value, &config.icon);
-            cached_icon = icon.clone();
-
-            let focused_window = FocusedWindow {
-                process_id: window_info.process_id,
-                process_name: window_info.process_name,
-                window_title: window_info.window_title.clone(),
-                icon,
-            };
-
-            if let Err(e) = on_focus(focused_window).await {
-                info!("Focus event handler failed: {}", e);
-            }
-
-            prev_hwnd = Some(window_info.hwnd_value);
-            prev_title = window_info.window_title;
-        }
-
-        // Main event loop - we'll use polling since Windows event hooks are complex to integrate
-        // with Rust's async runtime in a cross-platform way
-        loop {
-            // Check stop signal before processing
-            if let Some(stop) = stop_signal
-                && stop.load(Ordering::Relaxed)
-            {
-                break;
-            }
-
-            // Check current foreground window - use helper function to avoid non-Send types
-            if let Some(window_info) = get_window_info_without_icon() {
-                let current_hwnd_value = window_info.hwnd_value;
-                let focus_changed = match prev_hwnd {
-                    Some(prev) => prev != current_hwnd_value,
-                    None => true,
-                };
-
-                // Also check if title changed for the same window
-                let title_changed = match &prev_title {
-                    Some(prev_t) => Some(prev_t) != window_info.window_title.as_ref(),
-                    None => true,
-                };
-
-                // Trigger handler if either window focus or title has changed
-                if focus_changed || title_changed {
-                    // Only fetch icon when the focused app changes, not on title changes
-                    let icon = if focus_changed {
-                        let new_icon = get_window_icon_by_hwnd(current_hwnd_value, &config.icon);
-                        cached_icon = new_icon.clone();
-                        new_icon
-                    } else {
-                        cached_icon.clone()
-                    };
-
-                    let focused_window = FocusedWindow {
-                        process_id: window_info.process_id,
-                        process_name: window_info.process_name,
-                        window_title: window_info.window_title.clone(),
-                        icon,
-                    };
-
-                    if let Err(e) = on_focus(focused_window).await {
-                        info!("Focus event handler failed: {}", e);
-                    }
-
-                    prev_hwnd = Some(current_hwnd_value);
-                    prev_title = window_info.window_title;
-                }
-            } else {
-                // No foreground window
-                if prev_hwnd.is_some() {
-                    prev_hwnd = None;
-                    prev_title = None;
-                    cached_icon = None;
-                }
-            }
-
-            // Sleep to avoid high CPU usage (async version)
-            tokio::time::sleep(config.poll_interval).await;
-        }
-
-        Ok(())
+        self.run(on_change, Some(stop_signal), config)
     }
 
     fn run<F>(
         &self,
-        mut on_focus: F,
-        stop_signal: Option<&AtomicBool>,
+        mut on_change: F,
+        stop_signal: Option<&StopSignal>,
         config: &FocusTrackerConfig,
     ) -> FerrousFocusResult<()>
     where
-        F: FnMut(FocusedWindow) -> FerrousFocusResult<()>,
+        F: FnMut(Change, Detection) -> FerrousFocusResult<()>,
     {
         // Check if we're in an interactive session
         if !utils::is_interactive_session()? {
@@ -197,29 +107,30 @@ impl ImplFocusTracker {
         }
 
         // Track the previously focused window to avoid duplicate events
-        // Store HWND as isize for consistency with async path
+        // Store HWND as isize so windows can be compared after they are gone
         let mut prev_hwnd: Option<isize> = None;
         let mut prev_title: Option<String> = None;
         // Cache the icon for the currently focused window (only fetch on app change)
-        let mut cached_icon: Option<image::RgbaImage> = None;
+        let mut cached_icon = WindowIcon::default();
 
         // Get initial focused window
+        let detection = Detection::now();
         if let Some(hwnd) = utils::get_foreground_window()
             && let Ok((title, process)) = unsafe { utils::get_window_info(hwnd) }
         {
-            // Initial window - fetch icon
-            let icon = get_window_icon(hwnd, &config.icon);
-            cached_icon = icon.clone();
-
             let process_id = unsafe { utils::get_window_process_id(hwnd) }.unwrap_or_default();
-            if let Err(e) = on_focus(FocusedWindow {
+            let mut window = FocusedWindow {
+                window_id: Some(window_id(hwnd as isize)),
                 process_id: Some(process_id),
                 process_name: Some(process.clone()),
                 window_title: Some(title.clone()),
-                icon,
-            }) {
-                info!("Focus event handler failed: {}", e);
-            }
+                ..Default::default()
+            };
+            // Initial window - fetch icon, unless the window is filtered
+            let load = icon_loader(hwnd as isize, &config.icon);
+            cached_icon = WindowIcon::fetch(&window, config, load);
+            cached_icon.apply_to(&mut window);
+            on_change(Change::Focus(window), detection)?;
 
             prev_hwnd = Some(hwnd as isize);
             prev_title = Some(title);
@@ -229,13 +140,12 @@ impl ImplFocusTracker {
         // with Rust's async runtime in a cross-platform way
         loop {
             // Check stop signal before processing
-            if let Some(stop) = stop_signal
-                && stop.load(Ordering::Relaxed)
-            {
+            if stop_signal.is_some_and(StopSignal::is_stopped) {
                 break;
             }
 
             // Check current foreground window
+            let detection = Detection::now();
             if let Some(current_hwnd) = utils::get_foreground_window() {
                 let current_hwnd_value = current_hwnd as isize;
                 let focus_changed = match prev_hwnd {
@@ -253,25 +163,28 @@ impl ImplFocusTracker {
 
                         // Trigger handler if either window focus or title has changed
                         if focus_changed || title_changed {
-                            // Only fetch icon when the focused app changes, not on title changes
-                            let icon = if focus_changed {
-                                let new_icon = get_window_icon(current_hwnd, &config.icon);
-                                cached_icon = new_icon.clone();
-                                new_icon
-                            } else {
-                                cached_icon.clone()
-                            };
-
                             let process_id = unsafe { utils::get_window_process_id(current_hwnd) }
                                 .unwrap_or_default();
-                            if let Err(e) = on_focus(FocusedWindow {
+                            let mut window = FocusedWindow {
+                                window_id: Some(window_id(current_hwnd_value)),
                                 process_id: Some(process_id),
                                 process_name: Some(process.clone()),
                                 window_title: Some(title.clone()),
-                                icon,
-                            }) {
-                                info!("Focus event handler failed: {}", e);
+                                ..Default::default()
+                            };
+                            // Only fetch icon when the focused app changes, not on title changes,
+                            // and never for filtered windows
+                            if focus_changed {
+                                let load = icon_loader(current_hwnd_value, &config.icon);
+                                cached_icon = WindowIcon::fetch(&window, config, load);
                             }
+                            cached_icon.apply_to(&mut window);
+                            let change = if focus_changed {
+                                Change::Focus(window)
+                            } else {
+                                Change::Update(window)
+                            };
+                            on_change(change, detection)?;
 
                             prev_hwnd = Some(current_hwnd_value);
                             prev_title = Some(title);
@@ -286,12 +199,15 @@ impl ImplFocusTracker {
                 if prev_hwnd.is_some() {
                     prev_hwnd = None;
                     prev_title = None;
-                    cached_icon = None;
+                    cached_icon = WindowIcon::default();
+                    on_change(Change::Cleared, detection)?;
                 }
             }
 
-            // Sleep to avoid high CPU usage
-            std::thread::sleep(config.poll_interval);
+            // Sleep to avoid high CPU usage, waking up early when stopped
+            if stop_signal::sleep(stop_signal, config.poll_interval) {
+                break;
+            }
         }
 
         Ok(())
@@ -302,38 +218,9 @@ impl ImplFocusTracker {
 /* Helper functions                                              */
 /* ------------------------------------------------------------ */
 
-/// Helper struct to hold window information with Send-safe types (without icon)
-struct WindowInfo {
-    hwnd_value: isize,
-    process_id: Option<u32>,
-    process_name: Option<String>,
-    window_title: Option<String>,
-}
-
-/// Get current focused window info without icon, returning only Send-safe types
-/// This helper ensures no HWND pointers leak into async contexts
-/// The icon should be fetched separately using `get_window_icon_by_hwnd` only when the focused app changes.
-fn get_window_info_without_icon() -> Option<WindowInfo> {
-    let hwnd = utils::get_foreground_window()?;
-    let hwnd_value = hwnd as isize;
-
-    let (title, process) = unsafe { utils::get_window_info(hwnd) }.ok()?;
-    let process_id = unsafe { utils::get_window_process_id(hwnd) }.unwrap_or_default();
-
-    Some(WindowInfo {
-        hwnd_value,
-        process_id: Some(process_id),
-        process_name: Some(process),
-        window_title: Some(title),
-    })
-}
-
-/// Get the icon for a window by HWND value (isize)
-fn get_window_icon_by_hwnd(
-    hwnd_value: isize,
-    icon_config: &crate::config::IconConfig,
-) -> Option<image::RgbaImage> {
-    get_window_icon(hwnd_value as HWND, icon_config)
+/// The ID of the window with the given HWND, which stays the same while the window exists.
+fn window_id(hwnd_value: isize) -> WindowId {
+    WindowId::from_number(hwnd_value as u64)
 }
 
 /// Resize an image to the specified dimensions using Lanczos3 filtering
@@ -350,12 +237,15 @@ fn resize_icon(
     image::imageops::resize(&image, target_size, target_size, filter_type)
 }
 
-/// Get the icon for a window
-fn get_window_icon(
-    hwnd: HWND,
-    icon_config: &crate::config::IconConfig,
-) -> Option<image::RgbaImage> {
-    unsafe { extract_window_icon(hwnd, icon_config).ok() }
+/// Fetch the icon of the window with the given HWND whenever called, e.g. later by an
+/// [`IconHandle`](crate::IconHandle).
+fn icon_loader(
+    hwnd_value: isize,
+    icon_config: &IconConfig,
+) -> impl Fn() -> FerrousFocusResult<image::RgbaImage> + Send + Sync + 'static {
+    let icon_config = icon_config.clone();
+    // A window that is gone has no icon to send, which fails the extraction
+    move || unsafe { extract_window_icon(hwnd_value as HWND, &icon_config) }
 }
 
 /// Extract the icon bitmap from a window handle
@@ -364,7 +254,7 @@ fn get_window_icon(
 /// This function uses unsafe Win32 API calls and assumes the HWND is valid
 unsafe fn extract_window_icon(
     hwnd: HWND,
-    icon_config: &crate::config::IconConfig,
+    icon_config: &IconConfig,
 ) -> FerrousFocusResult<image::RgbaImage> {
     use windows_sys::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, ICONINFO};
 
@@ -1,11 +1,15 @@
 use crate::{FerrousFocusError, FerrousFocusResult};
 use std::ffi::OsString;
 use std::os::windows::ffi::OsStringExt;
+use std::path::PathBuf;
 use windows_sys::Win32::{
     Foundation::{CloseHandle, HWND},
     System::{
         ProcessStatus::GetModuleBaseNameW,
-        Threading::{OpenProcess, PROCESS_QUERY_INFORMATION, PROCESS_VM_READ},
+        Threading::{
+            OpenProcess, PROCESS_NAME_WIN32, PROCESS_QUERY_INFORMATION,
+            PROCESS_QUERY_LIMITED_INFORMATION, PROCESS_VM_READ, QueryFullProcessImageNameW,
+        },
     },
     UI::WindowsAndMessaging::{
         GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsWindow,
@@ -108,6 +112,32 @@ pub fn get_process_name(process_id: u32) -> FerrousFocusResult<String> {
     Ok(name)
 }
 
+/// Get the full path of the executable a process runs
+pub(crate) fn executable_path(process_id: u32) -> Option<PathBuf> {
+    let process_handle = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, process_id) };
+    if process_handle.is_null() {
+        return None;
+    }
+
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    let succeeded = unsafe {
+        QueryFullProcessImageNameW(
+            process_handle,
+            PROCESS_NAME_WIN32,
+            buffer.as_mut_ptr(),
+            &mut len,
+        )
+    };
+
+    // Close the process handle
+    unsafe {
+        CloseHandle(process_handle);
+    }
+
+    (succeeded != 0).then(|| PathBuf::from(OsString::from_wide(&buffer[..len as usize])))
+}
+
 /// Get window information (title and process name) for a given window handle
 ///
 /// # Safety
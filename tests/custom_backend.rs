@@ -0,0 +1,981 @@
+//! Custom backend tests
+//!
+//! These tests verify that the tracker machinery runs on top of a backend provided by the
+//! application. They need no display and always run.
+
+use ferrous_focus::{
+    Capabilities, ConfigError, ErrorPolicy, FerrousFocusError, FerrousFocusResult, FocusBackend,
+    FocusEmitter, FocusEvent, FocusFilter, FocusTracker, FocusTrackerConfig, FocusedWindow,
+    Pattern, WindowRule,
+};
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Emits a fixed list of windows, then idles until asked to stop.
+struct ScriptedBackend {
+    titles: Vec<&'static str>,
+}
+
+impl FocusBackend for ScriptedBackend {
+    fn name(&self) -> &str {
+        "scripted"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            titles: true,
+            ..Capabilities::default()
+        }
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        for title in &self.titles {
+            emitter.emit(FocusedWindow {
+                window_title: Some(title.to_string()),
+                ..Default::default()
+            });
+        }
+
+        while !emitter.sleep(config.poll_interval) {}
+        Ok(())
+    }
+}
+
+/// Emits one window, then fails like a backend that lost its display connection.
+struct FailingBackend;
+
+impl FocusBackend for FailingBackend {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        _config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        emitter.emit(FocusedWindow {
+            window_title: Some("Editor".to_string()),
+            ..Default::default()
+        });
+        Err(FerrousFocusError::Platform("Display went away".to_string()))
+    }
+}
+
+/// Focuses a window, renames it, then leaves nothing focused and idles until asked to stop.
+struct RenamingBackend;
+
+impl FocusBackend for RenamingBackend {
+    fn name(&self) -> &str {
+        "renaming"
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        let window = |title: &str| FocusedWindow {
+            window_title: Some(title.to_string()),
+            ..Default::default()
+        };
+        emitter.emit(window("Editor"));
+        // Unchanged windows are not reported as events
+        emitter.emit_update(window("Editor"));
+        emitter.emit_update(window("Editor*"));
+        emitter.emit_cleared();
+
+        while !emitter.sleep(config.poll_interval) {}
+        Ok(())
+    }
+}
+
+/// Focuses a window, then reports a new icon for it and idles until asked to stop.
+struct IconChangingBackend;
+
+impl FocusBackend for IconChangingBackend {
+    fn name(&self) -> &str {
+        "icon-changing"
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        let window = |icon_size: u32| FocusedWindow {
+            window_title: Some("Mail".to_string()),
+            icon: Some(Arc::new(image::RgbaImage::new(icon_size, icon_size))),
+            ..Default::default()
+        };
+        emitter.emit(window(16));
+        emitter.emit_icon(window(32));
+
+        while !emitter.sleep(config.poll_interval) {}
+        Ok(())
+    }
+}
+
+/// Focuses the first window and lets focus rest, then flicks through the other windows at
+/// once, lets focus rest again and stops.
+struct FlappingBackend {
+    titles: Vec<&'static str>,
+}
+
+impl FocusBackend for FlappingBackend {
+    fn name(&self) -> &str {
+        "flapping"
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        _config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        let rest = Duration::from_millis(200);
+        let (first, others) = self.titles.split_first().expect("a window to focus");
+        for titles in [std::slice::from_ref(first), others] {
+            for title in titles {
+                emitter.emit(FocusedWindow {
+                    window_title: Some(title.to_string()),
+                    window_class: Some(title.to_lowercase()),
+                    ..Default::default()
+                });
+            }
+            emitter.sleep(rest);
+        }
+        Ok(())
+    }
+}
+
+/// Runs a [`ScriptedBackend`] and records when it returned.
+#[cfg(feature = "async")]
+struct ExitRecordingBackend {
+    exited: Arc<AtomicBool>,
+}
+
+#[cfg(feature = "async")]
+impl FocusBackend for ExitRecordingBackend {
+    fn name(&self) -> &str {
+        "exit-recording"
+    }
+
+    fn run(
+        &self,
+        emitter: &mut FocusEmitter<'_>,
+        config: &FocusTrackerConfig,
+    ) -> FerrousFocusResult<()> {
+        let backend = ScriptedBackend {
+            titles: vec!["Editor", "Browser"],
+        };
+        let result = backend.run(emitter, config);
+        self.exited.store(true, Ordering::Release);
+        result
+    }
+}
+
+fn scripted_tracker() -> FocusTracker {
+    scripted_tracker_polling_every(10)
+}
+
+fn scripted_tracker_polling_every(poll_interval_ms: u64) -> FocusTracker {
+    FocusTracker::from_backend(
+        ScriptedBackend {
+            titles: vec!["Editor", "Browser"],
+        },
+        FocusTrackerConfig::new().with_poll_interval_ms(poll_interval_ms),
+    )
+}
+
+fn titles(windows: &[FocusedWindow]) -> Vec<&str> {
+    windows
+        .iter()
+        .filter_map(|window| window.window_title.as_deref())
+        .collect()
+}
+
+#[test]
+fn test_custom_backend_with_stop() {
+    let tracker = scripted_tracker();
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let stop_signal = Arc::new(AtomicBool::new(false));
+
+    let handle = {
+        let windows = Arc::clone(&windows);
+        let stop_signal = Arc::clone(&stop_signal);
+        thread::spawn(move || {
+            tracker.track_focus_with_stop(
+                |window| {
+                    windows.lock()?.push(window);
+                    Ok(())
+                },
+                &stop_signal,
+            )
+        })
+    };
+
+    while windows.lock().unwrap().len() < 2 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    stop_signal.store(true, Ordering::Release);
+    assert!(handle.join().unwrap().is_ok());
+
+    assert_eq!(titles(&windows.lock().unwrap()), ["Editor", "Browser"]);
+}
+
+#[test]
+fn test_spawned_tracker_stops_immediately() {
+    // The longest poll interval allowed
+    let tracker = scripted_tracker_polling_every(10_000);
+    let windows = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = {
+        let windows = Arc::clone(&windows);
+        tracker
+            .spawn(move |window| {
+                windows.lock()?.push(window);
+                Ok(())
+            })
+            .unwrap()
+    };
+
+    while windows.lock().unwrap().len() < 2 {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(handle.is_running());
+
+    let stopped_at = Instant::now();
+    handle.stop();
+    assert!(handle.join().is_ok());
+    assert!(stopped_at.elapsed() < Duration::from_secs(5));
+
+    assert_eq!(titles(&windows.lock().unwrap()), ["Editor", "Browser"]);
+}
+
+#[test]
+fn test_dropping_handle_stops_tracker() {
+    let tracker = scripted_tracker_polling_every(10_000);
+    let (sender, receiver) = mpsc::channel();
+
+    let handle = tracker
+        .spawn(move |window| {
+            sender
+                .send(window)
+                .map_err(|_| FerrousFocusError::new("Receiver dropped"))
+        })
+        .unwrap();
+    receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+    drop(handle);
+
+    // The handler, and with it the sender, is dropped once the tracker thread ends
+    let error = loop {
+        match receiver.recv_timeout(Duration::from_secs(5)) {
+            Ok(_) => continue,
+            Err(e) => break e,
+        }
+    };
+    assert_eq!(error, RecvTimeoutError::Disconnected);
+}
+
+#[test]
+fn test_custom_backend_subscription() {
+    let tracker = scripted_tracker();
+    assert_eq!(tracker.active_backend(), None);
+
+    let receiver = tracker.subscribe_focus_changes().unwrap();
+    let windows: Vec<_> = (0..2)
+        .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect();
+    assert_eq!(titles(&windows), ["Editor", "Browser"]);
+}
+
+#[test]
+fn test_unsubscribe_stops_immediately() {
+    let tracker = scripted_tracker_polling_every(10_000);
+    let subscription = tracker.subscribe_focus_changes().unwrap();
+    let windows: Vec<_> = (0..2)
+        .map(|_| subscription.recv_timeout(Duration::from_secs(5)).unwrap())
+        .collect();
+    assert_eq!(titles(&windows), ["Editor", "Browser"]);
+    assert!(subscription.is_active());
+
+    let unsubscribed_at = Instant::now();
+    assert!(subscription.unsubscribe().is_ok());
+    assert!(unsubscribed_at.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_subscription_surfaces_backend_error() {
+    let tracker = FocusTracker::from_backend(FailingBackend, FocusTrackerConfig::new());
+    let subscription = tracker.subscribe_focus_changes().unwrap();
+
+    let windows: Vec<_> = subscription.iter().collect();
+    assert_eq!(titles(&windows), ["Editor"]);
+    assert!(matches!(
+        subscription.unsubscribe(),
+        Err(FerrousFocusError::Platform(message)) if message == "Display went away"
+    ));
+}
+
+#[test]
+fn test_invalid_config_is_rejected_before_start() {
+    let mut config = FocusTrackerConfig::new();
+    config.poll_interval = Duration::ZERO;
+    let tracker = FocusTracker::from_backend(FailingBackend, config);
+
+    let mut windows = Vec::new();
+    let result = tracker.track_focus(|window| {
+        windows.push(window);
+        Ok(())
+    });
+    assert!(matches!(
+        result,
+        Err(FerrousFocusError::Config(ConfigError::ZeroPollInterval))
+    ));
+    assert!(windows.is_empty());
+    assert!(matches!(
+        tracker.current(),
+        Err(FerrousFocusError::Config(ConfigError::ZeroPollInterval))
+    ));
+}
+
+#[test]
+fn test_stop_policy_returns_handler_error() {
+    for debounce_ms in [0, 20] {
+        let tracker = FocusTracker::from_backend(
+            ScriptedBackend {
+                titles: vec!["Editor", "Browser"],
+            },
+            FocusTrackerConfig::new()
+                .with_debounce_ms(debounce_ms)
+                .with_error_policy(ErrorPolicy::Stop),
+        );
+
+        let mut windows = Vec::new();
+        let result = tracker.track_focus(|window| {
+            windows.push(window);
+            Err(FerrousFocusError::new("Database is locked"))
+        });
+        assert!(matches!(
+            result,
+            Err(FerrousFocusError::Error(message)) if message == "Database is locked"
+        ));
+        assert_eq!(windows.len(), 1);
+    }
+}
+
+#[test]
+fn test_sink_policy_collects_errors() {
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let sink = {
+        let errors = Arc::clone(&errors);
+        ErrorPolicy::sink(move |e| errors.lock().unwrap().push(e.to_string()))
+    };
+    let tracker = FocusTracker::from_backend(
+        ScriptedBackend {
+            titles: vec!["Editor", "Browser"],
+        },
+        FocusTrackerConfig::new().with_error_policy(sink.clone()),
+    );
+
+    let stop_signal = AtomicBool::new(false);
+    let result = tracker.track_focus_with_stop(
+        |window| {
+            if window.window_title.as_deref() == Some("Browser") {
+                stop_signal.store(true, Ordering::Release);
+            }
+            Err(FerrousFocusError::new(format!(
+                "Cannot log {:?}",
+                window.window_title
+            )))
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+    assert_eq!(
+        *errors.lock().unwrap(),
+        [
+            r#"Cannot log Some("Editor")"#,
+            r#"Cannot log Some("Browser")"#
+        ]
+    );
+
+    // Background trackers also pass on the error they end with
+    errors.lock().unwrap().clear();
+    let tracker = FocusTracker::from_backend(
+        FailingBackend,
+        FocusTrackerConfig::new().with_error_policy(sink),
+    );
+    let subscription = tracker.subscribe_focus_changes().unwrap();
+    assert_eq!(subscription.iter().count(), 1);
+    assert!(subscription.unsubscribe().is_err());
+    assert_eq!(
+        *errors.lock().unwrap(),
+        ["Platform error: Display went away"]
+    );
+}
+
+#[test]
+fn test_current_reports_first_window() {
+    // Polls rarely: the query must not wait for the backend's next poll
+    let tracker = scripted_tracker_polling_every(10_000);
+    let started_at = Instant::now();
+    let window = tracker.current().unwrap();
+    assert_eq!(window.unwrap().window_title.as_deref(), Some("Editor"));
+    assert!(started_at.elapsed() < Duration::from_secs(5));
+    assert_eq!(tracker.active_backend(), None);
+
+    let mut query = tracker.query().unwrap();
+    assert_eq!(query.backend(), None);
+    for _ in 0..2 {
+        let window = query.current().unwrap().unwrap();
+        assert_eq!(window.window_title.as_deref(), Some("Editor"));
+    }
+}
+
+#[test]
+fn test_filter_drops_or_anonymizes_windows() {
+    let filter = FocusFilter::new().exclude(WindowRule::title(Pattern::exact("Editor")));
+    let track = |filter: FocusFilter| {
+        let tracker = FocusTracker::from_backend(
+            ScriptedBackend {
+                titles: vec!["Editor", "Browser"],
+            },
+            FocusTrackerConfig::new().with_filter(filter),
+        );
+        let stop_signal = AtomicBool::new(false);
+        let mut windows = Vec::new();
+        let result = tracker.track_focus_with_stop(
+            |window| {
+                if window.window_title.as_deref() == Some("Browser") {
+                    stop_signal.store(true, Ordering::Release);
+                }
+                windows.push(window);
+                Ok(())
+            },
+            &stop_signal,
+        );
+        assert!(result.is_ok());
+        (windows, tracker.current().unwrap())
+    };
+
+    let (windows, current) = track(filter.clone());
+    assert_eq!(titles(&windows), ["Browser"]);
+    assert_eq!(current, None);
+
+    let (windows, current) = track(filter.report_filtered());
+    assert_eq!(windows[0], FocusedWindow::filtered());
+    assert_eq!(titles(&windows), ["Browser"]);
+    assert_eq!(current, Some(FocusedWindow::filtered()));
+}
+
+#[test]
+fn test_current_keeps_window_reported_before_failure() {
+    let tracker = FocusTracker::from_backend(FailingBackend, FocusTrackerConfig::new());
+    assert_eq!(
+        tracker.current().unwrap().unwrap().window_title.as_deref(),
+        Some("Editor")
+    );
+}
+
+#[test]
+fn test_track_events_reports_what_changed() {
+    let tracker = FocusTracker::from_backend(RenamingBackend, FocusTrackerConfig::new());
+    let stop_signal = AtomicBool::new(false);
+    let mut events = Vec::new();
+
+    let result = tracker.track_events_with_stop(
+        |event| {
+            if matches!(event, FocusEvent::FocusCleared { .. }) {
+                stop_signal.store(true, Ordering::Release);
+            }
+            events.push(event);
+            Ok(())
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+
+    let [
+        FocusEvent::BackendStarted { backend, .. },
+        FocusEvent::FocusChanged {
+            previous: None,
+            current: editor,
+            ..
+        },
+        FocusEvent::TitleChanged {
+            previous, current, ..
+        },
+        FocusEvent::FocusCleared {
+            previous: last_focused,
+            meta: cleared,
+        },
+        FocusEvent::BackendStopped { .. },
+    ] = &events[..]
+    else {
+        panic!("unexpected events: {events:?}");
+    };
+    assert_eq!(backend, "renaming");
+    assert_eq!(previous, editor);
+    assert_eq!(current.window_title.as_deref(), Some("Editor*"));
+    assert_eq!(last_focused, current);
+
+    let sequence: Vec<_> = events.iter().map(|event| event.meta().sequence).collect();
+    assert_eq!(sequence, [0, 1, 2, 3, 4]);
+    assert!(
+        events
+            .windows(2)
+            .all(|pair| pair[0].meta().instant <= pair[1].meta().instant)
+    );
+    assert_eq!(events[0].meta().server_time, None);
+    assert!(cleared.previous_focus_duration.is_some());
+    assert_eq!(events[2].meta().previous_focus_duration, None);
+
+    // Clones of the tracker continue the sequence
+    let stop_signal = AtomicBool::new(false);
+    let mut first = None;
+    let result = tracker.clone().track_events_with_stop(
+        |event| {
+            first.get_or_insert(event.meta().sequence);
+            stop_signal.store(true, Ordering::Release);
+            Ok(())
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+    assert_eq!(first, Some(5));
+}
+
+#[test]
+fn test_events_carry_sequence_and_focus_duration() {
+    let tracker = FocusTracker::from_backend(
+        FlappingBackend {
+            titles: vec!["Editor", "Browser"],
+        },
+        FocusTrackerConfig::new(),
+    );
+    let stop_signal = AtomicBool::new(false);
+    let mut events = Vec::new();
+
+    let result = tracker.track_events_with_stop(
+        |event| {
+            if let FocusEvent::FocusChanged { current, .. } = &event
+                && current.window_title.as_deref() == Some("Browser")
+            {
+                stop_signal.store(true, Ordering::Release);
+            }
+            events.push(event);
+            Ok(())
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+
+    let [
+        FocusEvent::BackendStarted { .. },
+        FocusEvent::FocusChanged { meta: first, .. },
+        FocusEvent::FocusChanged { meta: second, .. },
+        FocusEvent::BackendStopped { .. },
+    ] = &events[..]
+    else {
+        panic!("unexpected events: {events:?}");
+    };
+
+    // One sequence number per delivered event
+    let sequence: Vec<_> = events.iter().map(|event| event.meta().sequence).collect();
+    assert_eq!(sequence, [0, 1, 2, 3]);
+
+    // Nothing was focused before the first window; the second one ends the focus of the first
+    assert_eq!(first.previous_focus_duration, None);
+    let duration = second.previous_focus_duration.unwrap();
+    assert!(duration >= Duration::from_millis(200), "{duration:?}");
+    assert_eq!(duration, second.instant - first.instant);
+}
+
+#[test]
+fn test_custom_backend_reports_icon_changes() {
+    let tracker = FocusTracker::from_backend(IconChangingBackend, FocusTrackerConfig::new());
+    let stop_signal = AtomicBool::new(false);
+    let mut events = Vec::new();
+
+    let result = tracker.track_events_with_stop(
+        |event| {
+            if matches!(event, FocusEvent::IconChanged { .. }) {
+                stop_signal.store(true, Ordering::Release);
+            }
+            events.push(event);
+            Ok(())
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+
+    let [
+        FocusEvent::BackendStarted { .. },
+        FocusEvent::FocusChanged { .. },
+        FocusEvent::IconChanged {
+            previous, current, ..
+        },
+        FocusEvent::BackendStopped { .. },
+    ] = &events[..]
+    else {
+        panic!("unexpected events: {events:?}");
+    };
+    assert_eq!(previous.icon.as_ref().map(|icon| icon.width()), Some(16));
+    assert_eq!(current.icon.as_ref().map(|icon| icon.width()), Some(32));
+}
+
+#[test]
+fn test_window_handlers_skip_cleared_focus() {
+    let tracker = FocusTracker::from_backend(RenamingBackend, FocusTrackerConfig::new());
+    let stop_signal = AtomicBool::new(false);
+    let mut windows = Vec::new();
+
+    let result = tracker.track_focus_with_stop(
+        |window| {
+            windows.push(window);
+            if windows.len() == 2 {
+                stop_signal.store(true, Ordering::Release);
+            }
+            Ok(())
+        },
+        &stop_signal,
+    );
+    assert!(result.is_ok());
+    // The update that repeats the window is coalesced
+    assert_eq!(titles(&windows), ["Editor", "Editor*"]);
+    assert_eq!(tracker.suppressed_events(), 1);
+}
+
+#[test]
+fn test_debounce_reports_window_focus_rested_on() {
+    let tracker = FocusTracker::from_backend(
+        FlappingBackend {
+            titles: vec!["Terminal", "Browser", "Editor"],
+        },
+        FocusTrackerConfig::new().with_debounce_ms(30),
+    );
+    let mut windows = Vec::new();
+
+    let result = tracker.track_focus(|window| {
+        windows.push(window);
+        Ok(())
+    });
+    assert!(result.is_ok());
+    // Browser had focus too briefly to be reported
+    assert_eq!(titles(&windows), ["Terminal", "Editor"]);
+    assert_eq!(tracker.suppressed_events(), 1);
+}
+
+#[test]
+fn test_debounce_drops_focus_that_comes_back() {
+    let tracker = FocusTracker::from_backend(
+        FlappingBackend {
+            titles: vec!["Editor", "Browser", "Editor"],
+        },
+        FocusTrackerConfig::new().with_debounce_ms(30),
+    );
+    let mut events = Vec::new();
+
+    let result = tracker.track_events(|event| {
+        events.push(event);
+        Ok(())
+    });
+    assert!(result.is_ok());
+
+    let [
+        FocusEvent::BackendStarted { .. },
+        FocusEvent::FocusChanged {
+            previous: None,
+            current,
+            ..
+        },
+        FocusEvent::BackendStopped { .. },
+    ] = &events[..]
+    else {
+        panic!("unexpected events: {events:?}");
+    };
+    assert_eq!(current.window_title.as_deref(), Some("Editor"));
+    assert_eq!(tracker.suppressed_events(), 2);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_custom_backend_async() {
+    let tracker = scripted_tracker();
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let stop_signal = Arc::new(AtomicBool::new(false));
+
+    let stopper = {
+        let windows = Arc::clone(&windows);
+        let stop_signal = Arc::clone(&stop_signal);
+        tokio::spawn(async move {
+            while windows.lock().unwrap().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            stop_signal.store(true, Ordering::Release);
+        })
+    };
+
+    let result = tracker
+        .track_focus_async_with_stop(
+            |window| {
+                let windows = Arc::clone(&windows);
+                async move {
+                    windows.lock()?.push(window);
+                    Ok(())
+                }
+            },
+            &stop_signal,
+        )
+        .await;
+    stopper.await.unwrap();
+
+    assert!(result.is_ok());
+    assert_eq!(titles(&windows.lock().unwrap()), ["Editor", "Browser"]);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_stop_policy_ends_async_tracking() {
+    let tracker = FocusTracker::from_backend(
+        ScriptedBackend {
+            titles: vec!["Editor", "Browser"],
+        },
+        FocusTrackerConfig::new().with_error_policy(ErrorPolicy::Stop),
+    );
+
+    let result = tracker
+        .track_focus_async(|_| async { Err(FerrousFocusError::new("Database is locked")) })
+        .await;
+    assert!(matches!(
+        result,
+        Err(FerrousFocusError::Error(message)) if message == "Database is locked"
+    ));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_dropping_async_tracking_stops_backend() {
+    let exited = Arc::new(AtomicBool::new(false));
+    let tracker = FocusTracker::from_backend(
+        ExitRecordingBackend {
+            exited: Arc::clone(&exited),
+        },
+        FocusTrackerConfig::new().with_poll_interval_ms(10),
+    )
+    // A plain thread, which the test runtime does not wait for if the backend is leaked
+    .with_async_runtime(ferrous_focus::ThreadRuntime);
+
+    // The timeout drops the tracking future while the backend idles
+    let tracking = tracker.track_focus_async(|_| async { Ok(()) });
+    let result = tokio::time::timeout(Duration::from_millis(100), tracking).await;
+    assert!(result.is_err());
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !exited.load(Ordering::Acquire) {
+        assert!(Instant::now() < deadline, "backend kept running");
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+}
+
+/// A runtime that cannot start backends, like one out of threads.
+#[cfg(feature = "async")]
+struct ExhaustedRuntime;
+
+#[cfg(feature = "async")]
+impl ferrous_focus::AsyncRuntime for ExhaustedRuntime {
+    fn spawn_blocking(&self, _task: Box<dyn FnOnce() + Send + 'static>) -> FerrousFocusResult<()> {
+        Err(FerrousFocusError::new("Resource temporarily unavailable"))
+    }
+
+    fn sleep(&self, duration: Duration) -> futures::future::BoxFuture<'static, ()> {
+        ferrous_focus::ThreadRuntime.sleep(duration)
+    }
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_async_tracking_fails_when_backend_cannot_start() {
+    let tracker = scripted_tracker().with_async_runtime(ExhaustedRuntime);
+    let result = futures::executor::block_on(tracker.track_focus_async(|_| async { Ok(()) }));
+    assert!(matches!(
+        result,
+        Err(FerrousFocusError::Error(message)) if message == "Resource temporarily unavailable"
+    ));
+}
+
+/// Track until two windows arrived, with the tracker's async runtime left to `tracker`.
+#[cfg(feature = "async")]
+async fn track_two_windows(tracker: FocusTracker) -> Vec<FocusedWindow> {
+    let windows = Arc::new(Mutex::new(Vec::new()));
+    let stop_signal = Arc::new(AtomicBool::new(false));
+
+    let result = tracker
+        .track_focus_async_with_stop(
+            |window| {
+                let windows = Arc::clone(&windows);
+                let stop_signal = Arc::clone(&stop_signal);
+                async move {
+                    let mut windows = windows.lock()?;
+                    windows.push(window);
+                    if windows.len() == 2 {
+                        stop_signal.store(true, Ordering::Release);
+                    }
+                    Ok(())
+                }
+            },
+            &stop_signal,
+        )
+        .await;
+
+    assert!(result.is_ok());
+    Arc::try_unwrap(windows).unwrap().into_inner().unwrap()
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_custom_backend_async_without_executor() {
+    let tracker = scripted_tracker().with_async_runtime(ferrous_focus::ThreadRuntime);
+    let windows = futures::executor::block_on(track_two_windows(tracker));
+    assert_eq!(titles(&windows), ["Editor", "Browser"]);
+}
+
+#[cfg(feature = "smol")]
+#[test]
+fn test_custom_backend_async_on_smol() {
+    let tracker = scripted_tracker().with_async_runtime(ferrous_focus::SmolRuntime);
+    let windows = smol::block_on(track_two_windows(tracker));
+    assert_eq!(titles(&windows), ["Editor", "Browser"]);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_hub_async_subscriber() {
+    let hub = ferrous_focus::FocusHub::from_tracker(scripted_tracker());
+    let mut subscription = hub.subscribe_async().unwrap();
+
+    let mut windows = Vec::new();
+    for _ in 0..2 {
+        let window = tokio::time::timeout(Duration::from_secs(5), subscription.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        windows.push(window);
+    }
+    assert_eq!(titles(&windows), ["Editor", "Browser"]);
+
+    drop(subscription);
+    assert!(!hub.is_running());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_stream_yields_focus_changes() {
+    use futures::StreamExt;
+
+    let mut stream = scripted_tracker().stream();
+    let started = tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap();
+    assert!(matches!(
+        started,
+        Some(Ok(FocusEvent::BackendStarted { backend, .. })) if backend == "scripted"
+    ));
+
+    let mut next = async || match tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .unwrap()
+    {
+        Some(Ok(FocusEvent::FocusChanged {
+            previous, current, ..
+        })) => (previous, current),
+        other => panic!("unexpected stream item: {other:?}"),
+    };
+
+    let (previous, editor) = next().await;
+    assert_eq!(previous, None);
+    let (previous, browser) = next().await;
+    assert_eq!(previous, Some(editor));
+    assert_eq!(browser.window_title.as_deref(), Some("Browser"));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_stream_ends_with_backend_error() {
+    use futures::StreamExt;
+
+    let tracker = FocusTracker::from_backend(FailingBackend, FocusTrackerConfig::new());
+    let items: Vec<_> = tokio::time::timeout(Duration::from_secs(5), tracker.stream().collect())
+        .await
+        .unwrap();
+
+    assert_eq!(items.len(), 3);
+    assert!(matches!(&items[0], Ok(FocusEvent::BackendStarted { .. })));
+    assert!(matches!(&items[1], Ok(FocusEvent::FocusChanged { .. })));
+    assert!(matches!(&items[2], Err(FerrousFocusError::Platform(_))));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_stream_applies_backpressure() {
+    use futures::StreamExt;
+
+    /// Emits as fast as it can and counts what got through.
+    struct FloodBackend {
+        emitted: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl FocusBackend for FloodBackend {
+        fn name(&self) -> &str {
+            "flood"
+        }
+
+        fn run(
+            &self,
+            emitter: &mut FocusEmitter<'_>,
+            _config: &FocusTrackerConfig,
+        ) -> FerrousFocusResult<()> {
+            for i in 0..1000 {
+                if emitter.should_stop() {
+                    break;
+                }
+                emitter.emit(FocusedWindow {
+                    window_title: Some(format!("Window {i}")),
+                    ..Default::default()
+                });
+                self.emitted.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    let emitted = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let tracker = FocusTracker::from_backend(
+        FloodBackend {
+            emitted: Arc::clone(&emitted),
+        },
+        FocusTrackerConfig::new(),
+    );
+
+    let mut stream = tracker.stream();
+    assert!(stream.next().await.unwrap().is_ok());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The backend is held back while nobody polls the stream
+    let while_idle = emitted.load(Ordering::SeqCst);
+    assert!(while_idle < 100, "{while_idle} events emitted while idle");
+
+    drop(stream);
+}
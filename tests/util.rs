@@ -175,51 +175,7 @@ pub fn wait_for_focus(expected_title: &str, timeout: Duration) -> bool {
 /// Get the currently focused window (for testing purposes)
 fn get_current_focused_window() -> Result<ferrous_focus::FocusedWindow, Box<dyn std::error::Error>>
 {
-    // This is a simplified version for testing
-    // In a real implementation, this would use the actual focus tracking logic
-
-    #[cfg(target_os = "linux")]
-    {
-        get_focused_window_linux()
-    }
-
-    #[cfg(not(target_os = "linux"))]
-    {
-        // Placeholder for other platforms
-        Ok(ferrous_focus::FocusedWindow {
-            process_id: None,
-            process_name: Some("unknown".to_string()),
-            window_title: Some("unknown".to_string()),
-            icon: None,
-        })
-    }
-}
-
-#[cfg(target_os = "linux")]
-fn get_focused_window_linux() -> Result<ferrous_focus::FocusedWindow, Box<dyn std::error::Error>> {
-    use std::process::Command;
-
-    // Try to get the focused window using xdotool
-    if let Ok(output) = Command::new("xdotool")
-        .args(["getwindowfocus", "getwindowname"])
-        .output()
-    {
-        let title = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        return Ok(ferrous_focus::FocusedWindow {
-            process_id: None,
-            process_name: None,
-            window_title: Some(title),
-            icon: None,
-        });
-    }
-
-    // Fallback
-    Ok(ferrous_focus::FocusedWindow {
-        process_id: None,
-        process_name: Some("unknown".to_string()),
-        window_title: Some("unknown".to_string()),
-        icon: None,
-    })
+    ferrous_focus::current_focused_window()?.ok_or_else(|| "No window is focused".into())
 }
 
 /// Check if integration tests should run
@@ -295,6 +251,7 @@ pub fn get_focused_window() -> ferrous_focus::FocusedWindow {
         process_name: Some("unknown".to_string()),
         window_title: Some("unknown".to_string()),
         icon: None,
+        ..Default::default()
     })
 }
 